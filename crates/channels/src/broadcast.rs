@@ -55,6 +55,16 @@ impl<E: Send + 'static> Broadcast<E> {
         self.deliver_pending_messages();
     }
 
+    /// subscriber_count returns how many subscribers are still receiving.
+    pub fn subscriber_count(&self) -> usize {
+        let subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .iter()
+            .flatten()
+            .filter(|sub| !sub.is_closed())
+            .count()
+    }
+
     pub fn subscribe(&mut self) -> mspc::ReceiveChannel<sync::Arc<E>> {
         let (sender, receiver) = mspc::create::<sync::Arc<E>>();
         self.add_and_deliver_pending_messages(sender);
@@ -187,4 +197,17 @@ mod tests {
         assert!(!subscriber2.is_empty().unwrap());
         assert!(matches!(subscriber.is_empty(), Err(_)));
     }
+
+    #[test]
+    fn broadcast_counts_only_open_subscribers() {
+        let mut broadcaster = broadcast::create::<String>(5);
+        assert_eq!(broadcaster.subscriber_count(), 0);
+
+        let mut subscriber = broadcaster.subscribe();
+        let _subscriber2 = broadcaster.subscribe();
+        assert_eq!(broadcaster.subscriber_count(), 2);
+
+        subscriber.close();
+        assert_eq!(broadcaster.subscriber_count(), 1);
+    }
 }
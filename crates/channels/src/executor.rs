@@ -4,11 +4,16 @@ use async_channel;
 use futures::{
     future,
     task::{waker_ref, ArcWake},
-    Future,
+    Future, StreamExt,
 };
 use std::{
-    sync::{self, Arc},
-    task::Context,
+    pin::Pin,
+    sync::{
+        self,
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
     usize,
 };
 use thiserror::Error;
@@ -25,6 +30,9 @@ struct Task<E: Send + 'static> {
     // woken up. Basically we just send it back into the channel for reprocessing.
     task_sender: async_channel::Sender<Arc<Task<E>>>,
     ready_notification: async_channel::Sender<()>,
+
+    // count of tasks not yet completed, shared with the executor.
+    live_tasks: Arc<AtomicUsize>,
 }
 
 impl<E: Send + 'static> ArcWake for Task<E> {
@@ -50,10 +58,12 @@ pub fn create<E: Send + 'static>() -> (ExecutionService<E>, Executor<E>) {
         ExecutionService {
             completed_notification: task_completed_receiver,
             receiver,
+            ready_listener: None,
         },
         Executor {
             completed_notification: task_completed_sender,
             sender,
+            live_tasks: Arc::new(AtomicUsize::new(0)),
         },
     )
 }
@@ -75,11 +85,18 @@ pub type ExecutorResult<E> = anyhow::Result<E, ExecutorError>;
 pub struct ExecutionService<E: Send + 'static> {
     completed_notification: async_channel::Receiver<()>,
     receiver: async_channel::Receiver<Arc<Task<E>>>,
+
+    // pinned clone of the completed notification, polled for readiness.
+    ready_listener: Option<Pin<Box<async_channel::Receiver<()>>>>,
 }
 
 impl<E: Send + 'static> Drop for ExecutionService<E> {
     fn drop(&mut self) {
-        self.close();
+        // only the last clone closes the shared channels, others
+        // are still serving the same tasks.
+        if self.receiver.receiver_count() == 1 {
+            self.close();
+        }
     }
 }
 
@@ -88,6 +105,7 @@ impl<E: Send + 'static> Clone for ExecutionService<E> {
         Self {
             receiver: self.receiver.clone(),
             completed_notification: self.completed_notification.clone(),
+            ready_listener: None,
         }
     }
 }
@@ -102,6 +120,32 @@ impl<E: Send + 'static> ExecutionService<E> {
         self.completed_notification.clone()
     }
 
+    /// [`ExecutionService::has_pending_tasks`] returns true if tasks are queued
+    /// and waiting to be served.
+    pub fn has_pending_tasks(&self) -> bool {
+        !self.receiver.is_empty()
+    }
+
+    /// [`ExecutionService::poll_ready`] resolves once tasks are queued or a pending
+    /// task was woken, else registers the context's waker to be notified when
+    /// a pending task gets woken.
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let notification = &self.completed_notification;
+        let listener = self
+            .ready_listener
+            .get_or_insert_with(|| Box::pin(notification.clone()));
+
+        let mut woken = false;
+        while let Poll::Ready(Some(())) = listener.poll_next_unpin(cx) {
+            woken = true;
+        }
+
+        if woken || self.has_pending_tasks() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+
     pub async fn schedule_serve_async(&mut self) -> ExecutorResult<()> {
         self.schedule_serve()
     }
@@ -171,6 +215,8 @@ impl<E: Send + 'static> ExecutionService<E> {
                     pending_tasks.push(task.clone());
                     continue;
                 }
+
+                task.live_tasks.fetch_sub(1, Ordering::SeqCst);
             }
         }
 
@@ -181,9 +227,44 @@ impl<E: Send + 'static> ExecutionService<E> {
 pub struct Executor<E: Send + 'static> {
     completed_notification: async_channel::Sender<()>,
     sender: async_channel::Sender<Arc<Task<E>>>,
+    live_tasks: Arc<AtomicUsize>,
 }
 
 impl<E: Send + 'static> Executor<E> {
+    /// [`Executor::queued_tasks`] returns how many tasks wait to be polled
+    /// by the [`ExecutionService`].
+    pub fn queued_tasks(&self) -> usize {
+        self.sender.len()
+    }
+
+    /// [`Executor::live_tasks`] returns how many scheduled or spawned tasks
+    /// have not completed yet.
+    pub fn live_tasks(&self) -> usize {
+        self.live_tasks.load(Ordering::SeqCst)
+    }
+
+    fn submit(&self, handler: future::BoxFuture<'static, ()>) -> ExecutorResult<()> {
+        let task = Arc::new(Task {
+            task_sender: self.sender.clone(),
+            handler: sync::Mutex::new(Some(handler)),
+            ready_notification: self.completed_notification.clone(),
+            live_tasks: self.live_tasks.clone(),
+        });
+
+        // counted before sending so the service never completes an uncounted task.
+        self.live_tasks.fetch_add(1, Ordering::SeqCst);
+        match self.sender.try_send(task) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                self.live_tasks.fetch_sub(1, Ordering::SeqCst);
+                match err {
+                    async_channel::TrySendError::Closed(_) => Err(ExecutorError::Decommission),
+                    async_channel::TrySendError::Full(_) => Err(ExecutorError::ChannelFull),
+                }
+            }
+        }
+    }
+
     // schedule a task to execute when the receiver has data
     // usually the future here should really get scheduled
     // for polling if it's receiver finally received value.
@@ -204,18 +285,7 @@ impl<E: Send + 'static> Executor<E> {
             receiver_fn(received).await
         };
 
-        let box_future = Box::pin(captured_async_fn);
-        let task = Arc::new(Task {
-            task_sender: self.sender.clone(),
-            handler: sync::Mutex::new(Some(box_future)),
-            ready_notification: self.completed_notification.clone(),
-        });
-
-        match self.sender.try_send(task) {
-            Ok(_) => Ok(()),
-            Err(async_channel::TrySendError::Closed(_)) => Err(ExecutorError::Decommission),
-            Err(async_channel::TrySendError::Full(_)) => Err(ExecutorError::ChannelFull),
-        }
+        self.submit(Box::pin(captured_async_fn))
     }
 
     // schedules a task for completion without dependence on a channel
@@ -225,18 +295,7 @@ impl<E: Send + 'static> Executor<E> {
     // The focus is on the future itself and it's compeleness.
     //
     pub fn spawn(&self, fut: impl Future<Output = ()> + 'static + Send) -> ExecutorResult<()> {
-        let box_future = Box::pin(fut);
-        let task = Arc::new(Task {
-            task_sender: self.sender.clone(),
-            handler: sync::Mutex::new(Some(box_future)),
-            ready_notification: self.completed_notification.clone(),
-        });
-
-        match self.sender.try_send(task) {
-            Ok(_) => Ok(()),
-            Err(async_channel::TrySendError::Closed(_)) => Err(ExecutorError::Decommission),
-            Err(async_channel::TrySendError::Full(_)) => Err(ExecutorError::ChannelFull),
-        }
+        self.submit(Box::pin(fut))
     }
 }
 
@@ -508,4 +567,54 @@ mod tests {
 
         assert_eq!(String::from("new text"), recv_message);
     }
+
+    #[test]
+    fn reports_readiness_for_queued_and_woken_tasks() {
+        let (mut servicer, executor) = executor::create::<String>();
+        let waker = futures::task::noop_waker();
+        let mut cx = std::task::Context::from_waker(&waker);
+
+        assert!(servicer.poll_ready(&mut cx).is_pending());
+
+        let (mut sr, rr) = mspc::create::<String>();
+        executor
+            .schedule(rr, move |_| async {})
+            .expect("should have scheduled task");
+
+        assert!(servicer.has_pending_tasks());
+        assert!(servicer.poll_ready(&mut cx).is_ready());
+
+        // the task stays pending till the channel receives a value.
+        servicer.schedule_serve().expect("should serve tasks");
+        assert!(servicer.poll_ready(&mut cx).is_pending());
+
+        sr.try_send(String::from("wake")).unwrap();
+        assert!(servicer.poll_ready(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn reports_queued_and_live_task_counts() {
+        let (mut servicer, executor) = executor::create::<String>();
+        let (mut sender, receiver) = mspc::create::<String>();
+
+        executor
+            .schedule(receiver, |_| async {})
+            .expect("should have scheduled task");
+        executor
+            .spawn(async {})
+            .expect("should have scheduled task");
+
+        assert_eq!(executor.queued_tasks(), 2);
+        assert_eq!(executor.live_tasks(), 2);
+
+        servicer.schedule_serve().expect("should serve tasks");
+
+        // the scheduled task still waits on its receiver.
+        assert_eq!(executor.queued_tasks(), 0);
+        assert_eq!(executor.live_tasks(), 1);
+
+        sender.try_send(String::from("done")).unwrap();
+        servicer.schedule_serve().expect("should serve tasks");
+        assert_eq!(executor.live_tasks(), 0);
+    }
 }
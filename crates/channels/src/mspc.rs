@@ -1,6 +1,13 @@
 // Crate implementing the Engineering Principles of Channels
 
-use std::sync::{self, Arc};
+use std::{
+    future::Future,
+    pin,
+    sync::{self, Arc},
+    task::{Context, Poll, Wake, Waker},
+    thread,
+    time::{Duration, Instant},
+};
 
 use async_channel;
 use crossbeam::atomic;
@@ -30,6 +37,16 @@ pub fn create<T>() -> (SendChannel<T>, ReceiveChannel<T>) {
     (sender, receiver)
 }
 
+/// create_bounded returns a channel holding at most `capacity` pending
+/// messages, where [`SendChannel::block_send`] waits for the receiver to
+/// catch up and [`SendChannel::try_send`] fails while it is full.
+pub fn create_bounded<T>(capacity: usize) -> (SendChannel<T>, ReceiveChannel<T>) {
+    let (tx, rx) = async_channel::bounded::<T>(capacity);
+    let sender = SendChannel::new(tx);
+    let receiver = ReceiveChannel::new(rx);
+    (sender, receiver)
+}
+
 pub struct ChannelGroup<E>(pub Option<SendChannel<E>>, pub Option<ReceiveChannel<E>>);
 
 impl<E> Default for ChannelGroup<E> {
@@ -104,6 +121,12 @@ impl<T> SendChannel<T> {
         }
     }
 
+    /// [`SendChannel`].is_closed() returns true once the channel was closed
+    /// by either side.
+    pub fn is_closed(&self) -> bool {
+        self.src.as_ref().map_or(true, |src| src.is_closed())
+    }
+
     pub fn close(&mut self) -> ChannelResult<()> {
         if let Some(channel) = self.src.take() {
             drop(channel);
@@ -187,10 +210,12 @@ impl<T> ReceiveChannel<T> {
         }
     }
 
+    /// [`ReceiveChannel`].is_closed() returns true once all senders closed
+    /// the channel, messages sent before may still be waiting to be read.
     pub fn is_closed(&mut self) -> ChannelResult<bool> {
         match &self.src {
             None => Err(ChannelError::Closed),
-            Some(_) => Ok(false),
+            Some(src) => Ok(src.is_closed()),
         }
     }
 
@@ -210,6 +235,39 @@ impl<T> ReceiveChannel<T> {
         };
     }
 
+    /// [`ReceiveChannel`].block_receive_timeout() blocks the current thread like
+    /// [`ReceiveChannel`].block_receive() but for at most the timeout, returning
+    /// [`ChannelError::ReceivedNoData`] if nothing arrived by then.
+    pub fn block_receive_timeout(&mut self, timeout: Duration) -> ChannelResult<T> {
+        let deadline = Instant::now() + timeout;
+        let received = match &mut self.src {
+            None => return Err(ChannelError::Closed),
+            Some(src) => {
+                let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+                let mut cx = Context::from_waker(&waker);
+                let mut receiving = pin::pin!(src.recv());
+                loop {
+                    if let Poll::Ready(received) = receiving.as_mut().poll(&mut cx) {
+                        break received;
+                    }
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(ChannelError::ReceivedNoData);
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        };
+
+        match received {
+            Ok(item) => {
+                self.read_flag.store(true);
+                Ok(item)
+            }
+            Err(_) => self.close_channel(),
+        }
+    }
+
     pub async fn async_receive(&mut self) -> ChannelResult<T> {
         match &mut self.src {
             None => Err(ChannelError::Closed),
@@ -256,6 +314,15 @@ impl<T> ReceiveChannel<T> {
     }
 }
 
+/// ThreadWaker wakes a thread parked in [`ReceiveChannel`].block_receive_timeout().
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
 pub struct Drain<'a, T> {
     receiver: &'a mut ReceiveChannel<T>,
 }
@@ -277,9 +344,50 @@ impl<'a, T> Iterator for Drain<'a, T> {
 #[cfg(test)]
 mod tests {
 
-    use crate::mspc::{create, ChannelError};
+    use crate::mspc::{create, create_bounded, ChannelError};
     use std::time::Duration;
 
+    #[test]
+    fn should_refuse_to_send_into_a_full_bounded_channel() {
+        let (mut sender, mut receiver) = create_bounded::<u8>(1);
+
+        sender.try_send(1).expect("should have sent");
+        assert!(matches!(
+            sender.try_send(2),
+            Err(ChannelError::SendFailed(_))
+        ));
+
+        assert_eq!(receiver.try_receive().unwrap(), 1);
+        sender.try_send(2).expect("should have sent");
+    }
+
+    #[test]
+    fn should_wait_at_most_the_timeout_to_receive() {
+        let (mut sender, mut receiver) = create::<u8>();
+
+        assert!(matches!(
+            receiver.block_receive_timeout(Duration::from_millis(10)),
+            Err(ChannelError::ReceivedNoData)
+        ));
+
+        let sending = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(10));
+            sender.try_send(1).expect("should have sent");
+        });
+        assert_eq!(
+            receiver
+                .block_receive_timeout(Duration::from_secs(5))
+                .unwrap(),
+            1
+        );
+        sending.join().unwrap();
+
+        assert!(matches!(
+            receiver.block_receive_timeout(Duration::from_secs(5)),
+            Err(ChannelError::Closed)
+        ));
+    }
+
     #[test]
     fn should_be_able_to_close_a_send_channel() {
         let (mut sender, mut receiver) = create::<String>();
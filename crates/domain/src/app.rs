@@ -1,17 +1,26 @@
 use crate::{core, domains, servicer};
 
-pub fn create<App>() -> (
-    core::CoreExecutor,
-    Box<servicer::DServicer<App, App::Events, App::Requests, App::Platform>>,
-)
+/// AppServicer is the [`servicer::DServicer`] serving the domain App.
+pub type AppServicer<App> = servicer::DServicer<
+    App,
+    <App as domains::Domain>::Events,
+    <App as domains::Domain>::Requests,
+    <App as domains::Domain>::Platform,
+    <App as domains::Domain>::Error,
+>;
+
+pub fn create<App>() -> (core::CoreExecutor, Box<AppServicer<App>>)
 where
     App: domains::Domain + 'static,
+    App::Events: Sync,
+    App::Requests: Sync,
 {
     let app_server = Box::new(servicer::create::<
         App,
         App::Events,
         App::Requests,
         App::Platform,
+        App::Error,
     >());
 
     let mut app_core = core::CoreExecutor::new();
@@ -19,3 +28,16 @@ where
 
     (app_core, app_server)
 }
+
+/// create_async creates the [`servicer::DServicer`] for an [`domains::AsyncDomain`]
+/// wrapping it in a [`domains::AsyncDomainAdapter`] which spawns its handlers
+/// on the shell executor.
+pub fn create_async<App>() -> (
+    core::CoreExecutor,
+    Box<AppServicer<domains::AsyncDomainAdapter<App>>>,
+)
+where
+    App: domains::AsyncDomain,
+{
+    create::<domains::AsyncDomainAdapter<App>>()
+}
@@ -1,7 +1,65 @@
-use std::sync;
+use std::{
+    sync::{self, Arc},
+    task::{Context, Poll, Waker},
+    time::Instant,
+};
 
 use crate::domains;
 
+/// Notifier allows producers of work to wake whoever waits on the
+/// [`CoreExecutor`] to make progress, e.g the [`crate::drivers`].
+///
+/// Wakers are registered by the waiting side and are woken, then
+/// forgotten, on the next notification.
+#[derive(Clone, Default)]
+pub struct Notifier {
+    state: Arc<sync::Mutex<NotifierState>>,
+}
+
+#[derive(Default)]
+struct NotifierState {
+    notified: bool,
+    wakers: Vec<Waker>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register the waker to be woken on the next notification.
+    pub fn register(&self, waker: &Waker) {
+        let mut state = self.state.lock().unwrap();
+        if !state
+            .wakers
+            .iter()
+            .any(|registered| registered.will_wake(waker))
+        {
+            state.wakers.push(waker.clone());
+        }
+    }
+
+    /// notify wakes all registered wakers.
+    pub fn notify(&self) {
+        let wakers = {
+            let mut state = self.state.lock().unwrap();
+            state.notified = true;
+            std::mem::take(&mut state.wakers)
+        };
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// take_notified returns true if a notification happened since
+    /// last called.
+    pub fn take_notified(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        std::mem::replace(&mut state.notified, false)
+    }
+}
+
 /// CoreExecutor provides a core structure for handling and managing
 /// execution of all types implementing [`domains::TaskExecutor`].
 ///
@@ -12,14 +70,19 @@ use crate::domains;
 ///
 /// This becomes useful in non-async supporting environments like WASM and even the
 /// web where blocking the main thread can be disasterous.
+///
+/// See [`crate::drivers`] for running the executor without calling
+/// [`CoreExecutor::run_all`] in a loop.
 pub struct CoreExecutor {
     executors: sync::Mutex<Vec<Box<dyn domains::TaskExecutor>>>,
+    notifier: Notifier,
 }
 
 impl Default for CoreExecutor {
     fn default() -> Self {
         Self {
             executors: sync::Mutex::new(Vec::new()),
+            notifier: Notifier::new(),
         }
     }
 }
@@ -39,5 +102,51 @@ impl CoreExecutor {
     pub fn register(&mut self, executor: Box<dyn domains::TaskExecutor>) {
         let mut executors = self.executors.lock().unwrap();
         executors.push(executor);
+        self.notifier.notify();
+    }
+
+    /// notifier returns the [`Notifier`] which wakes whoever waits on the
+    /// executor regardless of the readiness of its executors.
+    pub fn notifier(&self) -> Notifier {
+        self.notifier.clone()
+    }
+
+    /// poll_ready resolves once any of the registered executors has work to
+    /// progress, else registers the context's waker with all of them.
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.notifier.register(cx.waker());
+
+        let mut ready = self.notifier.take_notified();
+        let mut executors = self.executors.lock().unwrap();
+
+        // every executor gets polled so they all register the waker.
+        for executor in executors.iter_mut() {
+            if executor.poll_ready(cx).is_ready() {
+                ready = true;
+            }
+        }
+
+        if ready {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+
+    /// next_wake returns the earliest instant any of the registered
+    /// executors has timed work to progress at, drivers must not wait
+    /// past it for a notification.
+    pub fn next_wake(&self) -> Option<Instant> {
+        let executors = self.executors.lock().unwrap();
+        executors
+            .iter()
+            .filter_map(|executor| executor.next_wake())
+            .min()
+    }
+
+    /// is_ready returns true if any of the registered executors has work
+    /// to progress.
+    pub fn is_ready(&mut self) -> bool {
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        self.poll_ready(&mut cx).is_ready()
     }
 }
@@ -1,17 +1,29 @@
 // trait defintion for the Domain concept from the Principles of Architecture
 
 use std::sync::Arc;
-use std::{fmt::Display, result};
+use std::time::{Duration, Instant};
+use std::{
+    any::Any,
+    fmt::{Debug, Display},
+    result,
+    task::{Context, Poll},
+};
 
 use futures::{future, Future};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use tracing::{debug, error};
 
 use ewe_channels::mspc::{self, ChannelError};
 
+use crate::{
+    core, policies,
+    supervisor::{self, Handler, Supervisor, SupervisorEvent, SupervisorPolicy},
+};
+
 // Id identifies a giving (Request, Vec<Event>) pair
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Id(pub String);
 
 impl Display for Id {
@@ -23,7 +35,7 @@ impl Display for Id {
 /// NamedRequest represent a target request of a specified
 /// type which has an Id to identify the request and
 /// any related events that are a response to the request.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct NamedRequest<T: Clone>(Id, T);
 
 impl<T: Clone> NamedRequest<T> {
@@ -55,7 +67,7 @@ impl<T: Clone> Display for NamedRequest<T> {
 }
 
 /// NamedEvent are events indicative of a response to a NamedRequest
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct NamedEvent<T: Clone>(Id, Vec<T>);
 
 impl<'a, T: Clone> Display for NamedEvent<T> {
@@ -134,14 +146,60 @@ pub enum DomainErrors {
 
 pub type DomainResult<R> = result::Result<R, DomainErrors>;
 
+/// DomainFailure describes why a request sent via [`DomainShell::do_request`]
+/// failed, separating failures from the events of a successful response.
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DomainFailure<Err> {
+    #[error("request failed: {0:?}")]
+    Failed(Err),
+
+    #[error("request handler panicked: {0}")]
+    Panicked(String),
+
+    #[error("request timed out")]
+    TimedOut,
+
+    #[error("domain stopped after a handler panicked")]
+    Stopped,
+
+    #[error("request rejected: {0}")]
+    Rejected(String),
+}
+
+/// DomainResponse is the response delivered to the requester of
+/// [`DomainShell::do_request`].
+pub type DomainResponse<E, Err> = result::Result<NamedEvent<E>, DomainFailure<Err>>;
+
+/// DomainResponseReceiver is the channel [`DomainShell::do_request`]
+/// delivers the [`DomainResponse`]s of a request on.
+pub type DomainResponseReceiver<E, Err> = mspc::ReceiveChannel<DomainResponse<E, Err>>;
+
 /// TaskExecutor defines a trait that other relevant implementations
 /// must implement to be able to work with the CoreExecutor which manages
 /// relevant parties to progress in their underlying processes.
 ///
 /// Generally you would see the [`DServicer`], [`UseCaseExecutor`] being
 /// implementing this trate for registration to a CoreExecutor.
-pub trait TaskExecutor {
+pub trait TaskExecutor: Send {
     fn run_tasks(&mut self);
+
+    /// poll_ready reports if the executor has work to progress when
+    /// [`TaskExecutor::run_tasks`] is called, else it registers the context's
+    /// waker to be woken once work arrives.
+    ///
+    /// Defaults to always being ready which has drivers continuously
+    /// running the executor.
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<()> {
+        Poll::Ready(())
+    }
+
+    /// next_wake returns the instant the executor has timed work to
+    /// progress at, e.g a deadline, which no one notifies the drivers of.
+    ///
+    /// Defaults to `None` for executors without timed work.
+    fn next_wake(&self) -> Option<Instant> {
+        None
+    }
 }
 
 // DomainShell provides the underlying boundary that wraps a domain and
@@ -154,7 +212,7 @@ pub trait TaskExecutor {
 // We envision that a Domain would accept a domain shell and use it accordingly.
 //
 // e.g DomainShell().serve(Domain)
-pub trait DomainShell: Clone {
+pub trait DomainShell: Clone + Send + 'static {
     // Enum defining your target event types
     type Events: Send + Clone + 'static;
 
@@ -162,7 +220,10 @@ pub trait DomainShell: Clone {
     type Requests: Send + Clone + 'static;
 
     // The platform provider context the domain will use.
-    type Platform: Clone + 'static;
+    type Platform: Clone + Send + 'static;
+
+    // The error type requests to the domain fail with.
+    type Error: Clone + Debug + Send + 'static;
 
     // the underlying platform provided by the shell.
     fn platform(&self) -> Self::Platform;
@@ -178,11 +239,37 @@ pub trait DomainShell: Clone {
     /// wish to get the domain to perform operations based on it's
     /// internal logic or use-cases.
     ///
+    /// The response channel delivers the events of the domain's response,
+    /// or the [`DomainFailure`] of the request if it failed, panicked or
+    /// timed out.
+    ///
     /// Hexagonal Architecture: Driven Side
     fn do_request(
         &mut self,
         req: NamedRequest<Self::Requests>,
-    ) -> DomainOpsResult<mspc::ReceiveChannel<NamedEvent<Self::Events>>, Self::Requests>;
+    ) -> DomainOpsResult<DomainResponseReceiver<Self::Events, Self::Error>, Self::Requests>;
+
+    /// do_request_with_timeout performs the request like
+    /// [`DomainShell::do_request`] but has the domain fail it with
+    /// [`DomainFailure::TimedOut`] if it does not finish its response
+    /// within the timeout, forgetting the request.
+    ///
+    /// Shells without deadlines perform the request without one.
+    fn do_request_with_timeout(
+        &mut self,
+        req: NamedRequest<Self::Requests>,
+        _timeout: Duration,
+    ) -> DomainOpsResult<DomainResponseReceiver<Self::Events, Self::Error>, Self::Requests> {
+        self.do_request(req)
+    }
+
+    /// notifier returns the [`core::Notifier`] the shell wakes the drivers
+    /// with whenever work arrives through it.
+    ///
+    /// Shells not driven by a [`core::CoreExecutor`] have none.
+    fn notifier(&self) -> Option<core::Notifier> {
+        None
+    }
 
     /// schedule a task to execute when the receiver has data
     /// usually the future here should really get scheduled
@@ -252,12 +339,28 @@ pub trait MasterShell: DomainShell {
         &mut self,
         req: NamedRequest<Self::Requests>,
     ) -> DomainOpsResult<mspc::ReceiveChannel<NamedEvent<Self::Events>>, Self::Requests>;
+
+    /// Fails the pending request with the giving id, delivering the error
+    /// to its requester as a [`DomainFailure::Failed`] and closing
+    /// its response.
+    fn fail(&mut self, id: Id, err: Self::Error) -> DomainOpsResult<(), Id>;
+
+    /// Reports the panic of a domain handler spawned for the request or
+    /// event with the giving id, failing a pending request with
+    /// [`DomainFailure::Panicked`] and handing the panic to the domain's
+    /// supervisor.
+    ///
+    /// Shells without a supervisor resume the panic.
+    fn report_panic(&mut self, id: Id, handler: Handler, payload: Box<dyn Any + Send>) {
+        _ = (id, handler);
+        std::panic::resume_unwind(payload)
+    }
 }
 
 // Implement [`Domain`] on your type to create a business domain unit
 // with specific inputs and outputs via requests and events
 // via central handling function [`Domain.handle`].
-pub trait Domain: Clone + Default {
+pub trait Domain: Clone + Default + Send {
     // Enum defining your target event types
     type Events: Clone + Send + 'static;
 
@@ -267,7 +370,11 @@ pub trait Domain: Clone + Default {
     // The platform provider context the domain
     // will use to access platform features, usually
     // a struct with a default implement.
-    type Platform: Default + Clone + 'static;
+    type Platform: Default + Clone + Send + 'static;
+
+    // The error type the domain fails a request with
+    // via [`MasterShell::fail`].
+    type Error: Clone + Debug + Send + 'static;
 
     // the domain simply must deliver response to the
     // send channel and has access to the shell if it
@@ -280,6 +387,7 @@ pub trait Domain: Clone + Default {
             Events = Self::Events,
             Requests = Self::Requests,
             Platform = Self::Platform,
+            Error = Self::Error,
         >,
     );
 
@@ -292,6 +400,7 @@ pub trait Domain: Clone + Default {
             Events = Self::Events,
             Requests = Self::Requests,
             Platform = Self::Platform,
+            Error = Self::Error,
         >,
     );
 }
@@ -307,7 +416,7 @@ pub trait Domain: Clone + Default {
 ///
 /// They usually hook into a [`DomainShell::requests`] broadcasts
 /// handling the specific request type that are focused on.
-pub trait UseCase: Clone {
+pub trait UseCase: Clone + Send {
     // Enum defining your target event types
     type Event: Clone + Send + 'static;
 
@@ -317,7 +426,7 @@ pub trait UseCase: Clone {
     // The platform provider context the domain
     // will use to access platform features, usually
     // a struct with a default implement.
-    type Platform: Clone + 'static;
+    type Platform: Clone + Send + 'static;
 
     /// allows the UseCaseManager decide which specific requests matches
     /// a given use-case.
@@ -336,6 +445,13 @@ pub trait UseCase: Clone {
             Platform = Self::Platform,
         >,
     );
+
+    /// take_panics returns the panics caught within the futures the
+    /// use-case spawned since last called, for its [`UseCaseExecutor`] to
+    /// supervise them like the panics of [`UseCase::handle_request`].
+    fn take_panics(&mut self) -> Vec<supervisor::CaughtPanic> {
+        Vec::new()
+    }
 }
 
 pub struct UseCaseExecutor<
@@ -343,17 +459,21 @@ pub struct UseCaseExecutor<
     U,
     E: Clone + Send + 'static,
     R: Clone + Send + 'static,
-    P: Clone + 'static,
+    P: Clone + Send + 'static,
 > where
     Shell: DomainShell<Events = E, Requests = R, Platform = P>,
     U: UseCase<Event = E, Request = R, Platform = P>,
 {
     use_case: U,
+    initial: U,
     shell: Shell,
     receiver: mspc::ReceiveChannel<Arc<NamedRequest<R>>>,
+    policy: Option<policies::PolicyEnforcer<E, R>>,
+    supervisor: Supervisor,
+    caught: Vec<supervisor::CaughtPanic>,
 }
 
-impl<S, U, E: Clone + Send + 'static, R: Clone + Send + 'static, P: Clone + 'static>
+impl<S, U, E: Clone + Send + 'static, R: Clone + Send + 'static, P: Clone + Send + 'static>
     UseCaseExecutor<S, U, E, R, P>
 where
     S: DomainShell<Events = E, Requests = R, Platform = P>,
@@ -363,18 +483,72 @@ where
         Self {
             receiver: shell_provider.requests().expect("expected request channel"),
             shell: shell_provider,
+            policy: None,
+            supervisor: Supervisor::default(),
+            caught: Vec::new(),
+            initial: use_case.clone(),
             use_case,
         }
     }
-}
 
-impl<S, U, E: Clone + Send + 'static, R: Clone + Send + 'static, P: Clone + 'static> TaskExecutor
-    for UseCaseExecutor<S, U, E, R, P>
-where
-    S: DomainShell<Events = E, Requests = R, Platform = P>,
-    U: UseCase<Event = E, Request = R, Platform = P>,
-{
-    fn run_tasks(&mut self) {
+    /// with_policy creates a [`UseCaseExecutor`] enforcing the provided
+    /// [`policies::UseCasePolicy`] on all requests handled by the use-case,
+    /// delivering the policy decisions to the domain's listeners as events.
+    pub fn with_policy(
+        mut shell_provider: S,
+        use_case: U,
+        policy: policies::UseCasePolicy<E, R>,
+    ) -> Self
+    where
+        S: MasterShell<Events = E, Requests = R, Platform = P>,
+        E: From<policies::PolicyEvent>,
+    {
+        let mut notifier = shell_provider.clone();
+        let notify = Box::new(move |req: &NamedRequest<R>, event: policies::PolicyEvent| {
+            if let Err(err) = notifier.send_others(req.to_one(E::from(event))) {
+                error!("UseCase executor failed to deliver policy event: {}", err);
+            }
+        });
+
+        Self {
+            receiver: shell_provider.requests().expect("expected request channel"),
+            policy: Some(policies::PolicyEnforcer::new(policy, notify)),
+            shell: shell_provider,
+            supervisor: Supervisor::default(),
+            caught: Vec::new(),
+            initial: use_case.clone(),
+            use_case,
+        }
+    }
+
+    /// supervise applies the giving [`SupervisorPolicy`] to panics of the
+    /// use-case, delivering the [`SupervisorEvent`]s to the domain's listeners.
+    ///
+    /// Restarting a use-case restores the use-case it was created with,
+    /// a panicking request is answered by closing its response channel.
+    pub fn supervise(&mut self, policy: SupervisorPolicy)
+    where
+        S: MasterShell<Events = E, Requests = R, Platform = P>,
+        E: From<SupervisorEvent>,
+    {
+        let mut notifier = self.shell.clone();
+        let notify = Box::new(move |id: &Id, event: SupervisorEvent| {
+            let event = NamedEvent::new(&id.0, vec![E::from(event)]);
+            if let Err(err) = notifier.send_others(event) {
+                error!(
+                    "UseCase executor failed to deliver supervisor event: {}",
+                    err
+                );
+            }
+        });
+        self.supervisor.configure(policy, notify);
+    }
+
+    pub fn supervisor(&self) -> Supervisor {
+        self.supervisor.clone()
+    }
+
+    fn receive_request(&mut self) {
         match self.receiver.try_receive() {
             Ok(req) => {
                 if !self.use_case.is_request(req.clone()) {
@@ -383,21 +557,411 @@ where
 
                 debug!("UseCase executor received a new task");
 
-                let sender = self.shell.respond(req.id()).expect("get response sender");
-                self.use_case
-                    .handle_request(req, sender, self.shell.clone())
+                // another use-case or a scripted reply already answers it.
+                let sender = match self.shell.respond(req.id()) {
+                    Ok(sender) => sender,
+                    Err(err) => {
+                        debug!("UseCase executor cannot respond to {}: {}", req, err);
+                        return;
+                    }
+                };
+                if self.supervisor.is_stopped() {
+                    debug!("UseCase executor is stopped, closing response of {}", req);
+                    return;
+                }
+
+                let mut panics = Vec::new();
+                match self.policy.as_mut() {
+                    Some(enforcer) => {
+                        let use_case = &mut self.use_case;
+                        let shell = &self.shell;
+                        enforcer.submit(req, sender, Instant::now(), &mut |req, chan| {
+                            let id = req.id();
+                            let handled = supervisor::catch(|| {
+                                use_case.handle_request(req, chan, shell.clone())
+                            });
+                            if let Err(payload) = handled {
+                                panics.push((id, payload));
+                            }
+                        });
+                    }
+                    None => {
+                        let id = req.id();
+                        let use_case = &mut self.use_case;
+                        let shell = self.shell.clone();
+                        let handled =
+                            supervisor::catch(|| use_case.handle_request(req, sender, shell));
+                        if let Err(payload) = handled {
+                            panics.push((id, payload));
+                        }
+                    }
+                }
+                self.recover(panics);
             }
             Err(ChannelError::ReceiveFailed(err)) => {
                 error!("UseCase executor failed with a receive error: {}", err);
-                return;
             }
             Err(ChannelError::Closed) => {
                 error!("UseCase executor receiver was closed");
-                return;
             }
-            _ => {
-                return;
+            _ => {}
+        }
+    }
+
+    fn enforce_policy(&mut self) {
+        let mut panics = Vec::new();
+        if let Some(enforcer) = self.policy.as_mut() {
+            let use_case = &mut self.use_case;
+            let shell = &self.shell;
+            enforcer.poll(Instant::now(), &mut |req, chan| {
+                if self.supervisor.is_stopped() {
+                    return;
+                }
+
+                let id = req.id();
+                let handled =
+                    supervisor::catch(|| use_case.handle_request(req, chan, shell.clone()));
+                if let Err(payload) = handled {
+                    panics.push((id, payload));
+                }
+            });
+        }
+        self.recover(panics);
+    }
+
+    // recover_spawned applies the supervisor's policy to the panics caught
+    // within the futures the use-case spawned.
+    fn recover_spawned(&mut self) {
+        self.caught.extend(self.use_case.take_panics());
+        let panics = std::mem::take(&mut self.caught)
+            .into_iter()
+            .map(|(id, _, payload)| (id, payload))
+            .collect();
+        self.recover(panics);
+    }
+
+    // recover applies the supervisor's policy to the panics of the use-case.
+    fn recover(&mut self, panics: Vec<(Id, Box<dyn std::any::Any + Send>)>) {
+        for (id, payload) in panics {
+            let message = supervisor::panic_message(payload.as_ref());
+            match self.supervisor.on_panic(&id, Handler::UseCase, message) {
+                SupervisorPolicy::Restart => self.use_case = self.initial.clone(),
+                SupervisorPolicy::Stop => debug!("UseCase executor stopped serving requests"),
+                SupervisorPolicy::Escalate => std::panic::resume_unwind(payload),
             }
         }
     }
 }
+
+impl<
+        S,
+        U,
+        E: Clone + Send + Sync + 'static,
+        R: Clone + Send + Sync + 'static,
+        P: Clone + Send + 'static,
+    > TaskExecutor for UseCaseExecutor<S, U, E, R, P>
+where
+    S: DomainShell<Events = E, Requests = R, Platform = P>,
+    U: UseCase<Event = E, Request = R, Platform = P>,
+{
+    fn run_tasks(&mut self) {
+        self.recover_spawned();
+        self.receive_request();
+        self.enforce_policy();
+    }
+
+    // requests reach the use-case through the shell which wakes the
+    // drivers on its own, in-flight policies are only ready once replies
+    // arrived or a backoff or attempt timeout elapsed.
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        // registered first so work arriving while checking still wakes us.
+        if let Some(notifier) = self.shell.notifier() {
+            notifier.register(cx.waker());
+        }
+
+        self.caught.extend(self.use_case.take_panics());
+
+        let has_requests = matches!(self.receiver.is_empty(), Ok(false));
+        let has_due = self
+            .policy
+            .as_mut()
+            .is_some_and(|enforcer| enforcer.is_due(Instant::now()));
+
+        if has_requests || has_due || !self.caught.is_empty() {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+
+    fn next_wake(&self) -> Option<Instant> {
+        self.policy
+            .as_ref()
+            .and_then(|enforcer| enforcer.next_wake())
+    }
+}
+
+// Implement [`AsyncDomain`] on your type to create a business domain unit
+// whose request handling is asynchronous, returning the events that answer
+// the request instead of delivering them manually to a response channel.
+//
+// Wrap it in [`AsyncDomainAdapter`] (see [`crate::app::create_async`]) to
+// have it served by the [`crate::servicer::DServicer`].
+pub trait AsyncDomain: Clone + Default + Send + Sync + 'static {
+    // Enum defining your target event types
+    type Events: Clone + Send + Sync + 'static;
+
+    // Enum defining your target request types.
+    type Requests: Clone + Send + Sync + 'static;
+
+    // The platform provider context the domain
+    // will use to access platform features, usually
+    // a struct with a default implement.
+    type Platform: Default + Clone + Send + 'static;
+
+    // The error type the domain fails a request with.
+    type Error: Clone + Debug + Send + Sync + 'static;
+
+    /// handles the request asynchronously, the returned events are
+    /// delivered to the requester by the servicer once the future completes,
+    /// an error fails the request via [`MasterShell::fail`].
+    fn handle_request(
+        &self,
+        req: NamedRequest<Self::Requests>,
+        shell: impl MasterShell<
+            Events = Self::Events,
+            Requests = Self::Requests,
+            Platform = Self::Platform,
+            Error = Self::Error,
+        >,
+    ) -> impl Future<Output = result::Result<Vec<Self::Events>, Self::Error>> + Send;
+
+    /// handles events from both the domain's handling of requests
+    /// and other incoming events sent
+    fn handle_event(
+        &self,
+        events: NamedEvent<Self::Events>,
+        shell: impl MasterShell<
+            Events = Self::Events,
+            Requests = Self::Requests,
+            Platform = Self::Platform,
+            Error = Self::Error,
+        >,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+/// UseCaseFailed is the event an [`AsyncUseCaseAdapter`] answers a request
+/// with when its [`AsyncUseCase`] failed to handle it, so the requesting
+/// domain can tell the failure apart from an empty response.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UseCaseFailed {
+    pub error: String,
+}
+
+impl Display for UseCaseFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "UseCaseFailed({})", self.error)
+    }
+}
+
+/// AsyncUseCase is the asynchronous variant of [`UseCase`] where the handler
+/// returns the events answering the request instead of delivering them
+/// manually to a response channel.
+///
+/// A failed request is answered with the [`UseCaseFailed`] event, match it
+/// with [`policies::UseCasePolicy::with_failure_check`] to have policies
+/// retry it.
+///
+/// Wrap it in [`AsyncUseCaseAdapter`] to register it with a [`UseCaseExecutor`].
+pub trait AsyncUseCase: Clone + Send + Sync + 'static {
+    // Enum defining your target event types, including the failure event.
+    type Event: Clone + Send + Sync + From<UseCaseFailed> + 'static;
+
+    // Enum defining your target request types.
+    type Request: Clone + Send + Sync + 'static;
+
+    // The platform provider context the domain
+    // will use to access platform features, usually
+    // a struct with a default implement.
+    type Platform: Clone + Send + 'static;
+
+    // The error type the use-case fails a request with.
+    type Error: Debug + Send + 'static;
+
+    /// allows the UseCaseManager decide which specific requests matches
+    /// a given use-case.
+    fn is_request(&self, req: Arc<NamedRequest<Self::Request>>) -> bool;
+
+    /// handles the request asynchronously, the returned events are
+    /// delivered to the requesting domain once the future completes, an
+    /// error is delivered as the [`UseCaseFailed`] event.
+    fn handle_request(
+        &self,
+        req: Arc<NamedRequest<Self::Request>>,
+        shell: impl DomainShell<
+            Events = Self::Event,
+            Requests = Self::Request,
+            Platform = Self::Platform,
+        >,
+    ) -> impl Future<Output = result::Result<Vec<Self::Event>, Self::Error>> + Send;
+}
+
+// deliver_events sends the events of an async handler to the pending response
+// channel, closing the channel after, so the requester knows the response is complete.
+async fn deliver_events<E: Clone, R: Clone>(
+    req: NamedRequest<R>,
+    events: Vec<E>,
+    mut chan: mspc::SendChannel<NamedEvent<E>>,
+) {
+    if let Err(err) = chan.async_send(req.to(events)).await {
+        error!("Failed to deliver response for {}: {}", req, err);
+    }
+    _ = chan.close();
+}
+
+/// AsyncDomainAdapter wraps an [`AsyncDomain`] into a [`Domain`], spawning
+/// each handler invocation on the shell's executor and routing the result into
+/// the pending response channel of the request.
+#[derive(Clone, Default)]
+pub struct AsyncDomainAdapter<A: AsyncDomain>(A);
+
+impl<A: AsyncDomain> AsyncDomainAdapter<A> {
+    pub fn new(domain: A) -> Self {
+        Self(domain)
+    }
+
+    pub fn domain(&self) -> &A {
+        &self.0
+    }
+}
+
+impl<A: AsyncDomain> Domain for AsyncDomainAdapter<A> {
+    type Events = A::Events;
+    type Requests = A::Requests;
+    type Platform = A::Platform;
+    type Error = A::Error;
+
+    fn handle_request(
+        &self,
+        req: NamedRequest<Self::Requests>,
+        chan: mspc::SendChannel<NamedEvent<Self::Events>>,
+        shell: impl MasterShell<
+            Events = Self::Events,
+            Requests = Self::Requests,
+            Platform = Self::Platform,
+            Error = Self::Error,
+        >,
+    ) {
+        let domain = self.0.clone();
+        let mut failer = shell.clone();
+        let spawned = shell.clone().spawn(async move {
+            let handled = supervisor::catch_async(domain.handle_request(req.clone(), shell));
+            match handled.await {
+                Ok(Ok(events)) => deliver_events(req, events, chan).await,
+                Ok(Err(err)) => {
+                    if let Err(failure) = failer.fail(req.id(), err) {
+                        error!("AsyncDomain failed to fail {}: {}", req, failure);
+                    }
+                    drop(chan);
+                }
+                // reported before the channel closes so the request fails
+                // instead of finishing with no events.
+                Err(payload) => {
+                    failer.report_panic(req.id(), Handler::Request, payload);
+                    drop(chan);
+                }
+            }
+        });
+
+        if let Err(err) = spawned {
+            error!("AsyncDomain failed to spawn request handler: {}", err);
+        }
+    }
+
+    fn handle_event(
+        &self,
+        events: NamedEvent<Self::Events>,
+        shell: impl MasterShell<
+            Events = Self::Events,
+            Requests = Self::Requests,
+            Platform = Self::Platform,
+            Error = Self::Error,
+        >,
+    ) {
+        let domain = self.0.clone();
+        let spawner = shell.clone();
+        let mut reporter = shell.clone();
+        let spawned = spawner.spawn(async move {
+            let id = events.id();
+            if let Err(payload) = supervisor::catch_async(domain.handle_event(events, shell)).await
+            {
+                reporter.report_panic(id, Handler::Event, payload);
+            }
+        });
+
+        if let Err(err) = spawned {
+            error!("AsyncDomain failed to spawn event handler: {}", err);
+        }
+    }
+}
+
+/// AsyncUseCaseAdapter wraps an [`AsyncUseCase`] into a [`UseCase`], spawning
+/// each handler invocation on the shell's executor and routing the result into
+/// the pending response channel of the request.
+#[derive(Clone)]
+pub struct AsyncUseCaseAdapter<U: AsyncUseCase>(U, supervisor::Panics);
+
+impl<U: AsyncUseCase> AsyncUseCaseAdapter<U> {
+    pub fn new(use_case: U) -> Self {
+        Self(use_case, supervisor::Panics::default())
+    }
+
+    pub fn use_case(&self) -> &U {
+        &self.0
+    }
+}
+
+impl<U: AsyncUseCase> UseCase for AsyncUseCaseAdapter<U> {
+    type Event = U::Event;
+    type Request = U::Request;
+    type Platform = U::Platform;
+
+    fn is_request(&self, req: Arc<NamedRequest<Self::Request>>) -> bool {
+        self.0.is_request(req)
+    }
+
+    fn handle_request(
+        &mut self,
+        req: Arc<NamedRequest<Self::Request>>,
+        chan: mspc::SendChannel<NamedEvent<Self::Event>>,
+        shell: impl DomainShell<
+            Events = Self::Event,
+            Requests = Self::Request,
+            Platform = Self::Platform,
+        >,
+    ) {
+        let use_case = self.0.clone();
+        let panics = self.1.clone();
+        let spawner = shell.clone();
+        let spawned = spawner.spawn(async move {
+            match supervisor::catch_async(use_case.handle_request(req.clone(), shell)).await {
+                Ok(Ok(events)) => deliver_events(req.as_ref().clone(), events, chan).await,
+                Ok(Err(err)) => {
+                    debug!("AsyncUseCase failed to handle {}: {:?}", req, err);
+                    let failed = UseCaseFailed {
+                        error: format!("{:?}", err),
+                    };
+                    deliver_events(req.as_ref().clone(), vec![failed.into()], chan).await
+                }
+                Err(payload) => panics.push(req.id(), Handler::UseCase, payload),
+            }
+        });
+
+        if let Err(err) = spawned {
+            error!("AsyncUseCase failed to spawn request handler: {}", err);
+        }
+    }
+
+    fn take_panics(&mut self) -> Vec<supervisor::CaughtPanic> {
+        self.1.take()
+    }
+}
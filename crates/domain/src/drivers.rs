@@ -0,0 +1,348 @@
+// Module implementing drivers which run the CoreExecutor on behalf of the host.
+
+use std::{
+    future, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tracing::debug;
+
+use crate::core::{self, CoreExecutor};
+
+// longest time a driver waits without running the executor, timers like
+// request deadlines are only checked when the executor runs.
+const MAX_PARK: Duration = Duration::from_millis(50);
+
+// park_timeout returns how long a driver may wait for a notification,
+// waking early for the next timed work of the executor.
+fn park_timeout(core: &CoreExecutor) -> Duration {
+    core.next_wake().map_or(MAX_PARK, |wake| {
+        wake.saturating_duration_since(Instant::now()).min(MAX_PARK)
+    })
+}
+
+/// DriverHandle allows stopping a running driver from anywhere.
+#[derive(Clone)]
+pub struct DriverHandle {
+    stopped: Arc<AtomicBool>,
+    notifier: core::Notifier,
+}
+
+impl DriverHandle {
+    fn new(core: &CoreExecutor) -> Self {
+        Self {
+            stopped: Arc::new(AtomicBool::new(false)),
+            notifier: core.notifier(),
+        }
+    }
+
+    /// stop asks the driver to stop after its current run.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.notifier.notify();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+/// StepDriver lets the host progress the [`CoreExecutor`] one step at a
+/// time, suitable for environments without threads like WASM where the
+/// host's own event loop calls [`StepDriver::step`].
+pub struct StepDriver {
+    core: CoreExecutor,
+}
+
+impl StepDriver {
+    pub fn new(core: CoreExecutor) -> Self {
+        Self { core }
+    }
+
+    /// is_ready returns true if calling [`StepDriver::step`] has work to progress.
+    pub fn is_ready(&mut self) -> bool {
+        self.core.is_ready()
+    }
+
+    /// poll_ready allows hosts with their own event loop to be woken once
+    /// the executor has work to progress.
+    pub fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.core.poll_ready(cx)
+    }
+
+    /// next_wake returns the instant the host should step the executor
+    /// at even if it was not woken, see [`CoreExecutor::next_wake`].
+    pub fn next_wake(&self) -> Option<Instant> {
+        self.core.next_wake()
+    }
+
+    /// step runs the executor once if it has work to progress, returning
+    /// true if it did.
+    pub fn step(&mut self) -> bool {
+        if !self.core.is_ready() {
+            return false;
+        }
+
+        self.core.run_all();
+        true
+    }
+
+    /// run_until_idle steps the executor until it has no more work or
+    /// max_steps is reached, returning the number of steps ran.
+    pub fn run_until_idle(&mut self, max_steps: usize) -> usize {
+        let mut steps = 0;
+        while steps < max_steps && self.step() {
+            steps += 1;
+        }
+        steps
+    }
+
+    pub fn core(&mut self) -> &mut CoreExecutor {
+        &mut self.core
+    }
+
+    pub fn into_inner(self) -> CoreExecutor {
+        self.core
+    }
+}
+
+struct ThreadWaker(thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// ThreadDriver runs the [`CoreExecutor`] on a dedicated thread which
+/// parks whenever the executor has no work, until a shell operation or
+/// woken task notifies it.
+pub struct ThreadDriver {
+    handle: DriverHandle,
+    thread: thread::JoinHandle<CoreExecutor>,
+}
+
+impl ThreadDriver {
+    pub fn spawn(mut core: CoreExecutor) -> io::Result<Self> {
+        let handle = DriverHandle::new(&core);
+        let driver_handle = handle.clone();
+
+        let thread = thread::Builder::new()
+            .name(String::from("ewe-domain-driver"))
+            .spawn(move || {
+                let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+                let mut cx = Context::from_waker(&waker);
+
+                while !driver_handle.is_stopped() {
+                    match core.poll_ready(&mut cx) {
+                        Poll::Ready(()) => core.run_all(),
+                        // a notification arriving before parking makes park return at once.
+                        Poll::Pending => thread::park_timeout(park_timeout(&core)),
+                    }
+                }
+
+                debug!("ThreadDriver stopped");
+                core
+            })?;
+
+        Ok(Self { handle, thread })
+    }
+
+    pub fn handle(&self) -> DriverHandle {
+        self.handle.clone()
+    }
+
+    /// stop stops the driver thread, returning the executor once it exits.
+    pub fn stop(self) -> thread::Result<CoreExecutor> {
+        self.handle.stop();
+        self.thread.join()
+    }
+}
+
+/// TokioDriver runs the [`CoreExecutor`] as a tokio task which awaits the
+/// executor's readiness between runs.
+pub struct TokioDriver {
+    handle: DriverHandle,
+    task: tokio::task::JoinHandle<CoreExecutor>,
+}
+
+impl TokioDriver {
+    /// spawn runs the executor on the current tokio runtime.
+    pub fn spawn(core: CoreExecutor) -> Self {
+        let handle = DriverHandle::new(&core);
+        let task = tokio::spawn(drive(core, handle.clone()));
+        Self { handle, task }
+    }
+
+    pub fn handle(&self) -> DriverHandle {
+        self.handle.clone()
+    }
+
+    /// stop stops the driver task, returning the executor once it exits.
+    pub async fn stop(self) -> Result<CoreExecutor, tokio::task::JoinError> {
+        self.handle.stop();
+        self.task.await
+    }
+}
+
+/// drive runs the executor whenever it is ready until the handle is stopped,
+/// waking periodically to check on timers like request deadlines. The
+/// periodic wake up relies on tokio's timer, so it must be awaited within
+/// a tokio runtime.
+pub async fn drive(mut core: CoreExecutor, handle: DriverHandle) -> CoreExecutor {
+    loop {
+        let timeout = park_timeout(&core);
+        let ready = future::poll_fn(|cx| {
+            if handle.is_stopped() {
+                return Poll::Ready(());
+            }
+            core.poll_ready(cx)
+        });
+        _ = tokio::time::timeout(timeout, ready).await;
+
+        if handle.is_stopped() {
+            break;
+        }
+
+        core.run_all();
+        tokio::task::yield_now().await;
+    }
+
+    debug!("Driver stopped");
+    core
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{
+        app,
+        domains::{self, DomainResponseReceiver, DomainShell, NamedEvent},
+        drivers::{StepDriver, ThreadDriver, TokioDriver},
+        fixtures::{CounterApp, CounterEvents, CounterRequests},
+        servicer,
+    };
+
+    #[test]
+    fn executor_reports_readiness() {
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+
+        // registration notifies waiting drivers once.
+        assert!(executor.is_ready());
+        executor.run_all();
+        assert!(!executor.is_ready());
+
+        let _receiver = shell
+            .do_request(domains::NamedRequest::new(
+                "increment",
+                CounterRequests::Increment,
+            ))
+            .expect("should send request");
+        assert!(executor.is_ready());
+
+        // the count's save request notifies the drivers once more.
+        executor.run_all();
+        assert!(executor.is_ready());
+        executor.run_all();
+        assert!(!executor.is_ready());
+    }
+
+    #[test]
+    fn step_driver_runs_until_idle() {
+        let (executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+        let mut driver = StepDriver::new(executor);
+        driver.run_until_idle(10);
+
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new(
+                "increment",
+                CounterRequests::Increment,
+            ))
+            .expect("should send request");
+
+        assert!(driver.is_ready());
+        assert!(driver.run_until_idle(10) > 0);
+        assert!(!driver.step());
+
+        let response = receiver
+            .try_receive()
+            .expect("should have response")
+            .expect("should succeed");
+        assert_eq!(response.items(), vec![CounterEvents::Incremented(1)]);
+    }
+
+    #[test]
+    fn thread_driver_wakes_on_requests() {
+        let (executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+        let driver = ThreadDriver::spawn(executor).expect("should spawn driver");
+
+        for count in 1..4 {
+            // give the driver time to park between requests.
+            std::thread::sleep(Duration::from_millis(20));
+
+            let receiver = shell
+                .do_request(domains::NamedRequest::new(
+                    "increment",
+                    CounterRequests::Increment,
+                ))
+                .expect("should send request");
+
+            assert_eq!(
+                wait_for(receiver).items(),
+                vec![CounterEvents::Incremented(count)]
+            );
+        }
+
+        driver.stop().expect("should stop driver");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn tokio_driver_runs_executor_as_a_task() {
+        let (executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+        let driver = TokioDriver::spawn(executor);
+
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new(
+                "increment",
+                CounterRequests::Increment,
+            ))
+            .expect("should send request");
+
+        let response = tokio::time::timeout(Duration::from_secs(5), receiver.async_receive())
+            .await
+            .expect("should respond in time")
+            .expect("should have response")
+            .expect("should succeed");
+        assert_eq!(response.items(), vec![CounterEvents::Incremented(1)]);
+
+        driver.stop().await.expect("should stop driver");
+    }
+
+    fn wait_for(
+        mut receiver: DomainResponseReceiver<CounterEvents, String>,
+    ) -> NamedEvent<CounterEvents> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Ok(response) = receiver.try_receive() {
+                return response.expect("should succeed");
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        panic!("driver did not respond in time");
+    }
+}
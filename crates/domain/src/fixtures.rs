@@ -0,0 +1,94 @@
+// Module implementing the counter domain the tests of this crate share.
+
+use std::sync::Arc;
+
+use crossbeam::atomic::AtomicCell;
+use ewe_channels::mspc;
+use serde::{Deserialize, Serialize};
+
+use crate::domains::{self, MasterShell};
+
+#[derive(Default, Clone)]
+pub struct Platform {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CounterEvents {
+    Incremented(i16),
+    Saved(i16),
+    Ignored,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CounterRequests {
+    Increment,
+    Save(i16),
+    Noop,
+}
+
+/// CounterApp counts the increments it is requested by `STEP`, answering
+/// and publishing every count and requesting it to be saved.
+#[derive(Clone, Default)]
+pub struct CounterApp<const STEP: i16 = 1> {
+    state: Arc<AtomicCell<i16>>,
+}
+
+/// DoubleCounterApp counts twice as fast as [`CounterApp`], diverging from
+/// its recordings.
+pub type DoubleCounterApp = CounterApp<2>;
+
+impl<const STEP: i16> domains::Domain for CounterApp<STEP> {
+    type Events = CounterEvents;
+    type Requests = CounterRequests;
+    type Platform = Platform;
+    type Error = String;
+
+    fn handle_request(
+        &self,
+        req: domains::NamedRequest<Self::Requests>,
+        mut chan: mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+        mut shell: impl MasterShell<
+            Events = Self::Events,
+            Requests = Self::Requests,
+            Platform = Self::Platform,
+            Error = Self::Error,
+        >,
+    ) {
+        let event = match req.item() {
+            CounterRequests::Increment => {
+                let next = self.state.load() + STEP;
+                self.state.store(next);
+                CounterEvents::Incremented(next)
+            }
+            CounterRequests::Noop => CounterEvents::Ignored,
+            // saving is left to whoever answers the domain's requests.
+            CounterRequests::Save(_) => return,
+        };
+
+        let event = req.to_one(event);
+        shell.send_all(event.clone()).expect("should send event");
+        chan.try_send(event).expect("should respond");
+        _ = chan.close();
+    }
+
+    fn handle_event(
+        &self,
+        events: domains::NamedEvent<Self::Events>,
+        mut shell: impl MasterShell<
+            Events = Self::Events,
+            Requests = Self::Requests,
+            Platform = Self::Platform,
+            Error = Self::Error,
+        >,
+    ) {
+        for event in events.items() {
+            if let CounterEvents::Incremented(count) = event {
+                shell
+                    .send_request(domains::NamedRequest::new(
+                        "save_count",
+                        CounterRequests::Save(count),
+                    ))
+                    .expect("should send request");
+            }
+        }
+    }
+}
@@ -0,0 +1,572 @@
+// Module implementing introspection of a running domain and a debug endpoint serving it.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use ewe_channels::{broadcast, executor};
+use serde::Serialize;
+use tracing::{debug, error};
+
+use crate::{
+    domains::{DomainResponse, NamedEvent, NamedRequest},
+    limits::RequestQueue,
+    pending_chan::{PendingChannelsRegistry, PendingResponses},
+    recording,
+    scheduler::Scheduler,
+    supervisor::Supervisor,
+};
+
+const DEFAULT_RECENT_EVENTS_CAPACITY: usize = 32;
+
+// how long the debug server sleeps when no connection is waiting.
+const ACCEPT_POLL: Duration = Duration::from_millis(20);
+
+// requests to the debug server are a request line and a few headers.
+const MAX_DEBUG_REQUEST_SIZE: usize = 8 * 1024;
+
+// how long a connection may take to send its whole request, the server
+// answers one connection at a time so a slow client must not hold it.
+const DEBUG_REQUEST_DEADLINE: Duration = Duration::from_secs(1);
+
+/// RecentEvent is an event the domain sent to its listeners.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RecentEvent<E> {
+    pub id: String,
+    pub items: Vec<E>,
+    pub timestamp_ms: u64,
+}
+
+/// RecentEvents keeps the last events sent by the domain, dropping the
+/// oldest once full, clones share the same events.
+pub(crate) struct RecentEvents<E> {
+    capacity: usize,
+    events: Arc<Mutex<VecDeque<RecentEvent<E>>>>,
+}
+
+impl<E> Clone for RecentEvents<E> {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            events: self.events.clone(),
+        }
+    }
+}
+
+impl<E: Clone> RecentEvents<E> {
+    pub(crate) fn new() -> Self {
+        Self {
+            capacity: DEFAULT_RECENT_EVENTS_CAPACITY,
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(
+                DEFAULT_RECENT_EVENTS_CAPACITY,
+            ))),
+        }
+    }
+
+    pub(crate) fn push(&self, event: &NamedEvent<E>) {
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(RecentEvent {
+            id: event.id().0,
+            items: event.items(),
+            timestamp_ms: recording::now_millis(),
+        });
+    }
+
+    pub(crate) fn list(&self) -> Vec<RecentEvent<E>> {
+        self.events.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// DomainSnapshot describes the state of a running domain at the time it
+/// was taken.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DomainSnapshot<E> {
+    pub taken_at_ms: u64,
+
+    /// ids of requests sent via [`crate::domains::DomainShell::do_request`]
+    /// whose response is not finished.
+    pub pending_responses: Vec<String>,
+
+    /// ids of requests sent via [`crate::domains::MasterShell::send_request`]
+    /// no one responded to yet.
+    pub pending_replies: Vec<String>,
+
+    pub in_flight: Vec<String>,
+    pub queue_depth: usize,
+    pub event_subscribers: usize,
+    pub request_subscribers: usize,
+    pub queued_tasks: usize,
+    pub live_tasks: usize,
+    pub schedules: usize,
+    pub stopped: bool,
+    pub recent_events: Vec<RecentEvent<E>>,
+}
+
+/// Introspector takes [`DomainSnapshot`]s of a running domain, it holds
+/// only shared handles so it can be moved to another thread while the
+/// domain keeps running.
+pub struct Introspector<
+    E: Clone + Send + Sync + 'static,
+    R: Clone + Send + Sync + 'static,
+    Err: Send + 'static,
+> {
+    pub(crate) response_registry: PendingChannelsRegistry<NamedEvent<E>>,
+    pub(crate) pending_responses: PendingResponses<DomainResponse<E, Err>>,
+    pub(crate) request_queue: RequestQueue<R>,
+    pub(crate) event_broadcast: broadcast::Broadcast<NamedEvent<E>>,
+    pub(crate) request_broadcast: broadcast::Broadcast<NamedRequest<R>>,
+    pub(crate) executor: Arc<executor::Executor<NamedEvent<E>>>,
+    pub(crate) recent_events: RecentEvents<E>,
+    pub(crate) supervisor: Supervisor,
+    pub(crate) scheduler: Scheduler<R>,
+}
+
+impl<E, R, Err> Clone for Introspector<E, R, Err>
+where
+    E: Clone + Send + Sync + 'static,
+    R: Clone + Send + Sync + 'static,
+    Err: Send + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            response_registry: self.response_registry.clone(),
+            pending_responses: self.pending_responses.clone(),
+            request_queue: self.request_queue.clone(),
+            event_broadcast: self.event_broadcast.clone(),
+            request_broadcast: self.request_broadcast.clone(),
+            executor: self.executor.clone(),
+            recent_events: self.recent_events.clone(),
+            supervisor: self.supervisor.clone(),
+            scheduler: self.scheduler.clone(),
+        }
+    }
+}
+
+impl<E, R, Err> Introspector<E, R, Err>
+where
+    E: Clone + Send + Sync + 'static,
+    R: Clone + Send + Sync + 'static,
+    Err: Send + 'static,
+{
+    pub fn snapshot(&self) -> DomainSnapshot<E> {
+        let responses = &self.pending_responses;
+        self.request_queue.settle(|id| responses.has(id));
+
+        let ids = |ids: Vec<crate::domains::Id>| -> Vec<String> {
+            let mut ids: Vec<String> = ids.into_iter().map(|id| id.0).collect();
+            ids.sort();
+            ids
+        };
+
+        DomainSnapshot {
+            taken_at_ms: recording::now_millis(),
+            pending_responses: ids(self.pending_responses.ids()),
+            pending_replies: ids(self.response_registry.ids()),
+            in_flight: ids(self.request_queue.in_flight()),
+            queue_depth: self.request_queue.depth(),
+            event_subscribers: self.event_broadcast.subscriber_count(),
+            request_subscribers: self.request_broadcast.subscriber_count(),
+            queued_tasks: self.executor.queued_tasks(),
+            live_tasks: self.executor.live_tasks(),
+            schedules: self.scheduler.list().len(),
+            stopped: self.supervisor.is_stopped(),
+            recent_events: self.recent_events.list(),
+        }
+    }
+}
+
+/// DebugServer serves the [`DomainSnapshot`] of a running domain as JSON
+/// over HTTP on a loopback address, e.g `curl http://127.0.0.1:7070/`.
+///
+/// It is meant for local debugging of a hanging domain and refuses to
+/// listen on anything but a loopback address. Requests naming any other
+/// host than a loopback address or `localhost` are refused, so pages
+/// rebinding their own domain to the loopback address cannot read it.
+pub struct DebugServer {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl DebugServer {
+    pub fn bind<E, R, Err>(
+        address: impl Into<SocketAddr>,
+        introspector: Introspector<E, R, Err>,
+    ) -> io::Result<Self>
+    where
+        E: Clone + Send + Sync + Serialize + 'static,
+        R: Clone + Send + Sync + 'static,
+        Err: Send + 'static,
+    {
+        let address = address.into();
+        if !address.ip().is_loopback() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "debug server only listens on loopback addresses",
+            ));
+        }
+
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let server_stopped = stopped.clone();
+        let thread = thread::Builder::new()
+            .name(String::from("ewe-domain-debug"))
+            .spawn(move || {
+                while !server_stopped.load(Ordering::SeqCst) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            if let Err(err) = respond(stream, &introspector) {
+                                error!("DebugServer failed to respond: {}", err);
+                            }
+                        }
+                        Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                            thread::sleep(ACCEPT_POLL);
+                        }
+                        Err(err) => error!("DebugServer failed to accept: {}", err),
+                    }
+                }
+                debug!("DebugServer stopped");
+            })?;
+
+        Ok(Self {
+            address,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    /// local_addr returns the address the server listens on, useful when
+    /// bound to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+    }
+}
+
+impl Drop for DebugServer {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn respond<E, R, Err>(
+    mut stream: TcpStream,
+    introspector: &Introspector<E, R, Err>,
+) -> io::Result<()>
+where
+    E: Clone + Send + Sync + Serialize + 'static,
+    R: Clone + Send + Sync + 'static,
+    Err: Send + 'static,
+{
+    stream.set_nonblocking(false)?;
+
+    let started = Instant::now();
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let remaining = DEBUG_REQUEST_DEADLINE.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "debug request not received within deadline",
+            ));
+        }
+        stream.set_read_timeout(Some(remaining))?;
+
+        let read = stream.read(&mut buffer)?;
+        if read == 0 || request.len() + read > MAX_DEBUG_REQUEST_SIZE {
+            break;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut lines = request.lines();
+    let mut request_line = lines.next().unwrap_or_default().split(' ');
+    let local_host = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+        .is_some_and(|(_, host)| is_loopback_host(host.trim()));
+
+    let (status, body) = match (request_line.next(), request_line.next()) {
+        _ if !local_host => (
+            "403 Forbidden",
+            serde_json::json!({ "error": "host not allowed" }).to_string(),
+        ),
+        (Some("GET"), Some("/")) => match serde_json::to_string(&introspector.snapshot()) {
+            Ok(body) => ("200 OK", body),
+            Err(err) => (
+                "500 Internal Server Error",
+                serde_json::json!({ "error": err.to_string() }).to_string(),
+            ),
+        },
+        _ => (
+            "404 Not Found",
+            serde_json::json!({ "error": "not found" }).to_string(),
+        ),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+// is_loopback_host returns true if the Host header value names the loopback
+// address or `localhost`, with or without a port.
+fn is_loopback_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    name.eq_ignore_ascii_case("localhost")
+        || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read, Write},
+        net::{SocketAddr, TcpStream},
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use ewe_channels::mspc;
+    use serde::Serialize;
+
+    use crate::{
+        app,
+        domains::{self, DomainShell, MasterShell},
+        fixtures::Platform,
+        introspection::DebugServer,
+        servicer,
+    };
+
+    #[test]
+    fn snapshot_reports_pending_requests_and_recent_events() {
+        let (mut executor, server) = app::create::<LookupApp>();
+        let introspector = server.introspector();
+        let mut shell = servicer::create_shell(server);
+        let _events = shell.listen().unwrap();
+
+        let _asked = shell
+            .do_request(domains::NamedRequest::new("ask", LookupRequests::Ask))
+            .expect("should send request");
+        let _announced = shell
+            .do_request(domains::NamedRequest::new(
+                "announce",
+                LookupRequests::Announce,
+            ))
+            .expect("should send request");
+        executor.run_all();
+
+        let snapshot = introspector.snapshot();
+        assert_eq!(snapshot.pending_responses, vec![String::from("ask")]);
+        assert_eq!(snapshot.pending_replies, vec![String::from("lookup")]);
+        assert_eq!(snapshot.in_flight, vec![String::from("ask")]);
+        assert_eq!(snapshot.queue_depth, 0);
+        assert_eq!(snapshot.event_subscribers, 1);
+        assert_eq!(snapshot.request_subscribers, 0);
+        assert_eq!(snapshot.live_tasks, 1);
+        assert!(!snapshot.stopped);
+        assert_eq!(
+            snapshot
+                .recent_events
+                .iter()
+                .map(|event| (event.id.as_str(), event.items.clone()))
+                .collect::<Vec<_>>(),
+            vec![("announce", vec![LookupEvents::Announced])]
+        );
+    }
+
+    #[test]
+    fn debug_server_serves_snapshots_as_json() {
+        let (_executor, server) = app::create::<LookupApp>();
+        assert_eq!(
+            DebugServer::bind(([0, 0, 0, 0], 0), server.introspector())
+                .err()
+                .map(|err| err.kind()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+
+        let debug_server =
+            DebugServer::bind(([127, 0, 0, 1], 0), server.introspector()).expect("should bind");
+
+        let response = get(debug_server.local_addr(), "/", "localhost");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        let body = response.split("\r\n\r\n").nth(1).expect("should have body");
+        let snapshot: serde_json::Value = serde_json::from_str(body).expect("should be json");
+        assert_eq!(snapshot["queue_depth"], 0);
+        assert_eq!(snapshot["stopped"], false);
+
+        assert!(get(debug_server.local_addr(), "/missing", "localhost").starts_with("HTTP/1.1 404"));
+        debug_server.stop();
+    }
+
+    #[test]
+    fn debug_server_refuses_hosts_other_than_loopback() {
+        let (_executor, server) = app::create::<LookupApp>();
+        let debug_server =
+            DebugServer::bind(([127, 0, 0, 1], 0), server.introspector()).expect("should bind");
+        let address = debug_server.local_addr();
+
+        for host in ["127.0.0.1", "[::1]:7070", "LOCALHOST:7070"] {
+            assert!(get(address, "/", host).starts_with("HTTP/1.1 200 OK"));
+        }
+        for host in ["attacker.example", "attacker.example:7070", "10.0.0.1"] {
+            assert!(get(address, "/", host).starts_with("HTTP/1.1 403"));
+        }
+
+        let mut stream = TcpStream::connect(address).expect("should connect");
+        write!(stream, "GET / HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("should read");
+        assert!(response.starts_with("HTTP/1.1 403"));
+
+        debug_server.stop();
+    }
+
+    #[test]
+    fn debug_server_drops_requests_exceeding_the_deadline() {
+        let (_executor, server) = app::create::<LookupApp>();
+        let debug_server =
+            DebugServer::bind(([127, 0, 0, 1], 0), server.introspector()).expect("should bind");
+        let address = debug_server.local_addr();
+
+        // trickles headers slower than the deadline but faster than any
+        // single read could time out.
+        let mut slow = TcpStream::connect(address).expect("should connect");
+        let mut writer = slow.try_clone().expect("should clone stream");
+        let trickle = thread::spawn(move || {
+            write!(writer, "GET / HTTP/1.1\r\nHost: localhost\r\n").unwrap();
+            for _ in 0..30 {
+                if writer.write_all(b"X-Slow: 1\r\n").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        let started = Instant::now();
+        slow.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut response = Vec::new();
+        _ = slow.read_to_end(&mut response);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(response.is_empty());
+        trickle.join().unwrap();
+
+        assert!(get(address, "/", "localhost").starts_with("HTTP/1.1 200 OK"));
+        debug_server.stop();
+    }
+
+    fn get(address: SocketAddr, path: &str, host: &str) -> String {
+        let mut stream = TcpStream::connect(address).expect("should connect");
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, host).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).expect("should read");
+        response
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+    enum LookupEvents {
+        Announced,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum LookupRequests {
+        Ask,
+        Announce,
+        Lookup,
+    }
+
+    type Waiting = (
+        mspc::SendChannel<domains::NamedEvent<LookupEvents>>,
+        mspc::ReceiveChannel<domains::NamedEvent<LookupEvents>>,
+    );
+
+    #[derive(Clone, Default)]
+    struct LookupApp {
+        waiting: Arc<Mutex<Vec<Waiting>>>,
+    }
+
+    impl domains::Domain for LookupApp {
+        type Events = LookupEvents;
+        type Requests = LookupRequests;
+        type Platform = Platform;
+        type Error = String;
+
+        fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            mut chan: mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+            mut shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            match req.item() {
+                // waits on a lookup no one answers.
+                LookupRequests::Ask => {
+                    let reply = shell
+                        .send_request(domains::NamedRequest::new("lookup", LookupRequests::Lookup))
+                        .expect("should send request");
+                    self.waiting.lock().unwrap().push((chan, reply));
+                }
+                LookupRequests::Announce => {
+                    shell
+                        .send_others(req.to_one(LookupEvents::Announced))
+                        .expect("should send event");
+                    _ = chan.close();
+                }
+                LookupRequests::Lookup => {}
+            }
+        }
+
+        fn handle_event(
+            &self,
+            _events: domains::NamedEvent<Self::Events>,
+            _shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+        }
+    }
+}
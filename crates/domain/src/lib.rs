@@ -1,5 +1,19 @@
 pub mod app;
 pub mod core;
 pub mod domains;
+pub mod drivers;
+#[cfg(test)]
+mod fixtures;
+pub mod introspection;
+pub mod limits;
 pub mod pending_chan;
+pub mod policies;
+pub mod projections;
+pub mod protocol;
+pub mod recording;
+pub mod sagas;
+pub mod scheduler;
 pub mod servicer;
+mod stores;
+pub mod supervisor;
+pub mod testing;
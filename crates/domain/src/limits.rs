@@ -0,0 +1,285 @@
+// Module implementing concurrency limits and queueing of requests to a domain.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use crate::domains::{Id, NamedRequest};
+
+pub type KindFn<R> = Arc<dyn Fn(&R) -> String + Send + Sync>;
+
+/// ConcurrencyLimits declares how many requests the
+/// [`crate::servicer::DServicer`] hands to its domain at once and how many
+/// may wait for their turn.
+///
+/// A request is in-flight from when its handler is called till its response
+/// is finished, failed or timed out. Requests beyond the limits wait in the
+/// queue, requests arriving to a full queue are rejected with
+/// [`crate::domains::DomainFailure::Rejected`].
+pub struct ConcurrencyLimits<R> {
+    max_in_flight: Option<usize>,
+    max_queued: Option<usize>,
+    kind_of: Option<KindFn<R>>,
+    kind_limits: HashMap<String, usize>,
+}
+
+impl<R> Clone for ConcurrencyLimits<R> {
+    fn clone(&self) -> Self {
+        Self {
+            max_in_flight: self.max_in_flight,
+            max_queued: self.max_queued,
+            kind_of: self.kind_of.clone(),
+            kind_limits: self.kind_limits.clone(),
+        }
+    }
+}
+
+impl<R> Default for ConcurrencyLimits<R> {
+    fn default() -> Self {
+        Self {
+            max_in_flight: None,
+            max_queued: None,
+            kind_of: None,
+            kind_limits: HashMap::new(),
+        }
+    }
+}
+
+impl<R> ConcurrencyLimits<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.max_in_flight = Some(max);
+        self
+    }
+
+    pub fn with_max_queued(mut self, max: usize) -> Self {
+        self.max_queued = Some(max);
+        self
+    }
+
+    /// with_kind provides how requests are grouped for the per-kind limits
+    /// given via [`ConcurrencyLimits::with_kind_limit`], usually the name of
+    /// the request's variant.
+    pub fn with_kind(mut self, kind_of: impl Fn(&R) -> String + Send + Sync + 'static) -> Self {
+        self.kind_of = Some(Arc::new(kind_of));
+        self
+    }
+
+    /// with_kind_limit caps how many requests of the giving kind are
+    /// in-flight at once.
+    pub fn with_kind_limit(mut self, kind: impl Into<String>, max: usize) -> Self {
+        self.kind_limits.insert(kind.into(), max);
+        self
+    }
+
+    pub fn max_in_flight(&self) -> Option<usize> {
+        self.max_in_flight
+    }
+
+    pub fn max_queued(&self) -> Option<usize> {
+        self.max_queued
+    }
+
+    pub fn kind_limit(&self, kind: &str) -> Option<usize> {
+        self.kind_limits.get(kind).copied()
+    }
+
+    fn kind(&self, req: &R) -> Option<String> {
+        self.kind_of.as_ref().map(|kind_of| kind_of(req))
+    }
+}
+
+struct InFlight {
+    id: Id,
+    kind: Option<String>,
+}
+
+struct QueueState<R: Clone> {
+    limits: ConcurrencyLimits<R>,
+    queued: VecDeque<NamedRequest<R>>,
+    in_flight: Vec<InFlight>,
+}
+
+/// RequestQueue holds the requests waiting to be handled by a domain under
+/// its [`ConcurrencyLimits`], clones share the same queue.
+pub(crate) struct RequestQueue<R: Clone> {
+    state: Arc<Mutex<QueueState<R>>>,
+}
+
+impl<R: Clone> Clone for RequestQueue<R> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<R: Clone> RequestQueue<R> {
+    pub(crate) fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(QueueState {
+                limits: ConcurrencyLimits::default(),
+                queued: VecDeque::new(),
+                in_flight: Vec::new(),
+            })),
+        }
+    }
+
+    pub(crate) fn set_limits(&self, limits: ConcurrencyLimits<R>) {
+        self.state.lock().unwrap().limits = limits;
+    }
+
+    /// push queues the request, returning it back if the queue is full.
+    pub(crate) fn push(&self, req: NamedRequest<R>) -> Result<(), NamedRequest<R>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(max) = state.limits.max_queued {
+            if state.queued.len() >= max {
+                return Err(req);
+            }
+        }
+
+        state.queued.push_back(req);
+        Ok(())
+    }
+
+    /// settle forgets the in-flight requests which are no longer pending.
+    pub(crate) fn settle(&self, is_pending: impl Fn(&Id) -> bool) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight.retain(|request| is_pending(&request.id));
+    }
+
+    /// next takes the first queued request the limits allow to be handled,
+    /// marking it in-flight. Requests held back by their kind's limit do not
+    /// hold back requests of other kinds.
+    pub(crate) fn next(&self) -> Option<NamedRequest<R>> {
+        let mut state = self.state.lock().unwrap();
+        let index = state.next_allowed()?;
+        let req = state.queued.remove(index)?;
+
+        let kind = state.limits.kind(&req.item());
+        state.in_flight.push(InFlight { id: req.id(), kind });
+        Some(req)
+    }
+
+    /// has_next returns true if a queued request is allowed to be handled.
+    pub(crate) fn has_next(&self) -> bool {
+        self.state.lock().unwrap().next_allowed().is_some()
+    }
+
+    /// drain takes all queued requests regardless of the limits.
+    pub(crate) fn drain(&self) -> Vec<NamedRequest<R>> {
+        self.state.lock().unwrap().queued.drain(..).collect()
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.state.lock().unwrap().queued.len()
+    }
+
+    pub(crate) fn in_flight(&self) -> Vec<Id> {
+        let state = self.state.lock().unwrap();
+        state
+            .in_flight
+            .iter()
+            .map(|request| request.id.clone())
+            .collect()
+    }
+}
+
+impl<R: Clone> QueueState<R> {
+    fn next_allowed(&self) -> Option<usize> {
+        if let Some(max) = self.limits.max_in_flight {
+            if self.in_flight.len() >= max {
+                return None;
+            }
+        }
+
+        self.queued.iter().position(|req| {
+            let Some(kind) = self.limits.kind(&req.item()) else {
+                return true;
+            };
+            let Some(max) = self.limits.kind_limit(&kind) else {
+                return true;
+            };
+
+            let running = self
+                .in_flight
+                .iter()
+                .filter(|request| request.kind.as_deref() == Some(kind.as_str()))
+                .count();
+            running < max
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        domains::{Id, NamedRequest},
+        limits::{ConcurrencyLimits, RequestQueue},
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Requests {
+        Read,
+        Write,
+    }
+
+    fn kind_of(req: &Requests) -> String {
+        format!("{:?}", req)
+    }
+
+    #[test]
+    fn queue_rejects_requests_beyond_its_depth() {
+        let queue = RequestQueue::new();
+        queue.set_limits(ConcurrencyLimits::new().with_max_queued(1));
+
+        assert!(queue
+            .push(NamedRequest::new("first", Requests::Read))
+            .is_ok());
+        assert_eq!(
+            queue.push(NamedRequest::new("second", Requests::Read)),
+            Err(NamedRequest::new("second", Requests::Read))
+        );
+        assert_eq!(queue.depth(), 1);
+    }
+
+    #[test]
+    fn queue_holds_back_requests_beyond_limits() {
+        let queue = RequestQueue::new();
+        queue.set_limits(
+            ConcurrencyLimits::new()
+                .with_max_in_flight(2)
+                .with_kind(kind_of)
+                .with_kind_limit("Write", 1),
+        );
+
+        for (id, req) in [
+            ("write-1", Requests::Write),
+            ("write-2", Requests::Write),
+            ("read-1", Requests::Read),
+            ("read-2", Requests::Read),
+        ] {
+            queue
+                .push(NamedRequest::new(id, req))
+                .expect("should queue");
+        }
+
+        // the second write waits on the first while reads go ahead.
+        assert_eq!(queue.next().map(|req| req.id()), Some(Id("write-1".into())));
+        assert_eq!(queue.next().map(|req| req.id()), Some(Id("read-1".into())));
+        assert_eq!(queue.next(), None);
+        assert_eq!(
+            queue.in_flight(),
+            vec![Id("write-1".into()), Id("read-1".into())]
+        );
+
+        queue.settle(|id| id.0 != "write-1");
+        assert!(queue.has_next());
+        assert_eq!(queue.next().map(|req| req.id()), Some(Id("write-2".into())));
+        assert_eq!(queue.depth(), 1);
+    }
+}
@@ -1,4 +1,4 @@
-use std::{collections, sync};
+use std::{collections, sync, time::Instant};
 
 use thiserror::Error;
 
@@ -68,6 +68,12 @@ impl<E> PendingChannelsRegistry<E> {
         group_channel
     }
 
+    /// ids returns the ids of the channels awaiting a response.
+    pub fn ids(&self) -> Vec<domains::Id> {
+        let registry = self.pending.lock().unwrap();
+        registry.keys().cloned().collect()
+    }
+
     pub fn resolve(&mut self, id: domains::Id) -> PendingChannelResult<mspc::SendChannel<E>> {
         let mut registry = self.pending.lock().unwrap();
         if !registry.contains_key(&id) {
@@ -94,6 +100,118 @@ impl<E> PendingChannelsRegistry<E> {
     }
 }
 
+struct PendingResponse<T> {
+    sender: mspc::SendChannel<T>,
+    deadline: Option<Instant>,
+}
+
+/// PendingResponses tracks the response channels of requests awaiting
+/// their responses, optionally with a deadline after which the request
+/// is considered timed out.
+///
+/// Unlike [`PendingChannelsRegistry`] the sender stays registered until
+/// the response is finished, allowing different parties (the handler, a
+/// failure or a timeout) to deliver to the same requester.
+pub struct PendingResponses<T> {
+    pending: sync::Arc<sync::Mutex<collections::HashMap<domains::Id, PendingResponse<T>>>>,
+}
+
+impl<T> Clone for PendingResponses<T> {
+    fn clone(&self) -> Self {
+        Self {
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<T> Default for PendingResponses<T> {
+    fn default() -> Self {
+        Self {
+            pending: sync::Arc::new(sync::Mutex::new(collections::HashMap::new())),
+        }
+    }
+}
+
+impl<T> PendingResponses<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register a pending response for the giving id returning the channel
+    /// the requester receives the response from.
+    pub fn register(&self, id: domains::Id, deadline: Option<Instant>) -> mspc::ReceiveChannel<T> {
+        let (sender, receiver) = mspc::create::<T>();
+        let mut registry = self.pending.lock().unwrap();
+        registry.insert(id, PendingResponse { sender, deadline });
+        receiver
+    }
+
+    pub fn has(&self, id: &domains::Id) -> bool {
+        let registry = self.pending.lock().unwrap();
+        registry.contains_key(id)
+    }
+
+    /// send delivers a response to the requester keeping the response open.
+    pub fn send(&self, id: &domains::Id, value: T) -> PendingChannelResult<()> {
+        let mut registry = self.pending.lock().unwrap();
+        let Some(entry) = registry.get_mut(id) else {
+            return Err(PendingChannelError::NotFound(id.0.clone()));
+        };
+
+        entry
+            .sender
+            .try_send(value)
+            .map_err(|_| PendingChannelError::ClosedSender(id.clone()))
+    }
+
+    /// finish delivers the last response to the requester, if any,
+    /// closing the response channel.
+    pub fn finish(&self, id: &domains::Id, value: Option<T>) -> PendingChannelResult<()> {
+        let Some(mut entry) = self.pending.lock().unwrap().remove(id) else {
+            return Err(PendingChannelError::NotFound(id.0.clone()));
+        };
+
+        let sent = match value {
+            Some(value) => entry
+                .sender
+                .try_send(value)
+                .map_err(|_| PendingChannelError::ClosedSender(id.clone())),
+            None => Ok(()),
+        };
+        _ = entry.sender.close();
+        sent
+    }
+
+    /// expired_ids returns the ids of the pending responses whose deadline
+    /// passed, leaving them pending till they are finished.
+    pub fn expired_ids(&self, now: Instant) -> Vec<domains::Id> {
+        let registry = self.pending.lock().unwrap();
+        registry
+            .iter()
+            .filter(|(_, entry)| entry.deadline.is_some_and(|deadline| deadline <= now))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
+    /// next_deadline returns the earliest deadline of the pending responses.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        let registry = self.pending.lock().unwrap();
+        registry.values().filter_map(|entry| entry.deadline).min()
+    }
+
+    pub fn ids(&self) -> Vec<domains::Id> {
+        let registry = self.pending.lock().unwrap();
+        registry.keys().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        let mut registry = self.pending.lock().unwrap();
+        for (_, mut entry) in registry.drain() {
+            _ = entry.sender.close();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use ewe_channels::mspc;
@@ -162,4 +280,42 @@ mod tests {
 
         assert!(!registry.has(target_id));
     }
+
+    #[test]
+    fn pending_responses_deliver_until_finished() {
+        let responses = pending_chan::PendingResponses::<String>::new();
+        let target_id = domains::Id(String::from("server_1"));
+
+        let mut receiver = responses.register(target_id.clone(), None);
+        responses
+            .send(&target_id, String::from("first"))
+            .expect("should send");
+        responses
+            .finish(&target_id, Some(String::from("last")))
+            .expect("should finish");
+
+        assert!(!responses.has(&target_id));
+        assert_eq!(receiver.try_receive().unwrap(), "first");
+        assert_eq!(receiver.try_receive().unwrap(), "last");
+        assert!(matches!(
+            receiver.try_receive(),
+            Err(mspc::ChannelError::Closed)
+        ));
+        assert!(responses.finish(&target_id, None).is_err());
+    }
+
+    #[test]
+    fn pending_responses_report_expired_deadlines() {
+        let responses = pending_chan::PendingResponses::<String>::new();
+        let now = std::time::Instant::now();
+
+        _ = responses.register(domains::Id(String::from("expired")), Some(now));
+        _ = responses.register(domains::Id(String::from("waiting")), None);
+
+        assert_eq!(responses.next_deadline(), Some(now));
+        assert_eq!(
+            responses.expired_ids(now),
+            vec![domains::Id(String::from("expired"))]
+        );
+    }
 }
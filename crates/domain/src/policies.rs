@@ -0,0 +1,795 @@
+// Module implementing declarative failure policies for use-cases.
+
+use std::{
+    fmt::Display,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ewe_channels::mspc::{self, ChannelError};
+use tracing::{debug, error};
+
+use crate::domains::{NamedEvent, NamedRequest};
+
+const DEFAULT_RETRY_MULTIPLIER: u32 = 2;
+
+/// PolicyEvent describes the decisions a [`crate::domains::UseCaseExecutor`]
+/// took while enforcing a [`UseCasePolicy`], these are delivered on the
+/// domain's [`crate::domains::DomainShell::listen`] stream via the domain's
+/// event type using its `From<PolicyEvent>` implementation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolicyEvent {
+    /// The request failed and will be retried after the given backoff.
+    Retrying { attempt: u32, backoff: Duration },
+
+    /// The attempt did not finish its response within the attempt timeout
+    /// and is considered failed.
+    TimedOut { attempt: u32 },
+
+    /// The request failed and no retries are left.
+    Exhausted { attempts: u32 },
+
+    /// The fallback response was delivered to the requester.
+    FallbackUsed,
+
+    /// The circuit breaker opened after consecutive failures.
+    CircuitOpened { failures: u32 },
+
+    /// The circuit breaker allows a trial request through.
+    CircuitHalfOpened,
+
+    /// The circuit breaker closed after a successful trial request.
+    CircuitClosed,
+
+    /// The request was rejected because the circuit breaker is open.
+    CircuitRejected,
+}
+
+impl Display for PolicyEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyEvent::Retrying { attempt, backoff } => {
+                write!(f, "Retrying(attempt={}, backoff={:?})", attempt, backoff)
+            }
+            PolicyEvent::TimedOut { attempt } => write!(f, "TimedOut(attempt={})", attempt),
+            PolicyEvent::Exhausted { attempts } => write!(f, "Exhausted(attempts={})", attempts),
+            PolicyEvent::FallbackUsed => write!(f, "FallbackUsed"),
+            PolicyEvent::CircuitOpened { failures } => {
+                write!(f, "CircuitOpened(failures={})", failures)
+            }
+            PolicyEvent::CircuitHalfOpened => write!(f, "CircuitHalfOpened"),
+            PolicyEvent::CircuitClosed => write!(f, "CircuitClosed"),
+            PolicyEvent::CircuitRejected => write!(f, "CircuitRejected"),
+        }
+    }
+}
+
+/// RetryPolicy retries failed requests with an exponential backoff
+/// starting at `initial_backoff`, growing by `multiplier` on each attempt
+/// and never exceeding `max_backoff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub multiplier: u32,
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self {
+            max_retries,
+            initial_backoff,
+            max_backoff,
+            multiplier: DEFAULT_RETRY_MULTIPLIER,
+        }
+    }
+
+    pub fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// backoff returns the delay before the giving retry attempt, where the
+    /// first retry is attempt 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .checked_pow(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |delay| delay.min(self.max_backoff))
+    }
+}
+
+/// CircuitBreakerPolicy opens the circuit after `failure_threshold`
+/// consecutive failures, rejecting requests till `reset_after` elapsed
+/// when a single trial request is let through.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitBreakerPolicy {
+    pub failure_threshold: u32,
+    pub reset_after: Duration,
+}
+
+impl CircuitBreakerPolicy {
+    pub fn new(failure_threshold: u32, reset_after: Duration) -> Self {
+        Self {
+            failure_threshold,
+            reset_after,
+        }
+    }
+}
+
+pub type FallbackFn<E, R> = Arc<dyn Fn(&NamedRequest<R>) -> Vec<E> + Send + Sync>;
+
+pub type FailureFn<E> = Arc<dyn Fn(&NamedEvent<E>) -> bool + Send + Sync>;
+
+/// UseCasePolicy declares how a [`crate::domains::UseCaseExecutor`] reacts
+/// to failures of its use-case.
+///
+/// A request is considered failed when the use-case closes the response
+/// channel without delivering any event, delivers an event matching the
+/// failure check provided via [`UseCasePolicy::with_failure_check`] or does
+/// not close it within the [`UseCasePolicy::with_attempt_timeout`].
+///
+/// The events of an attempt are held back till it succeeded, so the
+/// requester only ever sees the events of the successful attempt.
+pub struct UseCasePolicy<E: Clone, R: Clone> {
+    retry: Option<RetryPolicy>,
+    circuit_breaker: Option<CircuitBreakerPolicy>,
+    fallback: Option<FallbackFn<E, R>>,
+    is_failure: Option<FailureFn<E>>,
+    attempt_timeout: Option<Duration>,
+}
+
+impl<E: Clone, R: Clone> Clone for UseCasePolicy<E, R> {
+    fn clone(&self) -> Self {
+        Self {
+            retry: self.retry.clone(),
+            circuit_breaker: self.circuit_breaker.clone(),
+            fallback: self.fallback.clone(),
+            is_failure: self.is_failure.clone(),
+            attempt_timeout: self.attempt_timeout,
+        }
+    }
+}
+
+impl<E: Clone, R: Clone> Default for UseCasePolicy<E, R> {
+    fn default() -> Self {
+        Self {
+            retry: None,
+            circuit_breaker: None,
+            fallback: None,
+            is_failure: None,
+            attempt_timeout: None,
+        }
+    }
+}
+
+impl<E: Clone, R: Clone> UseCasePolicy<E, R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreakerPolicy) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    /// with_fallback provides the events delivered to the requester when
+    /// the request failed and no retries are left or the circuit is open.
+    pub fn with_fallback(
+        mut self,
+        fallback: impl Fn(&NamedRequest<R>) -> Vec<E> + Send + Sync + 'static,
+    ) -> Self {
+        self.fallback = Some(Arc::new(fallback));
+        self
+    }
+
+    /// with_failure_check marks events which indicate the use-case failed
+    /// to process the request.
+    pub fn with_failure_check(
+        mut self,
+        check: impl Fn(&NamedEvent<E>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.is_failure = Some(Arc::new(check));
+        self
+    }
+
+    /// with_attempt_timeout fails attempts whose response is not finished
+    /// within the timeout, counting them against the circuit breaker and
+    /// retrying them like any other failure.
+    pub fn with_attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = Some(timeout);
+        self
+    }
+
+    pub fn attempt_timeout(&self) -> Option<Duration> {
+        self.attempt_timeout
+    }
+
+    pub fn retry(&self) -> Option<&RetryPolicy> {
+        self.retry.as_ref()
+    }
+
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreakerPolicy> {
+        self.circuit_breaker.as_ref()
+    }
+
+    pub fn fallback(&self, req: &NamedRequest<R>) -> Option<Vec<E>> {
+        self.fallback.as_ref().map(|fallback| fallback(req))
+    }
+
+    pub fn is_failure(&self, event: &NamedEvent<E>) -> bool {
+        match &self.is_failure {
+            Some(check) => check(event),
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { trial_running: bool },
+}
+
+/// CircuitBreaker tracks the state of a [`CircuitBreakerPolicy`], a missing
+/// policy gives a breaker that never opens.
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker {
+    policy: Option<CircuitBreakerPolicy>,
+    state: CircuitState,
+}
+
+impl CircuitBreaker {
+    pub fn new(policy: Option<CircuitBreakerPolicy>) -> Self {
+        Self {
+            policy,
+            state: CircuitState::Closed { failures: 0 },
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        matches!(self.state, CircuitState::Open { .. })
+    }
+
+    /// allow decides if a request can be dispatched at the giving instant,
+    /// returning the state changes that occurred along with the decision.
+    pub fn allow(&mut self, now: Instant) -> (bool, Vec<PolicyEvent>) {
+        match self.state {
+            CircuitState::Closed { .. } => (true, vec![]),
+            CircuitState::Open { until } if now >= until => {
+                self.state = CircuitState::HalfOpen {
+                    trial_running: true,
+                };
+                (true, vec![PolicyEvent::CircuitHalfOpened])
+            }
+            CircuitState::Open { .. } => (false, vec![PolicyEvent::CircuitRejected]),
+            CircuitState::HalfOpen { trial_running } => {
+                if trial_running {
+                    return (false, vec![PolicyEvent::CircuitRejected]);
+                }
+                self.state = CircuitState::HalfOpen {
+                    trial_running: true,
+                };
+                (true, vec![])
+            }
+        }
+    }
+
+    pub fn record_success(&mut self) -> Vec<PolicyEvent> {
+        let previous = std::mem::replace(&mut self.state, CircuitState::Closed { failures: 0 });
+        match previous {
+            CircuitState::HalfOpen { .. } => vec![PolicyEvent::CircuitClosed],
+            _ => vec![],
+        }
+    }
+
+    pub fn record_failure(&mut self, now: Instant) -> Vec<PolicyEvent> {
+        let Some(policy) = &self.policy else {
+            return vec![];
+        };
+
+        let failures = match self.state {
+            CircuitState::Closed { failures } => failures + 1,
+            CircuitState::HalfOpen { .. } => policy.failure_threshold,
+            CircuitState::Open { .. } => return vec![],
+        };
+
+        if failures >= policy.failure_threshold {
+            self.state = CircuitState::Open {
+                until: now + policy.reset_after,
+            };
+            return vec![PolicyEvent::CircuitOpened { failures }];
+        }
+
+        self.state = CircuitState::Closed { failures };
+        vec![]
+    }
+}
+
+pub(crate) type NotifyFn<R> = Box<dyn FnMut(&NamedRequest<R>, PolicyEvent) + Send>;
+
+struct PolicyAttempt<E: Clone, R: Clone> {
+    req: Arc<NamedRequest<R>>,
+    attempt: u32,
+    received: Vec<NamedEvent<E>>,
+    retry_at: Option<Instant>,
+    deadline: Option<Instant>,
+    responder: mspc::SendChannel<NamedEvent<E>>,
+    response: Option<mspc::ReceiveChannel<NamedEvent<E>>>,
+}
+
+enum AttemptState {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// PolicyEnforcer applies a [`UseCasePolicy`] to requests dispatched by a
+/// [`crate::domains::UseCaseExecutor`], the use-case answers into a channel
+/// owned by the enforcer which forwards successful responses to the requester.
+pub(crate) struct PolicyEnforcer<E: Clone, R: Clone> {
+    policy: UseCasePolicy<E, R>,
+    breaker: CircuitBreaker,
+    attempts: Vec<PolicyAttempt<E, R>>,
+    notify: NotifyFn<R>,
+}
+
+impl<E: Clone, R: Clone> PolicyEnforcer<E, R> {
+    pub(crate) fn new(policy: UseCasePolicy<E, R>, notify: NotifyFn<R>) -> Self {
+        Self {
+            breaker: CircuitBreaker::new(policy.circuit_breaker.clone()),
+            attempts: Vec::new(),
+            policy,
+            notify,
+        }
+    }
+
+    fn emit(&mut self, req: &NamedRequest<R>, events: Vec<PolicyEvent>) {
+        for event in events {
+            debug!("UseCase policy for {}: {}", req, event);
+            (self.notify)(req, event);
+        }
+    }
+
+    pub(crate) fn submit(
+        &mut self,
+        req: Arc<NamedRequest<R>>,
+        responder: mspc::SendChannel<NamedEvent<E>>,
+        now: Instant,
+        dispatch: &mut dyn FnMut(Arc<NamedRequest<R>>, mspc::SendChannel<NamedEvent<E>>),
+    ) {
+        let mut attempt = PolicyAttempt {
+            req,
+            attempt: 0,
+            received: Vec::new(),
+            retry_at: Some(now),
+            deadline: None,
+            responder,
+            response: None,
+        };
+
+        if self.start(&mut attempt, now, dispatch) {
+            self.attempts.push(attempt);
+        }
+    }
+
+    /// poll checks the responses of all in-flight attempts and dispatches
+    /// retries whose backoff elapsed.
+    pub(crate) fn poll(
+        &mut self,
+        now: Instant,
+        dispatch: &mut dyn FnMut(Arc<NamedRequest<R>>, mspc::SendChannel<NamedEvent<E>>),
+    ) {
+        let attempts = std::mem::take(&mut self.attempts);
+        for mut attempt in attempts {
+            let keep = match attempt.retry_at {
+                Some(retry_at) if now >= retry_at => self.start(&mut attempt, now, dispatch),
+                Some(_) => true,
+                None => self.check(&mut attempt, now),
+            };
+
+            if keep {
+                self.attempts.push(attempt);
+            }
+        }
+    }
+
+    pub(crate) fn in_flight(&self) -> usize {
+        self.attempts.len()
+    }
+
+    /// is_due returns true if an in-flight attempt has responses to read,
+    /// or its backoff or attempt timeout elapsed.
+    pub(crate) fn is_due(&mut self, now: Instant) -> bool {
+        self.attempts
+            .iter_mut()
+            .any(|attempt| match attempt.retry_at {
+                Some(retry_at) => now >= retry_at,
+                None => {
+                    attempt.deadline.is_some_and(|deadline| now >= deadline)
+                        || attempt.response.as_mut().map_or(true, |receiver| {
+                            !matches!(receiver.is_empty(), Ok(true))
+                                || !matches!(receiver.is_closed(), Ok(false))
+                        })
+                }
+            })
+    }
+
+    /// next_wake returns the earliest instant an in-flight attempt is due
+    /// to be retried or to time out.
+    pub(crate) fn next_wake(&self) -> Option<Instant> {
+        self.attempts
+            .iter()
+            .filter_map(|attempt| attempt.retry_at.or(attempt.deadline))
+            .min()
+    }
+
+    // start dispatches the next attempt of the request, returning false
+    // if the request was concluded instead.
+    fn start(
+        &mut self,
+        attempt: &mut PolicyAttempt<E, R>,
+        now: Instant,
+        dispatch: &mut dyn FnMut(Arc<NamedRequest<R>>, mspc::SendChannel<NamedEvent<E>>),
+    ) -> bool {
+        let (allowed, events) = self.breaker.allow(now);
+        self.emit(&attempt.req, events);
+
+        if !allowed {
+            self.conclude_with_fallback(attempt);
+            return false;
+        }
+
+        let (sender, receiver) = mspc::create();
+        attempt.attempt += 1;
+        attempt.retry_at = None;
+        attempt.deadline = self.policy.attempt_timeout.map(|timeout| now + timeout);
+        attempt.response = Some(receiver);
+        dispatch(attempt.req.clone(), sender);
+        true
+    }
+
+    // check reads delivered responses, returning false if the request was concluded.
+    fn check(&mut self, attempt: &mut PolicyAttempt<E, R>, now: Instant) -> bool {
+        let state = match attempt.response.take() {
+            Some(mut receiver) => {
+                let state = self.read_responses(&mut receiver, attempt);
+                attempt.response = Some(receiver);
+                state
+            }
+            None => AttemptState::Failed,
+        };
+
+        let state = match state {
+            AttemptState::Pending if attempt.deadline.is_some_and(|deadline| now >= deadline) => {
+                let timed_out = PolicyEvent::TimedOut {
+                    attempt: attempt.attempt,
+                };
+                self.emit(&attempt.req, vec![timed_out]);
+                AttemptState::Failed
+            }
+            state => state,
+        };
+
+        match state {
+            AttemptState::Pending => true,
+            AttemptState::Succeeded => {
+                let events = self.breaker.record_success();
+                self.emit(&attempt.req, events);
+                for event in attempt.received.drain(..) {
+                    if let Err(err) = attempt.responder.try_send(event) {
+                        error!("UseCase policy failed to forward response: {}", err);
+                    }
+                }
+                _ = attempt.responder.close();
+                false
+            }
+            AttemptState::Failed => {
+                attempt.response = None;
+                attempt.received.clear();
+
+                let events = self.breaker.record_failure(now);
+                self.emit(&attempt.req, events);
+
+                let backoff = match &self.policy.retry {
+                    Some(retry) if attempt.attempt <= retry.max_retries => {
+                        Some(retry.backoff(attempt.attempt))
+                    }
+                    _ => None,
+                };
+
+                match backoff {
+                    Some(backoff) if !self.breaker.is_open() => {
+                        attempt.retry_at = Some(now + backoff);
+                        self.emit(
+                            &attempt.req,
+                            vec![PolicyEvent::Retrying {
+                                attempt: attempt.attempt,
+                                backoff,
+                            }],
+                        );
+                        true
+                    }
+                    _ => {
+                        self.emit(
+                            &attempt.req,
+                            vec![PolicyEvent::Exhausted {
+                                attempts: attempt.attempt,
+                            }],
+                        );
+                        self.conclude_with_fallback(attempt);
+                        false
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_responses(
+        &self,
+        receiver: &mut mspc::ReceiveChannel<NamedEvent<E>>,
+        attempt: &mut PolicyAttempt<E, R>,
+    ) -> AttemptState {
+        loop {
+            match receiver.try_receive() {
+                Ok(event) => {
+                    if self.policy.is_failure(&event) {
+                        return AttemptState::Failed;
+                    }
+                    attempt.received.push(event);
+                }
+                Err(ChannelError::ReceivedNoData) => return AttemptState::Pending,
+                Err(_) if !attempt.received.is_empty() => return AttemptState::Succeeded,
+                Err(_) => return AttemptState::Failed,
+            }
+        }
+    }
+
+    fn conclude_with_fallback(&mut self, attempt: &mut PolicyAttempt<E, R>) {
+        if let Some(events) = self.policy.fallback(&attempt.req) {
+            if let Err(err) = attempt.responder.try_send(attempt.req.to(events)) {
+                error!("UseCase policy failed to deliver fallback: {}", err);
+            }
+            self.emit(&attempt.req, vec![PolicyEvent::FallbackUsed]);
+        }
+        _ = attempt.responder.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
+    };
+
+    use ewe_channels::mspc;
+
+    use crate::{
+        domains::{NamedEvent, NamedRequest},
+        policies::{
+            CircuitBreaker, CircuitBreakerPolicy, PolicyEnforcer, PolicyEvent, RetryPolicy,
+            UseCasePolicy,
+        },
+    };
+
+    type Dispatched = Vec<mspc::SendChannel<NamedEvent<String>>>;
+
+    fn enforcer(
+        policy: UseCasePolicy<String, String>,
+    ) -> (PolicyEnforcer<String, String>, Arc<Mutex<Vec<PolicyEvent>>>) {
+        let notified = Arc::new(Mutex::new(Vec::new()));
+        let recorder = notified.clone();
+        let enforcer = PolicyEnforcer::new(
+            policy,
+            Box::new(move |_, event| recorder.lock().unwrap().push(event)),
+        );
+        (enforcer, notified)
+    }
+
+    fn received(receiver: &mut mspc::ReceiveChannel<NamedEvent<String>>) -> Vec<String> {
+        let mut items = Vec::new();
+        while let Ok(event) = receiver.try_receive() {
+            items.extend(event.items());
+        }
+        items
+    }
+
+    #[test]
+    fn enforcer_should_only_forward_events_of_the_successful_attempt() {
+        let policy = UseCasePolicy::default()
+            .with_retry(RetryPolicy::new(1, Duration::ZERO, Duration::ZERO))
+            .with_failure_check(|event| event.items().contains(&"failed".to_string()));
+        let (mut enforcer, _) = enforcer(policy);
+
+        let mut dispatched: Dispatched = Vec::new();
+        let mut dispatch = |_, sender| dispatched.push(sender);
+
+        let (responder, mut requester) = mspc::create();
+        let req = Arc::new(NamedRequest::new("1", "render".to_string()));
+        let now = Instant::now();
+        enforcer.submit(req, responder, now, &mut dispatch);
+
+        let mut first = dispatched.remove(0);
+        first
+            .try_send(NamedEvent::new("1", vec!["partial".to_string()]))
+            .expect("should send");
+        first
+            .try_send(NamedEvent::new("1", vec!["failed".to_string()]))
+            .expect("should send");
+
+        let mut dispatch = |_, sender| dispatched.push(sender);
+        enforcer.poll(now, &mut dispatch);
+        enforcer.poll(now, &mut dispatch);
+        assert!(received(&mut requester).is_empty());
+
+        let mut second = dispatched.remove(0);
+        second
+            .try_send(NamedEvent::new("1", vec!["first".to_string()]))
+            .expect("should send");
+        second
+            .try_send(NamedEvent::new("1", vec!["second".to_string()]))
+            .expect("should send");
+        second.close().expect("should close");
+
+        enforcer.poll(now, &mut |_, _| {});
+        assert_eq!(enforcer.in_flight(), 0);
+        assert_eq!(
+            received(&mut requester),
+            vec!["first".to_string(), "second".to_string()]
+        );
+    }
+
+    #[test]
+    fn enforcer_should_fail_attempts_exceeding_the_attempt_timeout() {
+        let policy = UseCasePolicy::default()
+            .with_attempt_timeout(Duration::from_millis(50))
+            .with_fallback(|_| vec!["fallback".to_string()]);
+        let (mut enforcer, notified) = enforcer(policy);
+
+        let mut dispatched: Dispatched = Vec::new();
+        let (responder, mut requester) = mspc::create();
+        let req = Arc::new(NamedRequest::new("1", "render".to_string()));
+        let now = Instant::now();
+        enforcer.submit(req, responder, now, &mut |_, sender| {
+            dispatched.push(sender)
+        });
+
+        let mut stalled = dispatched.remove(0);
+        stalled
+            .try_send(NamedEvent::new("1", vec!["stalled".to_string()]))
+            .expect("should send");
+
+        enforcer.poll(now + Duration::from_millis(10), &mut |_, _| {});
+        assert_eq!(enforcer.in_flight(), 1);
+
+        enforcer.poll(now + Duration::from_millis(60), &mut |_, _| {});
+        assert_eq!(enforcer.in_flight(), 0);
+        assert_eq!(received(&mut requester), vec!["fallback".to_string()]);
+        assert_eq!(
+            notified.lock().unwrap().clone(),
+            vec![
+                PolicyEvent::TimedOut { attempt: 1 },
+                PolicyEvent::Exhausted { attempts: 1 },
+                PolicyEvent::FallbackUsed,
+            ]
+        );
+    }
+
+    #[test]
+    fn enforcer_should_only_be_due_once_replies_arrive_or_timers_elapse() {
+        let policy = UseCasePolicy::default()
+            .with_retry(RetryPolicy::new(
+                1,
+                Duration::from_millis(100),
+                Duration::from_millis(100),
+            ))
+            .with_attempt_timeout(Duration::from_millis(50));
+        let (mut enforcer, _) = enforcer(policy);
+
+        let mut dispatched: Dispatched = Vec::new();
+        let (responder, _requester) = mspc::create();
+        let req = Arc::new(NamedRequest::new("1", "render".to_string()));
+        let now = Instant::now();
+        enforcer.submit(req, responder, now, &mut |_, sender| {
+            dispatched.push(sender)
+        });
+
+        // waiting on the use-case to reply.
+        assert!(!enforcer.is_due(now));
+        assert_eq!(enforcer.next_wake(), Some(now + Duration::from_millis(50)));
+        assert!(enforcer.is_due(now + Duration::from_millis(50)));
+
+        // waiting out the backoff after the attempt timed out.
+        let later = now + Duration::from_millis(60);
+        enforcer.poll(later, &mut |_, _| {});
+        assert_eq!(enforcer.in_flight(), 1);
+        assert!(!enforcer.is_due(later));
+        assert_eq!(
+            enforcer.next_wake(),
+            Some(later + Duration::from_millis(100))
+        );
+
+        enforcer.poll(later + Duration::from_millis(100), &mut |_, sender| {
+            dispatched.push(sender)
+        });
+        assert!(!enforcer.is_due(later + Duration::from_millis(100)));
+
+        let mut retried = dispatched.pop().expect("should retry");
+        retried.close().expect("should close");
+        assert!(enforcer.is_due(later + Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn retry_policy_should_backoff_exponentially_up_to_max() {
+        let retry = RetryPolicy::new(5, Duration::from_millis(10), Duration::from_millis(50));
+
+        assert_eq!(retry.backoff(1), Duration::from_millis(10));
+        assert_eq!(retry.backoff(2), Duration::from_millis(20));
+        assert_eq!(retry.backoff(3), Duration::from_millis(40));
+        assert_eq!(retry.backoff(4), Duration::from_millis(50));
+        assert_eq!(retry.backoff(40), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn circuit_breaker_should_open_after_threshold_failures() {
+        let mut breaker =
+            CircuitBreaker::new(Some(CircuitBreakerPolicy::new(2, Duration::from_secs(60))));
+        let now = Instant::now();
+
+        assert!(breaker.record_failure(now).is_empty());
+        assert_eq!(
+            breaker.record_failure(now),
+            vec![PolicyEvent::CircuitOpened { failures: 2 }]
+        );
+        assert!(breaker.is_open());
+        assert_eq!(
+            breaker.allow(now),
+            (false, vec![PolicyEvent::CircuitRejected])
+        );
+    }
+
+    #[test]
+    fn circuit_breaker_should_close_after_successful_trial() {
+        let mut breaker = CircuitBreaker::new(Some(CircuitBreakerPolicy::new(
+            1,
+            Duration::from_millis(10),
+        )));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+
+        let later = now + Duration::from_millis(20);
+        assert_eq!(
+            breaker.allow(later),
+            (true, vec![PolicyEvent::CircuitHalfOpened])
+        );
+        assert_eq!(
+            breaker.allow(later),
+            (false, vec![PolicyEvent::CircuitRejected])
+        );
+        assert_eq!(breaker.record_success(), vec![PolicyEvent::CircuitClosed]);
+        assert_eq!(breaker.allow(later), (true, vec![]));
+    }
+
+    #[test]
+    fn circuit_breaker_without_policy_never_opens() {
+        let mut breaker = CircuitBreaker::new(None);
+        let now = Instant::now();
+
+        for _ in 0..10 {
+            assert!(breaker.record_failure(now).is_empty());
+        }
+        assert_eq!(breaker.allow(now), (true, vec![]));
+    }
+}
@@ -0,0 +1,365 @@
+// Module implementing projections, read models derived from a domain's events.
+
+use std::{
+    future,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use ewe_channels::mspc::{self, ChannelError};
+use tracing::debug;
+
+use crate::{
+    core,
+    domains::{self, DomainResult, DomainShell, NamedEvent},
+};
+
+/// Projection folds the events a domain emits to its listeners into a read
+/// model, allowing consumers like UIs to subscribe to derived state instead
+/// of reconstructing it from raw [`NamedEvent`]s.
+pub trait Projection: Send + 'static {
+    // Enum defining the events the projection is folded from.
+    type Events: Clone + Send + Sync + 'static;
+
+    // The read model produced by the projection.
+    type Model: Clone + PartialEq + Send + 'static;
+
+    /// initial returns the read model before any event was applied.
+    fn initial(&self) -> Self::Model;
+
+    /// apply folds the event into the read model.
+    fn apply(&self, model: &mut Self::Model, event: &NamedEvent<Self::Events>);
+}
+
+struct ModelState<M> {
+    model: M,
+    version: u64,
+    closed: bool,
+    wakers: Vec<Waker>,
+}
+
+/// ReadModel provides access to the current value of a projection and
+/// allows watching for changes to it.
+pub struct ReadModel<M> {
+    state: Arc<Mutex<ModelState<M>>>,
+}
+
+impl<M> Clone for ReadModel<M> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<M: Clone + Send + 'static> ReadModel<M> {
+    fn new(model: M) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(ModelState {
+                model,
+                version: 0,
+                closed: false,
+                wakers: Vec::new(),
+            })),
+        }
+    }
+
+    /// current returns the latest value of the read model.
+    pub fn current(&self) -> M {
+        self.state.lock().unwrap().model.clone()
+    }
+
+    /// version returns how many times the read model changed.
+    pub fn version(&self) -> u64 {
+        self.state.lock().unwrap().version
+    }
+
+    /// is_closed returns true once the domain's event stream has closed,
+    /// after which the read model no longer changes.
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+
+    /// watch returns a [`Watch`] which yields the read model whenever it
+    /// changes after this call.
+    pub fn watch(&self) -> Watch<M> {
+        Watch {
+            seen: self.version(),
+            model: self.clone(),
+        }
+    }
+
+    fn update(&self, model: M) {
+        let mut state = self.state.lock().unwrap();
+        state.model = model;
+        state.version += 1;
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        for waker in state.wakers.drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// Watch is a watch-style channel over a [`ReadModel`], only the latest
+/// value is kept, so a slow watcher skips intermediate values rather than
+/// queueing them.
+pub struct Watch<M> {
+    model: ReadModel<M>,
+    seen: u64,
+}
+
+impl<M: Clone + Send + 'static> Watch<M> {
+    /// has_changed returns true if the read model changed since last seen.
+    pub fn has_changed(&self) -> bool {
+        self.model.version() != self.seen
+    }
+
+    /// try_changed returns the latest read model if it changed since last
+    /// seen, marking it as seen.
+    pub fn try_changed(&mut self) -> Option<M> {
+        let state = self.model.state.lock().unwrap();
+        if state.version == self.seen {
+            return None;
+        }
+
+        self.seen = state.version;
+        Some(state.model.clone())
+    }
+
+    /// changed waits until the read model changes returning its latest
+    /// value, or None once the event stream closed without further changes.
+    pub async fn changed(&mut self) -> Option<M> {
+        future::poll_fn(|cx| {
+            let mut state = self.model.state.lock().unwrap();
+            if state.version != self.seen {
+                self.seen = state.version;
+                return Poll::Ready(Some(state.model.clone()));
+            }
+
+            if state.closed {
+                return Poll::Ready(None);
+            }
+
+            // polling again must not grow the wakers of the read model.
+            match state
+                .wakers
+                .iter_mut()
+                .find(|waker| waker.will_wake(cx.waker()))
+            {
+                Some(waker) => *waker = cx.waker().clone(),
+                None => state.wakers.push(cx.waker().clone()),
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// ProjectionExecutor applies the events received from the domain to its
+/// projection everytime the [`core::CoreExecutor`] runs.
+pub struct ProjectionExecutor<P: Projection> {
+    projection: P,
+    model: ReadModel<P::Model>,
+    events: mspc::ReceiveChannel<Arc<NamedEvent<P::Events>>>,
+}
+
+impl<P: Projection> ProjectionExecutor<P> {
+    pub fn new(
+        projection: P,
+        events: mspc::ReceiveChannel<Arc<NamedEvent<P::Events>>>,
+    ) -> (Self, ReadModel<P::Model>) {
+        let model = ReadModel::new(projection.initial());
+        let executor = Self {
+            projection,
+            model: model.clone(),
+            events,
+        };
+        (executor, model)
+    }
+}
+
+impl<P: Projection> domains::TaskExecutor for ProjectionExecutor<P> {
+    fn run_tasks(&mut self) {
+        if self.model.is_closed() {
+            return;
+        }
+
+        let mut model = self.model.current();
+        let before = model.clone();
+
+        let mut closed = false;
+        loop {
+            match self.events.try_receive() {
+                Ok(event) => self.projection.apply(&mut model, &event),
+                Err(ChannelError::Closed) => {
+                    debug!("Projection event stream closed");
+                    closed = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        // watchers are only notified of actual changes to the read model.
+        if model != before {
+            self.model.update(model);
+        }
+
+        // closing last so watchers woken by it already see the final model.
+        if closed {
+            self.model.close();
+        }
+    }
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<()> {
+        if self.model.is_closed() {
+            return Poll::Pending;
+        }
+
+        // events arrive through the shell which wakes the drivers on its own,
+        // a closed stream is ready too so the read model gets closed.
+        match (self.events.is_empty(), self.events.is_closed()) {
+            (Ok(false), _) | (_, Ok(true)) | (_, Err(_)) => Poll::Ready(()),
+            _ => Poll::Pending,
+        }
+    }
+}
+
+/// project subscribes the projection to the events of the shell, registering
+/// its executor with the [`core::CoreExecutor`] and returning its read model.
+pub fn project<P: Projection>(
+    projection: P,
+    shell: &mut impl DomainShell<Events = P::Events>,
+    executor: &mut core::CoreExecutor,
+) -> DomainResult<ReadModel<P::Model>> {
+    let (projector, model) = ProjectionExecutor::new(projection, shell.listen()?);
+    executor.register(Box::new(projector));
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, task::Context};
+
+    use ewe_channels::mspc;
+
+    use crate::{
+        domains::{NamedEvent, TaskExecutor},
+        fixtures::{CounterApp, CounterEvents, CounterRequests},
+        projections::{self, Projection, ProjectionExecutor},
+        testing::DomainHarness,
+    };
+
+    #[test]
+    fn projection_folds_domain_events_into_read_model() {
+        let mut harness = DomainHarness::<CounterApp>::new();
+        let mut shell = harness.shell().clone();
+        let model = projections::project(CounterView, &mut shell, harness.executor())
+            .expect("should project");
+
+        assert_eq!(model.current(), CounterModel::default());
+
+        harness.request("increment", CounterRequests::Increment);
+        harness.request("increment", CounterRequests::Increment);
+
+        assert_eq!(
+            model.current(),
+            CounterModel {
+                count: 2,
+                last_change: Some("increment".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn watch_yields_only_latest_changes() {
+        let mut harness = DomainHarness::<CounterApp>::new();
+        let mut shell = harness.shell().clone();
+        let model = projections::project(CounterView, &mut shell, harness.executor())
+            .expect("should project");
+
+        let mut watch = model.watch();
+        assert!(!watch.has_changed());
+        assert_eq!(watch.try_changed(), None);
+
+        harness.request("increment", CounterRequests::Increment);
+        harness.request("increment", CounterRequests::Increment);
+
+        assert!(watch.has_changed());
+        assert_eq!(watch.try_changed().map(|m| m.count), Some(2));
+        assert_eq!(watch.try_changed(), None);
+
+        // events leaving the read model unchanged do not notify watchers.
+        harness.request("noop", CounterRequests::Noop);
+        assert!(!watch.has_changed());
+
+        harness.request("increment", CounterRequests::Increment);
+        let changed = futures::executor::block_on(watch.changed());
+        assert_eq!(changed.map(|m| m.count), Some(3));
+    }
+
+    #[test]
+    fn watch_registers_one_waker_however_often_polled() {
+        let (_events, receiver) = mspc::create();
+        let (_executor, model) = ProjectionExecutor::new(CounterView, receiver);
+        let mut watch = model.watch();
+
+        {
+            let mut changed = Box::pin(watch.changed());
+            let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+            for _ in 0..3 {
+                assert!(changed.as_mut().poll(&mut cx).is_pending());
+            }
+        }
+
+        assert_eq!(model.state.lock().unwrap().wakers.len(), 1);
+    }
+
+    #[test]
+    fn projection_executor_is_ready_once_its_event_stream_closed() {
+        let (events, receiver) = mspc::create();
+        let (mut executor, model) = ProjectionExecutor::new(CounterView, receiver);
+        let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+        assert!(executor.poll_ready(&mut cx).is_pending());
+
+        drop(events);
+        assert!(executor.poll_ready(&mut cx).is_ready());
+
+        executor.run_tasks();
+        assert!(model.is_closed());
+        assert!(executor.poll_ready(&mut cx).is_pending());
+    }
+
+    #[derive(Clone, Debug, Default, PartialEq, Eq)]
+    struct CounterModel {
+        count: i16,
+        last_change: Option<String>,
+    }
+
+    struct CounterView;
+
+    impl Projection for CounterView {
+        type Events = CounterEvents;
+        type Model = CounterModel;
+
+        fn initial(&self) -> Self::Model {
+            CounterModel::default()
+        }
+
+        fn apply(&self, model: &mut Self::Model, event: &NamedEvent<Self::Events>) {
+            for item in event.items() {
+                if let CounterEvents::Incremented(count) = item {
+                    model.count = count;
+                    model.last_change = Some(event.id().0);
+                }
+            }
+        }
+    }
+}
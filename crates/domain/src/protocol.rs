@@ -0,0 +1,619 @@
+// Module implementing the wire protocol exposing domains outside the process.
+
+use std::{
+    io::{self, BufRead, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, error};
+
+use ewe_channels::mspc::{self, ChannelError};
+
+use crate::{
+    app,
+    domains::{self, DomainResponseReceiver, DomainShell, NamedEvent, NamedRequest},
+    drivers, recording, servicer,
+};
+
+/// Version of the [`Envelope`] format produced and accepted by this crate.
+pub const ENVELOPE_VERSION: u32 = 1;
+
+// how long the adapter waits for input before checking on pending
+// responses and events again.
+const IDLE_POLL: Duration = Duration::from_millis(5);
+
+// how long a request can wait for its next response by default.
+const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+static MESSAGE_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+static REQUEST_SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("failed to read or write messages: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to encode or decode message: {0}")]
+    Encoding(#[from] serde_json::Error),
+
+    #[error("unsupported envelope version {0}, expected {ENVELOPE_VERSION}")]
+    UnsupportedVersion(u32),
+
+    #[error("envelope {0} carries no request")]
+    NotARequest(String),
+}
+
+pub type ProtocolResult<T> = std::result::Result<T, ProtocolError>;
+
+/// Message is the typed content of an [`Envelope`], serialized as its
+/// `type` tag and `payload`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "snake_case")]
+pub enum Message<E, R> {
+    /// A request for the domain to handle.
+    Request(R),
+
+    /// Events the domain responded with to a request.
+    Response(Vec<E>),
+
+    /// Events the domain published to its listeners.
+    Event(Vec<E>),
+
+    /// A request could not be processed.
+    Error(String),
+}
+
+/// Envelope wraps every [`Message`] crossing the process boundary with
+/// a version, unique id, creation time and the id of the envelope it
+/// relates to.
+///
+/// # Example:
+///
+/// ```ignore
+/// {"version":1,"id":"inc-1","type":"request","payload":"Increment","timestamp_ms":0}
+/// {"version":1,"id":"msg-1","type":"response","payload":[{"Incremented":1}],"timestamp_ms":1718000000000,"correlation_id":"inc-1"}
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<E, R> {
+    pub version: u32,
+    pub id: String,
+    #[serde(flatten)]
+    pub message: Message<E, R>,
+    #[serde(default)]
+    pub timestamp_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl<E, R> Envelope<E, R> {
+    pub fn new(message: Message<E, R>, correlation_id: Option<String>) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            id: format!("msg-{}", MESSAGE_SEQUENCE.fetch_add(1, Ordering::SeqCst)),
+            timestamp_ms: recording::now_millis(),
+            message,
+            correlation_id,
+        }
+    }
+
+    pub fn request(id: &str, request: R) -> Self {
+        Self {
+            id: id.to_string(),
+            ..Self::new(Message::Request(request), None)
+        }
+    }
+
+    pub fn error(message: impl Into<String>, correlation_id: Option<String>) -> Self {
+        Self::new(Message::Error(message.into()), correlation_id)
+    }
+}
+
+impl<E: Clone, R: Clone> Envelope<E, R> {
+    /// response wraps the events responding to the request envelope with
+    /// the giving id.
+    pub fn response(correlation_id: &str, event: &NamedEvent<E>) -> Self {
+        Self::new(
+            Message::Response(event.items()),
+            Some(correlation_id.to_string()),
+        )
+    }
+
+    /// event wraps published events, correlated to the request they
+    /// originated from.
+    pub fn event(event: &NamedEvent<E>) -> Self {
+        Self::new(Message::Event(event.items()), Some(event.id().0))
+    }
+
+    /// into_request validates the envelope and turns it into a
+    /// [`NamedRequest`] sharing the envelope's id.
+    pub fn into_request(self) -> ProtocolResult<NamedRequest<R>> {
+        if self.version != ENVELOPE_VERSION {
+            return Err(ProtocolError::UnsupportedVersion(self.version));
+        }
+
+        match self.message {
+            Message::Request(request) => Ok(NamedRequest::new(&self.id, request)),
+            _ => Err(ProtocolError::NotARequest(self.id)),
+        }
+    }
+}
+
+struct PendingResponse<E: Clone, Err> {
+    correlation_id: String,
+    receiver: DomainResponseReceiver<E, Err>,
+    // reset on every response so streaming requests only time out once
+    // they stall.
+    waiting_since: Instant,
+}
+
+/// JsonLinesAdapter exposes a domain over a JSON-lines protocol, every
+/// input line is a request [`Envelope`] and every output line is an
+/// [`Envelope`] carrying a response, a published event or an error.
+///
+/// The adapter only relays messages, the domain's executor must be driven
+/// separately, see [`serve_stdio`] which does both.
+pub struct JsonLinesAdapter<S: DomainShell> {
+    shell: S,
+    events: mspc::ReceiveChannel<Arc<NamedEvent<S::Events>>>,
+    pending: Vec<PendingResponse<S::Events, S::Error>>,
+    response_timeout: Duration,
+}
+
+impl<S> JsonLinesAdapter<S>
+where
+    S: DomainShell,
+    S::Events: Serialize + DeserializeOwned,
+    S::Requests: Serialize + DeserializeOwned,
+{
+    pub fn new(mut shell: S) -> domains::DomainResult<Self> {
+        Ok(Self {
+            events: shell.listen()?,
+            pending: Vec::new(),
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            shell,
+        })
+    }
+
+    /// with_response_timeout sets how long a request may wait for its
+    /// next response before an error is written for it, it also bounds
+    /// how long the domain keeps the request pending.
+    pub fn with_response_timeout(mut self, timeout: Duration) -> Self {
+        self.response_timeout = timeout;
+        self
+    }
+
+    /// serve relays requests read from the reader to the domain, writing
+    /// responses and events to the writer until the reader ends and every
+    /// pending request was answered.
+    pub fn serve(
+        &mut self,
+        reader: impl BufRead + Send + 'static,
+        mut writer: impl Write,
+    ) -> ProtocolResult<()> {
+        let (line_sender, line_receiver) = mpsc::channel::<io::Result<String>>();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                if line_sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut input_closed = false;
+        loop {
+            if !input_closed {
+                match line_receiver.recv_timeout(IDLE_POLL) {
+                    Ok(line) => self.handle_line(&line?, &mut writer)?,
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => input_closed = true,
+                }
+            } else {
+                thread::sleep(IDLE_POLL);
+            }
+
+            self.relay(&mut writer)?;
+
+            if input_closed && self.pending.is_empty() {
+                // give events published by the last responses a final chance.
+                self.relay(&mut writer)?;
+                debug!("JsonLinesAdapter input ended");
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle_line(&mut self, line: &str, writer: &mut impl Write) -> ProtocolResult<()> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+
+        let envelope = match serde_json::from_str::<Envelope<S::Events, S::Requests>>(line) {
+            Ok(envelope) => envelope,
+            Err(err) => {
+                return write_envelope(
+                    writer,
+                    &Envelope::<S::Events, S::Requests>::error(err.to_string(), None),
+                )
+            }
+        };
+
+        let correlation_id = envelope.id.clone();
+        let request = match envelope.into_request() {
+            Ok(request) => request,
+            Err(err) => {
+                return write_envelope(
+                    writer,
+                    &Envelope::<S::Events, S::Requests>::error(
+                        err.to_string(),
+                        Some(correlation_id),
+                    ),
+                )
+            }
+        };
+
+        // clients pick envelope ids freely, so the domain gets its own
+        // unique id and responses are mapped back by correlation_id.
+        let domain_id = format!(
+            "{}/{}",
+            correlation_id,
+            REQUEST_SEQUENCE.fetch_add(1, Ordering::SeqCst)
+        );
+        let request = NamedRequest::new(&domain_id, request.item());
+
+        match self
+            .shell
+            .do_request_with_timeout(request, self.response_timeout)
+        {
+            Ok(receiver) => {
+                self.pending.push(PendingResponse {
+                    correlation_id,
+                    receiver,
+                    waiting_since: Instant::now(),
+                });
+                Ok(())
+            }
+            Err(err) => write_envelope(
+                writer,
+                &Envelope::<S::Events, S::Requests>::error(err.to_string(), Some(correlation_id)),
+            ),
+        }
+    }
+
+    // relay writes out available responses and events, forgetting
+    // requests whose response channel closed or timed out.
+    fn relay(&mut self, writer: &mut impl Write) -> ProtocolResult<()> {
+        let mut pending = std::mem::take(&mut self.pending);
+        let mut result = Ok(());
+
+        pending.retain_mut(|entry| loop {
+            match entry.receiver.try_receive() {
+                Ok(response) => {
+                    entry.waiting_since = Instant::now();
+                    let envelope = match response {
+                        Ok(event) => Envelope::<S::Events, S::Requests>::response(
+                            &entry.correlation_id,
+                            &event,
+                        ),
+                        Err(failure) => Envelope::<S::Events, S::Requests>::error(
+                            failure.to_string(),
+                            Some(entry.correlation_id.clone()),
+                        ),
+                    };
+                    if let Err(err) = write_envelope(writer, &envelope) {
+                        result = Err(err);
+                    }
+                }
+                Err(ChannelError::ReceivedNoData) => {
+                    if entry.waiting_since.elapsed() < self.response_timeout {
+                        return true;
+                    }
+
+                    let envelope = Envelope::<S::Events, S::Requests>::error(
+                        "request timed out",
+                        Some(entry.correlation_id.clone()),
+                    );
+                    if let Err(err) = write_envelope(writer, &envelope) {
+                        result = Err(err);
+                    }
+                    return false;
+                }
+                Err(_) => return false,
+            }
+        });
+        self.pending = pending;
+        result?;
+
+        while let Ok(event) = self.events.try_receive() {
+            write_envelope(writer, &Envelope::<S::Events, S::Requests>::event(&event))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn write_envelope<E: Serialize, R: Serialize>(
+    writer: &mut impl Write,
+    envelope: &Envelope<E, R>,
+) -> ProtocolResult<()> {
+    serde_json::to_writer(&mut *writer, envelope)?;
+    writer.write_all(b"\n")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// serve_stdio runs the domain on a [`drivers::ThreadDriver`] exposing it
+/// over a JSON-lines protocol on stdin and stdout until stdin closes.
+pub fn serve_stdio<App>() -> ProtocolResult<()>
+where
+    App: domains::Domain + 'static,
+    App::Events: Serialize + DeserializeOwned + Sync,
+    App::Requests: Serialize + DeserializeOwned + Sync,
+{
+    let (executor, server) = app::create::<App>();
+    let shell = servicer::create_shell(server);
+    let driver = drivers::ThreadDriver::spawn(executor)?;
+
+    let mut adapter = JsonLinesAdapter::new(shell).map_err(|err| {
+        error!("JsonLinesAdapter failed to listen to events: {}", err);
+        io::Error::other(err.to_string())
+    })?;
+    let served = adapter.serve(io::BufReader::new(io::stdin()), io::stdout().lock());
+
+    if driver.stop().is_err() {
+        error!("Domain driver panicked while serving stdio");
+    }
+    served
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use crate::{
+        app,
+        domains::{self, MasterShell, NamedRequest},
+        drivers,
+        fixtures::{CounterApp, CounterEvents, CounterRequests, Platform},
+        protocol::{Envelope, JsonLinesAdapter, Message, ProtocolError, ENVELOPE_VERSION},
+        servicer,
+    };
+
+    #[test]
+    fn envelope_round_trips_with_type_tag_and_payload() {
+        let envelope = Envelope::<CounterEvents, CounterRequests>::request(
+            "inc-1",
+            CounterRequests::Increment,
+        );
+
+        let encoded = serde_json::to_value(&envelope).expect("should encode");
+        assert_eq!(encoded["version"], ENVELOPE_VERSION);
+        assert_eq!(encoded["type"], "request");
+        assert_eq!(encoded["payload"], "Increment");
+        assert!(encoded.get("correlation_id").is_none());
+
+        let decoded: Envelope<CounterEvents, CounterRequests> =
+            serde_json::from_value(encoded).expect("should decode");
+        assert_eq!(decoded, envelope);
+        assert_eq!(
+            decoded.into_request().unwrap(),
+            NamedRequest::new("inc-1", CounterRequests::Increment)
+        );
+    }
+
+    #[test]
+    fn envelope_rejects_unknown_versions_and_non_requests() {
+        let mut envelope = Envelope::<CounterEvents, CounterRequests>::request(
+            "inc-1",
+            CounterRequests::Increment,
+        );
+        envelope.version = ENVELOPE_VERSION + 1;
+        assert!(matches!(
+            envelope.into_request(),
+            Err(ProtocolError::UnsupportedVersion(_))
+        ));
+
+        let envelope = Envelope::<CounterEvents, CounterRequests>::error("boom", None);
+        assert!(matches!(
+            envelope.into_request(),
+            Err(ProtocolError::NotARequest(_))
+        ));
+    }
+
+    #[test]
+    fn adapter_serves_a_domain_over_json_lines() {
+        let (executor, server) = app::create::<CounterApp>();
+        let shell = servicer::create_shell(server);
+        let driver = drivers::ThreadDriver::spawn(executor).expect("should spawn driver");
+
+        let input = [
+            r#"{"version":1,"id":"inc-1","type":"request","payload":"Increment"}"#,
+            "not json",
+            r#"{"version":1,"id":"inc-2","type":"request","payload":"Increment"}"#,
+        ]
+        .join("\n");
+
+        let mut output = Vec::new();
+        JsonLinesAdapter::new(shell)
+            .expect("should create adapter")
+            .with_response_timeout(Duration::from_secs(5))
+            .serve(io::Cursor::new(input.into_bytes()), &mut output)
+            .expect("should serve input");
+        driver.stop().expect("should stop driver");
+
+        let envelopes: Vec<Envelope<CounterEvents, CounterRequests>> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("should decode output"))
+            .collect();
+
+        let responses: Vec<_> = envelopes
+            .iter()
+            .filter(|envelope| matches!(envelope.message, Message::Response(_)))
+            .map(|envelope| (envelope.correlation_id.clone(), envelope.message.clone()))
+            .collect();
+        assert_eq!(
+            responses,
+            vec![
+                (
+                    Some("inc-1".to_string()),
+                    Message::Response(vec![CounterEvents::Incremented(1)])
+                ),
+                (
+                    Some("inc-2".to_string()),
+                    Message::Response(vec![CounterEvents::Incremented(2)])
+                ),
+            ]
+        );
+
+        let events = envelopes
+            .iter()
+            .filter(|envelope| matches!(envelope.message, Message::Event(_)))
+            .count();
+        assert_eq!(events, 2);
+
+        assert!(envelopes
+            .iter()
+            .any(|envelope| matches!(envelope.message, Message::Error(_))
+                && envelope.correlation_id.is_none()));
+    }
+
+    #[test]
+    fn adapter_answers_requests_sharing_an_envelope_id() {
+        let (executor, server) = app::create::<CounterApp>();
+        let shell = servicer::create_shell(server);
+        let driver = drivers::ThreadDriver::spawn(executor).expect("should spawn driver");
+
+        let input = [
+            r#"{"version":1,"id":"inc","type":"request","payload":"Increment"}"#,
+            r#"{"version":1,"id":"inc","type":"request","payload":"Increment"}"#,
+        ]
+        .join("\n");
+
+        let mut output = Vec::new();
+        JsonLinesAdapter::new(shell)
+            .expect("should create adapter")
+            .with_response_timeout(Duration::from_secs(5))
+            .serve(io::Cursor::new(input.into_bytes()), &mut output)
+            .expect("should serve input");
+        driver.stop().expect("should stop driver");
+
+        let mut responses: Vec<_> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<Envelope<CounterEvents, CounterRequests>>(line)
+                    .expect("should decode output")
+            })
+            .filter(|envelope| !matches!(envelope.message, Message::Event(_)))
+            .map(|envelope| (envelope.correlation_id, envelope.message))
+            .collect();
+        responses.sort_by_key(|(_, message)| format!("{:?}", message));
+        assert_eq!(
+            responses,
+            vec![
+                (
+                    Some("inc".to_string()),
+                    Message::Response(vec![CounterEvents::Incremented(1)])
+                ),
+                (
+                    Some("inc".to_string()),
+                    Message::Response(vec![CounterEvents::Incremented(2)])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn adapter_times_out_streaming_requests_which_stall() {
+        let (executor, server) = app::create::<StallingApp>();
+        let shell = servicer::create_shell(server);
+        let driver = drivers::ThreadDriver::spawn(executor).expect("should spawn driver");
+
+        let input = r#"{"version":1,"id":"inc-1","type":"request","payload":"Increment"}"#;
+
+        let mut output = Vec::new();
+        JsonLinesAdapter::new(shell)
+            .expect("should create adapter")
+            .with_response_timeout(Duration::from_millis(100))
+            .serve(io::Cursor::new(input.as_bytes().to_vec()), &mut output)
+            .expect("should serve input");
+        driver.stop().expect("should stop driver");
+
+        let messages: Vec<_> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<Envelope<CounterEvents, CounterRequests>>(line)
+                    .expect("should decode output")
+            })
+            .map(|envelope| (envelope.correlation_id, envelope.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    Some("inc-1".to_string()),
+                    Message::Response(vec![CounterEvents::Incremented(1)])
+                ),
+                (
+                    Some("inc-1".to_string()),
+                    Message::Error("request timed out".to_string())
+                ),
+            ]
+        );
+    }
+
+    // StallingApp responds once to every request and then keeps the
+    // response channel open without ever closing it.
+    #[derive(Clone, Default)]
+    struct StallingApp {
+        stalled: std::sync::Arc<
+            std::sync::Mutex<
+                Vec<ewe_channels::mspc::SendChannel<domains::NamedEvent<CounterEvents>>>,
+            >,
+        >,
+    }
+
+    impl domains::Domain for StallingApp {
+        type Events = CounterEvents;
+        type Requests = CounterRequests;
+        type Platform = Platform;
+        type Error = String;
+
+        fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            mut chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+            _shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            chan.try_send(req.to_one(CounterEvents::Incremented(1)))
+                .expect("should respond");
+            self.stalled.lock().unwrap().push(chan);
+        }
+
+        fn handle_event(
+            &self,
+            _events: domains::NamedEvent<Self::Events>,
+            _shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+        }
+    }
+}
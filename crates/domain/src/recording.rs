@@ -0,0 +1,557 @@
+// Module implementing recording and replaying of DServicer sessions.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tracing::error;
+
+use ewe_channels::{executor, mspc};
+
+use crate::{
+    app,
+    domains::{self, DomainShell, NamedEvent, NamedRequest},
+    servicer,
+};
+
+// number of executor rounds ran after feeding every entry during a replay.
+const REPLAY_SETTLE_ROUNDS: usize = 10;
+
+#[derive(Error, Debug)]
+pub enum RecordingError {
+    #[error("failed to access recording: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("failed to encode or decode recording: {0}")]
+    Encoding(#[from] serde_json::Error),
+}
+
+pub type RecordingResult<T> = std::result::Result<T, RecordingError>;
+
+/// RecordedEntry is a single interaction of a domain captured by the [`Recorder`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value")]
+pub enum RecordedEntry<E: Clone, R: Clone> {
+    /// A request received by the domain from a driving client.
+    Request(NamedRequest<R>),
+
+    /// A response the domain delivered for a received request.
+    Response(NamedEvent<E>),
+
+    /// An event delivered to the domain's event handler.
+    Event(NamedEvent<E>),
+
+    /// An event published to the domain's listeners.
+    Published(NamedEvent<E>),
+
+    /// A request the domain sent out to be handled by use-cases.
+    OutgoingRequest(NamedRequest<R>),
+
+    /// A reply delivered for a request the domain sent out.
+    Reply(NamedEvent<E>),
+}
+
+/// Record is a [`RecordedEntry`] with its position in the recording and
+/// the wall-clock time in milliseconds it was captured at.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record<E: Clone, R: Clone> {
+    pub sequence: u64,
+    pub timestamp_ms: u64,
+    pub entry: RecordedEntry<E, R>,
+}
+
+/// RecordSink stores the records produced by a [`Recorder`].
+pub trait RecordSink<E: Clone, R: Clone>: Send {
+    fn write(&mut self, record: &Record<E, R>) -> RecordingResult<()>;
+}
+
+/// JsonLinesSink writes every record as a line of JSON.
+pub struct JsonLinesSink<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl JsonLinesSink<io::BufWriter<fs::File>> {
+    pub fn create(path: impl AsRef<Path>) -> RecordingResult<Self> {
+        Ok(Self::new(io::BufWriter::new(fs::File::create(path)?)))
+    }
+}
+
+impl<W: Write + Send, E: Clone + Serialize, R: Clone + Serialize> RecordSink<E, R>
+    for JsonLinesSink<W>
+{
+    fn write(&mut self, record: &Record<E, R>) -> RecordingResult<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// MemorySink keeps records in memory, clones share the same records.
+pub struct MemorySink<E: Clone, R: Clone> {
+    records: Arc<Mutex<Vec<Record<E, R>>>>,
+}
+
+impl<E: Clone, R: Clone> Clone for MemorySink<E, R> {
+    fn clone(&self) -> Self {
+        Self {
+            records: self.records.clone(),
+        }
+    }
+}
+
+impl<E: Clone, R: Clone> Default for MemorySink<E, R> {
+    fn default() -> Self {
+        Self {
+            records: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl<E: Clone, R: Clone> MemorySink<E, R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn records(&self) -> Vec<Record<E, R>> {
+        self.records.lock().unwrap().clone()
+    }
+}
+
+impl<E: Clone + Send, R: Clone + Send> RecordSink<E, R> for MemorySink<E, R> {
+    fn write(&mut self, record: &Record<E, R>) -> RecordingResult<()> {
+        self.records.lock().unwrap().push(record.clone());
+        Ok(())
+    }
+}
+
+struct RecorderState<E: Clone, R: Clone> {
+    sequence: u64,
+    sink: Box<dyn RecordSink<E, R>>,
+}
+
+/// Recorder captures the interactions of a [`servicer::DServicer`] in the
+/// order they occurred, clones share the same recording.
+pub struct Recorder<E: Clone, R: Clone> {
+    state: Arc<Mutex<Option<RecorderState<E, R>>>>,
+}
+
+impl<E: Clone, R: Clone> Clone for Recorder<E, R> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<E: Clone, R: Clone> Default for Recorder<E, R> {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl<E: Clone, R: Clone> Recorder<E, R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// start begins recording into the sink, replacing any existing sink.
+    pub fn start(&self, sink: impl RecordSink<E, R> + 'static) {
+        let mut state = self.state.lock().unwrap();
+        *state = Some(RecorderState {
+            sequence: 0,
+            sink: Box::new(sink),
+        });
+    }
+
+    /// start_file begins recording as JSON lines into the file at the giving path.
+    pub fn start_file(&self, path: impl AsRef<Path>) -> RecordingResult<()>
+    where
+        E: Serialize + 'static,
+        R: Serialize + 'static,
+    {
+        self.start(JsonLinesSink::create(path)?);
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = None;
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.state.lock().unwrap().is_some()
+    }
+
+    pub fn record(&self, entry: RecordedEntry<E, R>) {
+        let mut guard = self.state.lock().unwrap();
+        let Some(state) = guard.as_mut() else {
+            return;
+        };
+
+        let record = Record {
+            sequence: state.sequence,
+            timestamp_ms: now_millis(),
+            entry,
+        };
+        state.sequence += 1;
+
+        if let Err(err) = state.sink.write(&record) {
+            error!("Recorder failed to write record: {}", err);
+        }
+    }
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// forward returns a channel which records every event sent through it
+/// before delivering it to the provided sender, the forwarding runs
+/// as a task on the executor.
+pub(crate) fn forward<E: Clone + Send + 'static, R: Clone + Send + 'static>(
+    recorder: Recorder<E, R>,
+    executor: &executor::Executor<NamedEvent<E>>,
+    kind: fn(NamedEvent<E>) -> RecordedEntry<E, R>,
+    mut sender: mspc::SendChannel<NamedEvent<E>>,
+) -> mspc::SendChannel<NamedEvent<E>> {
+    let (proxy_sender, mut proxy_receiver) = mspc::create::<NamedEvent<E>>();
+
+    let spawned = executor.spawn(async move {
+        while let Ok(event) = proxy_receiver.async_receive().await {
+            recorder.record(kind(event.clone()));
+            if let Err(err) = sender.async_send(event).await {
+                error!("Recorder failed to forward event: {}", err);
+            }
+        }
+        _ = sender.close();
+    });
+
+    if let Err(err) = spawned {
+        error!("Recorder failed to spawn forwarding task: {}", err);
+    }
+
+    proxy_sender
+}
+
+/// read_records reads a recording written by the [`JsonLinesSink`].
+pub fn read_records<E, R>(reader: impl BufRead) -> RecordingResult<Vec<Record<E, R>>>
+where
+    E: Clone + DeserializeOwned,
+    R: Clone + DeserializeOwned,
+{
+    let mut records = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line)?);
+    }
+    Ok(records)
+}
+
+/// Divergence describes an entry of a replay that does not match the
+/// recording at the giving position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence<E: Clone, R: Clone> {
+    pub position: usize,
+    pub expected: Option<RecordedEntry<E, R>>,
+    pub actual: Option<RecordedEntry<E, R>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReplayReport<E: Clone, R: Clone> {
+    pub replayed: Vec<Record<E, R>>,
+    pub divergences: Vec<Divergence<E, R>>,
+}
+
+impl<E: Clone, R: Clone> ReplayReport<E, R> {
+    pub fn is_faithful(&self) -> bool {
+        self.divergences.is_empty()
+    }
+}
+
+/// replay feeds the requests and replies of a recording into a fresh
+/// instance of the domain, comparing everything the domain does with
+/// the recording.
+///
+/// Replies are delivered in place of the use-cases the recorded domain
+/// talked to, so no use-case needs to be registered for a replay. All
+/// replies of a request go through the one response channel of the
+/// request which is closed after its last recorded reply.
+pub fn replay<App>(
+    records: &[Record<App::Events, App::Requests>],
+) -> ReplayReport<App::Events, App::Requests>
+where
+    App: domains::Domain + 'static,
+    App::Events: PartialEq + Sync,
+    App::Requests: PartialEq + Sync,
+{
+    let (mut executor, server) = app::create::<App>();
+
+    let sink = MemorySink::new();
+    server.recorder().start(sink.clone());
+
+    let mut shell = servicer::create_shell(server);
+    let mut pending = Vec::new();
+
+    let mut last_replies = HashMap::new();
+    for (position, record) in records.iter().enumerate() {
+        if let RecordedEntry::Reply(event) = &record.entry {
+            last_replies.insert(event.id(), position);
+        }
+    }
+    let mut repliers: HashMap<domains::Id, mspc::SendChannel<NamedEvent<App::Events>>> =
+        HashMap::new();
+
+    for (position, record) in records.iter().enumerate() {
+        match &record.entry {
+            RecordedEntry::Request(req) => match shell.do_request(req.clone()) {
+                Ok(receiver) => pending.push(receiver),
+                Err(err) => error!("Replay failed to send request: {}", err),
+            },
+            RecordedEntry::Reply(event) => {
+                let id = event.id();
+                let sender = match repliers.remove(&id) {
+                    Some(sender) => Ok(sender),
+                    None => shell.respond(id.clone()),
+                };
+
+                match sender {
+                    Ok(mut sender) => {
+                        if let Err(err) = sender.try_send(event.clone()) {
+                            error!("Replay failed to deliver reply: {}", err);
+                        }
+                        if last_replies.get(&id) == Some(&position) {
+                            _ = sender.close();
+                        } else {
+                            repliers.insert(id, sender);
+                        }
+                    }
+                    Err(err) => error!("Replay found no pending request for reply: {}", err),
+                }
+            }
+            _ => continue,
+        }
+
+        for _ in 0..REPLAY_SETTLE_ROUNDS {
+            executor.run_all();
+        }
+    }
+
+    let replayed = sink.records();
+    let divergences = diverging_entries(records, &replayed);
+
+    ReplayReport {
+        replayed,
+        divergences,
+    }
+}
+
+/// replay_file replays the recording stored at the giving path.
+pub fn replay_file<App>(
+    path: impl AsRef<Path>,
+) -> RecordingResult<ReplayReport<App::Events, App::Requests>>
+where
+    App: domains::Domain + 'static,
+    App::Events: PartialEq + DeserializeOwned + Sync,
+    App::Requests: PartialEq + DeserializeOwned + Sync,
+{
+    let file = io::BufReader::new(fs::File::open(path)?);
+    let records = read_records(file)?;
+    Ok(replay::<App>(&records))
+}
+
+fn diverging_entries<E: Clone + PartialEq, R: Clone + PartialEq>(
+    expected: &[Record<E, R>],
+    actual: &[Record<E, R>],
+) -> Vec<Divergence<E, R>> {
+    let mut divergences = Vec::new();
+    for position in 0..expected.len().max(actual.len()) {
+        let expected_entry = expected.get(position).map(|record| record.entry.clone());
+        let actual_entry = actual.get(position).map(|record| record.entry.clone());
+        if expected_entry != actual_entry {
+            divergences.push(Divergence {
+                position,
+                expected: expected_entry,
+                actual: actual_entry,
+            });
+        }
+    }
+    divergences
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io};
+
+    use crate::{
+        app,
+        domains::{self, DomainShell},
+        fixtures::{CounterApp, CounterEvents, CounterRequests, DoubleCounterApp},
+        recording::{self, RecordedEntry},
+        servicer,
+    };
+
+    #[test]
+    fn can_record_and_read_back_a_domain_session() {
+        let path =
+            std::env::temp_dir().join(format!("ewe_domain_recording_{}.jsonl", std::process::id()));
+
+        record_session::<CounterApp>(&[CounterEvents::Saved(1)], |recorder| {
+            recorder.start_file(&path).expect("should create recording")
+        });
+
+        let file = io::BufReader::new(fs::File::open(&path).expect("should open recording"));
+        let records = recording::read_records::<CounterEvents, CounterRequests>(file)
+            .expect("should read recording");
+        fs::remove_file(&path).expect("should remove recording");
+
+        let entries: Vec<RecordedEntry<CounterEvents, CounterRequests>> =
+            records.iter().map(|record| record.entry.clone()).collect();
+
+        assert_eq!(
+            entries,
+            vec![
+                RecordedEntry::Request(domains::NamedRequest::new(
+                    "increment",
+                    CounterRequests::Increment
+                )),
+                RecordedEntry::Published(domains::NamedEvent::new(
+                    "increment",
+                    vec![CounterEvents::Incremented(1)]
+                )),
+                RecordedEntry::Response(domains::NamedEvent::new(
+                    "increment",
+                    vec![CounterEvents::Incremented(1)]
+                )),
+                RecordedEntry::Event(domains::NamedEvent::new(
+                    "increment",
+                    vec![CounterEvents::Incremented(1)]
+                )),
+                RecordedEntry::OutgoingRequest(domains::NamedRequest::new(
+                    "save_count",
+                    CounterRequests::Save(1)
+                )),
+                RecordedEntry::Reply(domains::NamedEvent::new(
+                    "save_count",
+                    vec![CounterEvents::Saved(1)]
+                )),
+            ]
+        );
+
+        let sequences: Vec<u64> = records.iter().map(|record| record.sequence).collect();
+        assert_eq!(sequences, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn can_replay_a_recording_without_divergence() {
+        let sink = recording::MemorySink::new();
+        record_session::<CounterApp>(&[CounterEvents::Saved(1)], |recorder| {
+            recorder.start(sink.clone())
+        });
+
+        let report = recording::replay::<CounterApp>(&sink.records());
+
+        assert!(report.is_faithful(), "{:?}", report.divergences);
+        assert_eq!(report.replayed.len(), sink.records().len());
+    }
+
+    #[test]
+    fn can_replay_replies_of_multiple_events_through_one_sender() {
+        let sink = recording::MemorySink::new();
+        let replies = [CounterEvents::Saved(1), CounterEvents::Saved(1)];
+        record_session::<CounterApp>(&replies, |recorder| recorder.start(sink.clone()));
+
+        let report = recording::replay::<CounterApp>(&sink.records());
+
+        assert!(report.is_faithful(), "{:?}", report.divergences);
+        let replies = report
+            .replayed
+            .iter()
+            .filter(|record| matches!(record.entry, RecordedEntry::Reply(_)))
+            .count();
+        assert_eq!(replies, 2);
+    }
+
+    #[test]
+    fn can_report_divergences_of_a_changed_domain() {
+        let sink = recording::MemorySink::new();
+        record_session::<CounterApp>(&[CounterEvents::Saved(1)], |recorder| {
+            recorder.start(sink.clone())
+        });
+
+        let report = recording::replay::<DoubleCounterApp>(&sink.records());
+
+        assert!(!report.is_faithful());
+        assert_eq!(report.divergences[0].position, 1);
+        assert_eq!(
+            report.divergences[0].actual,
+            Some(RecordedEntry::Published(domains::NamedEvent::new(
+                "increment",
+                vec![CounterEvents::Incremented(2)]
+            )))
+        );
+    }
+
+    fn record_session<App>(
+        replies: &[CounterEvents],
+        start: impl FnOnce(&recording::Recorder<CounterEvents, CounterRequests>),
+    ) where
+        App: domains::Domain<Events = CounterEvents, Requests = CounterRequests> + 'static,
+    {
+        let (mut executor, server) = app::create::<App>();
+        let recorder = server.recorder();
+        start(&recorder);
+
+        let mut shell = servicer::create_shell(server);
+        let mut requests = shell.requests().unwrap();
+
+        let _response = shell
+            .do_request(domains::NamedRequest::new(
+                "increment",
+                CounterRequests::Increment,
+            ))
+            .expect("should send request");
+
+        for _ in 0..5 {
+            executor.run_all();
+        }
+
+        // act as the use-case saving the count.
+        let save_request = requests.try_receive().expect("should have save request");
+        let mut sender = shell
+            .respond(save_request.id())
+            .expect("should have pending request");
+        for reply in replies {
+            sender
+                .try_send(save_request.to_one(reply.clone()))
+                .expect("should send reply");
+        }
+        sender.close().expect("should close reply");
+
+        for _ in 0..5 {
+            executor.run_all();
+        }
+
+        recorder.stop();
+    }
+}
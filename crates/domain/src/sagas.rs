@@ -0,0 +1,769 @@
+// Module implementing sagas, multi-step domain workflows with compensation.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{debug, error};
+
+use ewe_channels::mspc;
+
+use crate::{
+    domains::{DomainErrors, MasterShell, NamedEvent, NamedRequest},
+    stores::JsonDir,
+};
+
+#[derive(Error, Debug)]
+pub enum SagaError {
+    #[error("saga store failed: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("saga state could not be encoded or decoded: {0}")]
+    Encoding(#[from] serde_json::Error),
+
+    #[error("saga {0} already exists")]
+    AlreadyExists(String),
+
+    #[error("saga {0} belongs to another saga definition")]
+    DefinitionMismatch(String),
+
+    #[error("saga {0} completed more steps than its definition has")]
+    InvalidState(String),
+
+    #[error("saga step could not be issued: {0}")]
+    Domain(#[from] DomainErrors),
+
+    #[error("saga step request could not be sent: {0}")]
+    RequestFailed(String),
+}
+
+pub type SagaResult<T> = std::result::Result<T, SagaError>;
+
+/// SagaEvent reports the progress of a saga, these are delivered to the
+/// domain and its listeners via the domain's event type using its
+/// `From<SagaEvent>` implementation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SagaEvent {
+    StepCompleted { saga_id: String, step: String },
+    StepFailed { saga_id: String, step: String },
+    StepCompensated { saga_id: String, step: String },
+    CompensationFailed { saga_id: String, step: String },
+    Completed { saga_id: String },
+    Compensated { saga_id: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SagaStatus {
+    /// Steps are being issued in order.
+    Running,
+
+    /// A step failed and completed steps are being compensated in reverse.
+    Compensating,
+
+    /// All steps completed.
+    Completed,
+
+    /// All completed steps were compensated after a failure.
+    Compensated,
+
+    /// A compensation failed, the saga needs manual attention.
+    Failed,
+}
+
+impl SagaStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            SagaStatus::Completed | SagaStatus::Compensated | SagaStatus::Failed
+        )
+    }
+}
+
+/// SagaState is the persisted progress of a saga, `completed` counts the
+/// steps that completed and are yet to be compensated.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SagaState {
+    pub saga_id: String,
+    pub definition: String,
+    pub status: SagaStatus,
+    pub completed: usize,
+    pub failed_step: Option<usize>,
+}
+
+/// SagaStore persists [`SagaState`] allowing sagas to resume after restart.
+pub trait SagaStore: Send + Sync {
+    fn save(&self, state: &SagaState) -> SagaResult<()>;
+
+    fn load(&self, saga_id: &str) -> SagaResult<Option<SagaState>>;
+
+    fn list(&self) -> SagaResult<Vec<SagaState>>;
+}
+
+#[derive(Clone, Default)]
+pub struct MemorySagaStore {
+    states: Arc<Mutex<HashMap<String, SagaState>>>,
+}
+
+impl MemorySagaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SagaStore for MemorySagaStore {
+    fn save(&self, state: &SagaState) -> SagaResult<()> {
+        let mut states = self.states.lock().unwrap();
+        states.insert(state.saga_id.clone(), state.clone());
+        Ok(())
+    }
+
+    fn load(&self, saga_id: &str) -> SagaResult<Option<SagaState>> {
+        let states = self.states.lock().unwrap();
+        Ok(states.get(saga_id).cloned())
+    }
+
+    fn list(&self) -> SagaResult<Vec<SagaState>> {
+        let states = self.states.lock().unwrap();
+        Ok(states.values().cloned().collect())
+    }
+}
+
+/// FileSagaStore stores every saga as a JSON file within a directory, saga
+/// ids are limited to ASCII alphanumerics, `-` and `_`.
+#[derive(Clone)]
+pub struct FileSagaStore {
+    directory: JsonDir,
+}
+
+impl FileSagaStore {
+    pub fn new(directory: impl Into<PathBuf>) -> SagaResult<Self> {
+        Ok(Self {
+            directory: JsonDir::new(directory)?,
+        })
+    }
+}
+
+impl SagaStore for FileSagaStore {
+    fn save(&self, state: &SagaState) -> SagaResult<()> {
+        let content = serde_json::to_vec(state)?;
+        Ok(self.directory.write(&state.saga_id, &content)?)
+    }
+
+    fn load(&self, saga_id: &str) -> SagaResult<Option<SagaState>> {
+        match self.directory.read(saga_id)? {
+            Some(content) => Ok(Some(serde_json::from_slice(&content)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn list(&self) -> SagaResult<Vec<SagaState>> {
+        let mut states = Vec::new();
+        for content in self.directory.documents()? {
+            states.push(serde_json::from_slice(&content)?);
+        }
+        Ok(states)
+    }
+}
+
+pub struct SagaStep<R: Clone> {
+    pub name: String,
+    pub request: R,
+    pub compensation: Option<R>,
+}
+
+type FailureCheck<E> = Arc<dyn Fn(&NamedEvent<E>) -> bool + Send + Sync>;
+
+/// SagaDefinition declares the ordered steps of a saga, each step issues
+/// its request via [`MasterShell::send_request`] and waits for the reply
+/// before the next step is issued.
+///
+/// A step fails when its request is closed without a reply or the reply
+/// matches the failure check, at which point the compensating requests of
+/// the completed steps are issued in reverse order.
+pub struct SagaDefinition<E: Clone, R: Clone> {
+    name: String,
+    steps: Vec<SagaStep<R>>,
+    is_failure: Option<FailureCheck<E>>,
+}
+
+impl<E: Clone, R: Clone> SagaDefinition<E, R> {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            steps: Vec::new(),
+            is_failure: None,
+        }
+    }
+
+    pub fn step(mut self, name: &str, request: R, compensation: Option<R>) -> Self {
+        self.steps.push(SagaStep {
+            name: name.to_string(),
+            request,
+            compensation,
+        });
+        self
+    }
+
+    pub fn with_failure_check(
+        mut self,
+        check: impl Fn(&NamedEvent<E>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.is_failure = Some(Arc::new(check));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn steps(&self) -> &[SagaStep<R>] {
+        &self.steps
+    }
+
+    fn is_failure(&self, reply: &NamedEvent<E>) -> bool {
+        match &self.is_failure {
+            Some(check) => check(reply),
+            None => false,
+        }
+    }
+}
+
+/// Saga runs instances of a [`SagaDefinition`], persisting their progress
+/// to a [`SagaStore`].
+pub struct Saga<E: Clone, R: Clone> {
+    definition: Arc<SagaDefinition<E, R>>,
+    store: Arc<dyn SagaStore>,
+}
+
+impl<E: Clone, R: Clone> Clone for Saga<E, R> {
+    fn clone(&self) -> Self {
+        Self {
+            definition: self.definition.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<E, R> Saga<E, R>
+where
+    E: Clone + Send + Sync + From<SagaEvent> + 'static,
+    R: Clone + Send + Sync + 'static,
+{
+    pub fn new(definition: SagaDefinition<E, R>, store: impl SagaStore + 'static) -> Self {
+        Self {
+            definition: Arc::new(definition),
+            store: Arc::new(store),
+        }
+    }
+
+    pub fn definition(&self) -> &SagaDefinition<E, R> {
+        &self.definition
+    }
+
+    pub fn state(&self, saga_id: &str) -> SagaResult<Option<SagaState>> {
+        self.store.load(saga_id)
+    }
+
+    /// start begins a new saga instance with the giving id.
+    pub fn start(
+        &self,
+        saga_id: &str,
+        shell: impl MasterShell<Events = E, Requests = R>,
+    ) -> SagaResult<()> {
+        if self.store.load(saga_id)?.is_some() {
+            return Err(SagaError::AlreadyExists(saga_id.to_string()));
+        }
+
+        let state = SagaState {
+            saga_id: saga_id.to_string(),
+            definition: self.definition.name.clone(),
+            status: SagaStatus::Running,
+            completed: 0,
+            failed_step: None,
+        };
+        self.store.save(&state)?;
+        self.advance(state, shell)
+    }
+
+    /// resume continues a persisted saga instance from its last recorded step,
+    /// re-issuing the request of that step. Returns false if the saga is
+    /// unknown or already finished.
+    pub fn resume(
+        &self,
+        saga_id: &str,
+        shell: impl MasterShell<Events = E, Requests = R>,
+    ) -> SagaResult<bool> {
+        match self.store.load(saga_id)? {
+            Some(state) if state.definition != self.definition.name => {
+                Err(SagaError::DefinitionMismatch(saga_id.to_string()))
+            }
+            Some(state) if !state.status.is_finished() => {
+                self.validate(&state)?;
+                self.advance(state, shell)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// resume_all resumes every unfinished saga of this definition,
+    /// returning how many were resumed.
+    pub fn resume_all(
+        &self,
+        shell: impl MasterShell<Events = E, Requests = R>,
+    ) -> SagaResult<usize> {
+        let mut resumed = 0;
+        for state in self.store.list()? {
+            if state.definition == self.definition.name && !state.status.is_finished() {
+                self.validate(&state)?;
+                self.advance(state, shell.clone())?;
+                resumed += 1;
+            }
+        }
+        Ok(resumed)
+    }
+
+    // validate rejects persisted states which do not fit the definition,
+    // e.g stored before it lost steps.
+    fn validate(&self, state: &SagaState) -> SagaResult<()> {
+        if state.completed > self.definition.steps.len() {
+            return Err(SagaError::InvalidState(state.saga_id.clone()));
+        }
+        Ok(())
+    }
+
+    fn advance(
+        &self,
+        mut state: SagaState,
+        mut shell: impl MasterShell<Events = E, Requests = R>,
+    ) -> SagaResult<()> {
+        let steps = &self.definition.steps;
+        match state.status {
+            SagaStatus::Running if state.completed >= steps.len() => {
+                state.status = SagaStatus::Completed;
+                self.store.save(&state)?;
+                self.notify(
+                    &mut shell,
+                    &state.saga_id,
+                    SagaEvent::Completed {
+                        saga_id: state.saga_id.clone(),
+                    },
+                );
+                Ok(())
+            }
+            SagaStatus::Running => {
+                let step = &steps[state.completed];
+                let request_id = format!("{}:{}", state.saga_id, step.name);
+                self.issue(state, shell, &request_id, step.request.clone())
+            }
+            SagaStatus::Compensating => {
+                // skip completed steps that need no compensation.
+                while state.completed > 0 && steps[state.completed - 1].compensation.is_none() {
+                    state.completed -= 1;
+                }
+
+                if state.completed == 0 {
+                    state.status = SagaStatus::Compensated;
+                    self.store.save(&state)?;
+                    self.notify(
+                        &mut shell,
+                        &state.saga_id,
+                        SagaEvent::Compensated {
+                            saga_id: state.saga_id.clone(),
+                        },
+                    );
+                    return Ok(());
+                }
+
+                let step = &steps[state.completed - 1];
+                let request_id = format!("{}:{}:compensate", state.saga_id, step.name);
+                let compensation = step.compensation.clone().expect("has compensation");
+                self.issue(state, shell, &request_id, compensation)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // issue sends the request of the current step, scheduling the
+    // saga to move forward once the reply arrives.
+    fn issue(
+        &self,
+        state: SagaState,
+        mut shell: impl MasterShell<Events = E, Requests = R>,
+        request_id: &str,
+        request: R,
+    ) -> SagaResult<()> {
+        self.store.save(&state)?;
+        debug!("Saga {} issuing request {}", state.saga_id, request_id);
+
+        let receiver = shell
+            .send_request(NamedRequest::new(request_id, request))
+            .map_err(|err| SagaError::RequestFailed(err.to_string()))?;
+
+        let saga = self.clone();
+        let next_shell = shell.clone();
+        shell.schedule(receiver, move |reply| async move {
+            if let Err(err) = saga.on_reply(state, reply, next_shell) {
+                error!("Saga failed to move forward: {}", err);
+            }
+        })?;
+        Ok(())
+    }
+
+    fn on_reply(
+        &self,
+        mut state: SagaState,
+        reply: mspc::ChannelResult<NamedEvent<E>>,
+        mut shell: impl MasterShell<Events = E, Requests = R>,
+    ) -> SagaResult<()> {
+        let succeeded = match &reply {
+            Ok(event) => !self.definition.is_failure(event),
+            Err(_) => false,
+        };
+
+        let saga_id = state.saga_id.clone();
+        match state.status {
+            SagaStatus::Running => {
+                let step = self.definition.steps[state.completed].name.clone();
+                if succeeded {
+                    state.completed += 1;
+                    self.notify(
+                        &mut shell,
+                        &saga_id,
+                        SagaEvent::StepCompleted {
+                            saga_id: saga_id.clone(),
+                            step,
+                        },
+                    );
+                } else {
+                    state.failed_step = Some(state.completed);
+                    state.status = SagaStatus::Compensating;
+                    self.notify(
+                        &mut shell,
+                        &saga_id,
+                        SagaEvent::StepFailed {
+                            saga_id: saga_id.clone(),
+                            step,
+                        },
+                    );
+                }
+            }
+            SagaStatus::Compensating => {
+                let step = self.definition.steps[state.completed - 1].name.clone();
+                if succeeded {
+                    state.completed -= 1;
+                    self.notify(
+                        &mut shell,
+                        &saga_id,
+                        SagaEvent::StepCompensated {
+                            saga_id: saga_id.clone(),
+                            step,
+                        },
+                    );
+                } else {
+                    state.status = SagaStatus::Failed;
+                    self.store.save(&state)?;
+                    self.notify(
+                        &mut shell,
+                        &saga_id,
+                        SagaEvent::CompensationFailed {
+                            saga_id: saga_id.clone(),
+                            step,
+                        },
+                    );
+                    return Ok(());
+                }
+            }
+            _ => return Ok(()),
+        }
+
+        self.advance(state, shell)
+    }
+
+    fn notify(
+        &self,
+        shell: &mut impl MasterShell<Events = E, Requests = R>,
+        saga_id: &str,
+        event: SagaEvent,
+    ) {
+        if let Err(err) = shell.send_all(NamedEvent::new(saga_id, vec![E::from(event)])) {
+            error!("Saga failed to deliver event: {}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use crate::{
+        domains::{self, MasterShell},
+        fixtures::Platform,
+        sagas::{
+            FileSagaStore, MemorySagaStore, Saga, SagaDefinition, SagaError, SagaEvent, SagaState,
+            SagaStatus, SagaStore,
+        },
+        testing::DomainHarness,
+    };
+
+    #[test]
+    fn saga_completes_all_steps_in_order() {
+        let mut harness = DomainHarness::<OrderApp>::new();
+        script_replies(&mut harness, false);
+
+        harness.request("place", OrderRequests::Place("order-complete".into()));
+
+        assert_eq!(
+            requested(&harness),
+            vec![
+                OrderRequests::Reserve,
+                OrderRequests::Charge,
+                OrderRequests::Ship
+            ]
+        );
+        harness.assert_emitted(&OrderEvents::Saga(SagaEvent::Completed {
+            saga_id: "order-complete".into(),
+        }));
+        assert_eq!(status("order-complete"), SagaStatus::Completed);
+    }
+
+    #[test]
+    fn saga_compensates_completed_steps_in_reverse_on_failure() {
+        let mut harness = DomainHarness::<OrderApp>::new();
+        script_replies(&mut harness, true);
+
+        harness.request("place", OrderRequests::Place("order-declined".into()));
+
+        assert_eq!(
+            requested(&harness),
+            vec![
+                OrderRequests::Reserve,
+                OrderRequests::Charge,
+                OrderRequests::Ship,
+                OrderRequests::Refund,
+                OrderRequests::Release,
+            ]
+        );
+        harness.assert_emitted(&OrderEvents::Saga(SagaEvent::StepFailed {
+            saga_id: "order-declined".into(),
+            step: "ship".into(),
+        }));
+        harness.assert_emitted(&OrderEvents::Saga(SagaEvent::Compensated {
+            saga_id: "order-declined".into(),
+        }));
+
+        let state = store().load("order-declined").unwrap().unwrap();
+        assert_eq!(state.status, SagaStatus::Compensated);
+        assert_eq!(state.failed_step, Some(2));
+    }
+
+    #[test]
+    fn saga_resumes_from_persisted_state_after_restart() {
+        {
+            let mut harness = DomainHarness::<OrderApp>::new();
+            harness.on_request(
+                |req| matches!(req, OrderRequests::Reserve),
+                |_| vec![OrderEvents::Done],
+            );
+
+            // the charge request is never answered before the "restart".
+            harness.request("place", OrderRequests::Place("order-resumed".into()));
+
+            let state = store().load("order-resumed").unwrap().unwrap();
+            assert_eq!(state.status, SagaStatus::Running);
+            assert_eq!(state.completed, 1);
+        }
+
+        let mut harness = DomainHarness::<OrderApp>::new();
+        script_replies(&mut harness, false);
+
+        harness.request("resume", OrderRequests::Resume);
+
+        assert_eq!(
+            requested(&harness),
+            vec![OrderRequests::Charge, OrderRequests::Ship]
+        );
+        assert_eq!(status("order-resumed"), SagaStatus::Completed);
+    }
+
+    #[test]
+    fn saga_refuses_to_resume_states_beyond_its_steps() {
+        let store = MemorySagaStore::new();
+        let saga = order_saga(store.clone());
+        store
+            .save(&SagaState {
+                saga_id: "order-corrupt".into(),
+                definition: "order".into(),
+                status: SagaStatus::Compensating,
+                completed: 5,
+                failed_step: Some(5),
+            })
+            .expect("should save state");
+
+        let mut harness = DomainHarness::<OrderApp>::new();
+        assert!(matches!(
+            saga.resume("order-corrupt", harness.shell().clone()),
+            Err(SagaError::InvalidState(_))
+        ));
+        assert!(matches!(
+            saga.resume_all(harness.shell().clone()),
+            Err(SagaError::InvalidState(_))
+        ));
+        assert!(harness.outgoing_requests().is_empty());
+    }
+
+    #[test]
+    fn file_store_persists_saga_state() {
+        let directory =
+            std::env::temp_dir().join(format!("ewe_domain_sagas_{}", std::process::id()));
+        let store = FileSagaStore::new(&directory).expect("should create store");
+
+        let state = SagaState {
+            saga_id: "order-1".into(),
+            definition: "order".into(),
+            status: SagaStatus::Compensating,
+            completed: 2,
+            failed_step: Some(2),
+        };
+        store.save(&state).expect("should save state");
+
+        assert_eq!(store.load("order-1").unwrap(), Some(state.clone()));
+        assert_eq!(store.load("order-2").unwrap(), None);
+        assert_eq!(store.list().unwrap(), vec![state]);
+
+        std::fs::remove_dir_all(directory).expect("should remove store");
+    }
+
+    fn store() -> MemorySagaStore {
+        static STORE: OnceLock<MemorySagaStore> = OnceLock::new();
+        STORE.get_or_init(MemorySagaStore::new).clone()
+    }
+
+    fn status(saga_id: &str) -> SagaStatus {
+        store()
+            .load(saga_id)
+            .unwrap()
+            .expect("should have saga")
+            .status
+    }
+
+    fn requested(harness: &DomainHarness<OrderApp>) -> Vec<OrderRequests> {
+        harness
+            .outgoing_requests()
+            .iter()
+            .map(|req| req.item())
+            .collect()
+    }
+
+    fn script_replies(harness: &mut DomainHarness<OrderApp>, decline_shipping: bool) {
+        harness.on_request(
+            |req| matches!(req, OrderRequests::Ship),
+            move |_| {
+                if decline_shipping {
+                    vec![OrderEvents::Declined]
+                } else {
+                    vec![OrderEvents::Done]
+                }
+            },
+        );
+        harness.on_request(|_| true, |_| vec![OrderEvents::Done]);
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum OrderEvents {
+        Done,
+        Declined,
+        Saga(SagaEvent),
+    }
+
+    impl From<SagaEvent> for OrderEvents {
+        fn from(value: SagaEvent) -> Self {
+            OrderEvents::Saga(value)
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum OrderRequests {
+        Place(String),
+        Resume,
+        Reserve,
+        Release,
+        Charge,
+        Refund,
+        Ship,
+    }
+
+    #[derive(Clone)]
+    struct OrderApp {
+        saga: Saga<OrderEvents, OrderRequests>,
+    }
+
+    fn order_saga(store: MemorySagaStore) -> Saga<OrderEvents, OrderRequests> {
+        let definition = SagaDefinition::new("order")
+            .step(
+                "reserve",
+                OrderRequests::Reserve,
+                Some(OrderRequests::Release),
+            )
+            .step("charge", OrderRequests::Charge, Some(OrderRequests::Refund))
+            .step("ship", OrderRequests::Ship, None)
+            .with_failure_check(|reply| reply.items().contains(&OrderEvents::Declined));
+        Saga::new(definition, store)
+    }
+
+    impl Default for OrderApp {
+        fn default() -> Self {
+            Self {
+                saga: order_saga(store()),
+            }
+        }
+    }
+
+    impl domains::Domain for OrderApp {
+        type Events = OrderEvents;
+        type Requests = OrderRequests;
+        type Platform = Platform;
+        type Error = String;
+
+        fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            _chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+            shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            match req.item() {
+                OrderRequests::Place(order_id) => {
+                    self.saga
+                        .start(&order_id, shell)
+                        .expect("should start saga");
+                }
+                OrderRequests::Resume => {
+                    self.saga.resume_all(shell).expect("should resume sagas");
+                }
+                _ => {}
+            }
+        }
+
+        fn handle_event(
+            &self,
+            _events: domains::NamedEvent<Self::Events>,
+            _shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+        }
+    }
+}
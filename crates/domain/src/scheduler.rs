@@ -0,0 +1,645 @@
+// Module implementing scheduling of one-off and recurring domain requests.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tracing::error;
+
+use crate::{core, domains::NamedRequest, recording, stores::JsonDir};
+
+const MILLIS_PER_MINUTE: u64 = 60_000;
+const MINUTES_PER_DAY: u64 = 1_440;
+
+// bounds the search for the next matching minute of a cron expression,
+// enough to skip over a few years of non-matching months and days.
+const MAX_CRON_STEPS: usize = 100_000;
+
+#[derive(Error, Debug)]
+pub enum ScheduleError {
+    #[error("schedule store failed: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("schedule could not be encoded or decoded: {0}")]
+    Encoding(#[from] serde_json::Error),
+
+    #[error("schedule {0} already exists")]
+    AlreadyExists(String),
+
+    #[error("schedule {0} does not exists")]
+    NotFound(String),
+
+    #[error("invalid cron expression {0:?}: {1}")]
+    InvalidCron(String, String),
+}
+
+pub type ScheduleResult<T> = std::result::Result<T, ScheduleError>;
+
+/// Schedule declares when a scheduled request is delivered to the domain.
+///
+/// Times are milliseconds since the unix epoch, cron expressions use the
+/// usual five fields (minute, hour, day of month, month, day of week)
+/// evaluated in UTC.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Schedule {
+    At { at_ms: u64 },
+    Every { interval_ms: u64 },
+    Cron { expression: String },
+}
+
+impl Schedule {
+    pub fn at(time: SystemTime) -> Self {
+        let at_ms = time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        Self::At { at_ms }
+    }
+
+    pub fn after(delay: Duration) -> Self {
+        Self::at(SystemTime::now() + delay)
+    }
+
+    pub fn every(interval: Duration) -> Self {
+        Self::Every {
+            interval_ms: interval.as_millis().max(1) as u64,
+        }
+    }
+
+    /// cron validates the expression returning its schedule.
+    pub fn cron(expression: impl Into<String>) -> ScheduleResult<Self> {
+        let expression = expression.into();
+        CronExpression::parse(&expression)?;
+        Ok(Self::Cron { expression })
+    }
+
+    /// next_after returns when the schedule is next due after the giving
+    /// time, None once a one-off schedule has passed.
+    pub fn next_after(&self, after_ms: u64) -> ScheduleResult<Option<u64>> {
+        match self {
+            Schedule::At { at_ms } if *at_ms > after_ms => Ok(Some(*at_ms)),
+            Schedule::At { .. } => Ok(None),
+            Schedule::Every { interval_ms } => Ok(Some(after_ms + interval_ms)),
+            Schedule::Cron { expression } => {
+                Ok(CronExpression::parse(expression)?.next_after(after_ms))
+            }
+        }
+    }
+
+    pub fn is_recurring(&self) -> bool {
+        !matches!(self, Schedule::At { .. })
+    }
+}
+
+/// ScheduledRequest is the persisted state of a request registered with
+/// the [`Scheduler`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledRequest<R> {
+    pub schedule_id: String,
+    pub request: R,
+    pub schedule: Schedule,
+    pub next_run_ms: Option<u64>,
+    pub runs: u64,
+}
+
+/// ScheduleStore persists [`ScheduledRequest`]s allowing schedules to
+/// survive restarts of the domain.
+pub trait ScheduleStore<R>: Send + Sync {
+    fn save(&self, scheduled: &ScheduledRequest<R>) -> ScheduleResult<()>;
+
+    fn remove(&self, schedule_id: &str) -> ScheduleResult<()>;
+
+    fn list(&self) -> ScheduleResult<Vec<ScheduledRequest<R>>>;
+}
+
+pub struct MemoryScheduleStore<R> {
+    schedules: Arc<Mutex<HashMap<String, ScheduledRequest<R>>>>,
+}
+
+impl<R> Clone for MemoryScheduleStore<R> {
+    fn clone(&self) -> Self {
+        Self {
+            schedules: self.schedules.clone(),
+        }
+    }
+}
+
+impl<R> Default for MemoryScheduleStore<R> {
+    fn default() -> Self {
+        Self {
+            schedules: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<R> MemoryScheduleStore<R> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<R: Clone + Send> ScheduleStore<R> for MemoryScheduleStore<R> {
+    fn save(&self, scheduled: &ScheduledRequest<R>) -> ScheduleResult<()> {
+        let mut schedules = self.schedules.lock().unwrap();
+        schedules.insert(scheduled.schedule_id.clone(), scheduled.clone());
+        Ok(())
+    }
+
+    fn remove(&self, schedule_id: &str) -> ScheduleResult<()> {
+        self.schedules.lock().unwrap().remove(schedule_id);
+        Ok(())
+    }
+
+    fn list(&self) -> ScheduleResult<Vec<ScheduledRequest<R>>> {
+        Ok(self.schedules.lock().unwrap().values().cloned().collect())
+    }
+}
+
+/// FileScheduleStore persists each schedule as a JSON file within its
+/// directory, schedule ids are limited to ASCII alphanumerics, `-` and `_`.
+pub struct FileScheduleStore {
+    directory: JsonDir,
+}
+
+impl FileScheduleStore {
+    pub fn new(directory: impl Into<PathBuf>) -> ScheduleResult<Self> {
+        Ok(Self {
+            directory: JsonDir::new(directory)?,
+        })
+    }
+}
+
+impl<R: Serialize + DeserializeOwned> ScheduleStore<R> for FileScheduleStore {
+    fn save(&self, scheduled: &ScheduledRequest<R>) -> ScheduleResult<()> {
+        let content = serde_json::to_vec(scheduled)?;
+        Ok(self.directory.write(&scheduled.schedule_id, &content)?)
+    }
+
+    fn remove(&self, schedule_id: &str) -> ScheduleResult<()> {
+        Ok(self.directory.remove(schedule_id)?)
+    }
+
+    fn list(&self) -> ScheduleResult<Vec<ScheduledRequest<R>>> {
+        let mut schedules = Vec::new();
+        for content in self.directory.documents()? {
+            schedules.push(serde_json::from_slice(&content)?);
+        }
+        Ok(schedules)
+    }
+}
+
+struct SchedulerState<R> {
+    schedules: BTreeMap<String, ScheduledRequest<R>>,
+    store: Option<Arc<dyn ScheduleStore<R>>>,
+}
+
+/// Scheduler delivers registered requests to the domain through the same
+/// path as [`crate::domains::DomainShell::do_request`] once due, clones
+/// share the same schedules.
+///
+/// Each delivery is sent as a request identified by `{schedule_id}:{run}`,
+/// a missed delivery, e.g while the domain was not running, is delivered
+/// once on the next run of the executor.
+pub struct Scheduler<R> {
+    state: Arc<Mutex<SchedulerState<R>>>,
+    notifier: core::Notifier,
+}
+
+impl<R> Clone for Scheduler<R> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            notifier: self.notifier.clone(),
+        }
+    }
+}
+
+impl<R: Clone + Send + 'static> Scheduler<R> {
+    pub(crate) fn new(notifier: core::Notifier) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SchedulerState {
+                schedules: BTreeMap::new(),
+                store: None,
+            })),
+            notifier,
+        }
+    }
+
+    /// restore persists schedules to the store from now on, restoring the
+    /// schedules it already holds and returning how many were restored.
+    pub fn restore(&self, store: impl ScheduleStore<R> + 'static) -> ScheduleResult<usize> {
+        let saved = store.list()?;
+        let restored = saved.len();
+
+        let mut state = self.state.lock().unwrap();
+        for scheduled in saved {
+            state
+                .schedules
+                .insert(scheduled.schedule_id.clone(), scheduled);
+        }
+        state.store = Some(Arc::new(store));
+        drop(state);
+
+        self.notifier.notify();
+        Ok(restored)
+    }
+
+    /// add registers the request to be delivered per the schedule.
+    pub fn add(
+        &self,
+        schedule_id: impl Into<String>,
+        request: R,
+        schedule: Schedule,
+    ) -> ScheduleResult<()> {
+        let schedule_id = schedule_id.into();
+        let mut state = self.state.lock().unwrap();
+        if state.schedules.contains_key(&schedule_id) {
+            return Err(ScheduleError::AlreadyExists(schedule_id));
+        }
+
+        let next_run_ms = match schedule {
+            // a one-off schedule in the past is delivered right away.
+            Schedule::At { at_ms } => Some(at_ms),
+            _ => schedule.next_after(recording::now_millis())?,
+        };
+        let scheduled = ScheduledRequest {
+            schedule_id: schedule_id.clone(),
+            request,
+            schedule,
+            next_run_ms,
+            runs: 0,
+        };
+
+        if let Some(store) = state.store.as_ref() {
+            store.save(&scheduled)?;
+        }
+        state.schedules.insert(schedule_id, scheduled);
+        drop(state);
+
+        self.notifier.notify();
+        Ok(())
+    }
+
+    /// cancel removes the schedule returning its last state.
+    pub fn cancel(&self, schedule_id: &str) -> ScheduleResult<ScheduledRequest<R>> {
+        let mut state = self.state.lock().unwrap();
+        let scheduled = state
+            .schedules
+            .remove(schedule_id)
+            .ok_or_else(|| ScheduleError::NotFound(schedule_id.to_string()))?;
+
+        if let Some(store) = state.store.as_ref() {
+            store.remove(schedule_id)?;
+        }
+        Ok(scheduled)
+    }
+
+    /// list returns the registered schedules ordered by their id.
+    pub fn list(&self) -> Vec<ScheduledRequest<R>> {
+        let state = self.state.lock().unwrap();
+        state.schedules.values().cloned().collect()
+    }
+
+    /// next_due returns when the earliest schedule is due.
+    pub fn next_due(&self) -> Option<u64> {
+        let state = self.state.lock().unwrap();
+        state
+            .schedules
+            .values()
+            .filter_map(|scheduled| scheduled.next_run_ms)
+            .min()
+    }
+
+    pub(crate) fn is_due(&self, now_ms: u64) -> bool {
+        self.next_due().is_some_and(|due| due <= now_ms)
+    }
+
+    /// take_due returns the requests of the schedules due by the giving
+    /// time, advancing recurring schedules and removing finished ones.
+    pub(crate) fn take_due(&self, now_ms: u64) -> Vec<NamedRequest<R>> {
+        let mut state = self.state.lock().unwrap();
+        let SchedulerState { schedules, store } = &mut *state;
+
+        let mut due = Vec::new();
+        let mut finished = Vec::new();
+        for scheduled in schedules.values_mut() {
+            if !scheduled.next_run_ms.is_some_and(|next| next <= now_ms) {
+                continue;
+            }
+
+            scheduled.runs += 1;
+            let id = format!("{}:{}", scheduled.schedule_id, scheduled.runs);
+            due.push(NamedRequest::new(&id, scheduled.request.clone()));
+
+            scheduled.next_run_ms = match scheduled.schedule.next_after(now_ms) {
+                Ok(next) if scheduled.schedule.is_recurring() => next,
+                Ok(_) => None,
+                Err(err) => {
+                    error!(
+                        "Schedule {} can not be advanced: {}",
+                        scheduled.schedule_id, err
+                    );
+                    None
+                }
+            };
+
+            let persisted = match (store.as_ref(), scheduled.next_run_ms) {
+                (Some(store), Some(_)) => store.save(scheduled),
+                (Some(store), None) => store.remove(&scheduled.schedule_id),
+                (None, _) => Ok(()),
+            };
+            if let Err(err) = persisted {
+                error!(
+                    "Schedule {} could not be persisted: {}",
+                    scheduled.schedule_id, err
+                );
+            }
+
+            if scheduled.next_run_ms.is_none() {
+                finished.push(scheduled.schedule_id.clone());
+            }
+        }
+
+        for schedule_id in finished {
+            schedules.remove(&schedule_id);
+        }
+        due
+    }
+}
+
+// CronExpression holds the matching values of each cron field as bitsets.
+struct CronExpression {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl CronExpression {
+    fn parse(expression: &str) -> ScheduleResult<Self> {
+        let invalid = |reason: &str| ScheduleError::InvalidCron(expression.into(), reason.into());
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields.as_slice() else {
+            return Err(invalid("expected five fields"));
+        };
+
+        let mut weekday_bits = parse_cron_field(weekdays, 0, 7).map_err(invalid)?;
+        // both 0 and 7 are sunday.
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits = (weekday_bits | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: parse_cron_field(minutes, 0, 59).map_err(invalid)?,
+            hours: parse_cron_field(hours, 0, 23).map_err(invalid)?,
+            days: parse_cron_field(days, 1, 31).map_err(invalid)?,
+            months: parse_cron_field(months, 1, 12).map_err(invalid)?,
+            weekdays: weekday_bits,
+            // like cron, a field starting with `*` is unrestricted even
+            // with a step, e.g `*/2`.
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+
+    // matches_day follows cron in matching either the day of month or the
+    // day of week when both are restricted.
+    fn matches_day(&self, day: u64, weekday: u64) -> bool {
+        let day_matches = self.days & (1 << day) != 0;
+        let weekday_matches = self.weekdays & (1 << weekday) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        }
+    }
+
+    fn next_after(&self, after_ms: u64) -> Option<u64> {
+        let mut minute = after_ms / MILLIS_PER_MINUTE + 1;
+
+        for _ in 0..MAX_CRON_STEPS {
+            let days = minute / MINUTES_PER_DAY;
+            let (year, month, day) = civil_from_days(days);
+
+            if self.months & (1 << month) == 0 {
+                let (year, month) = if month == 12 {
+                    (year + 1, 1)
+                } else {
+                    (year, month + 1)
+                };
+                minute = days_from_civil(year, month, 1) * MINUTES_PER_DAY;
+                continue;
+            }
+
+            // the unix epoch was a thursday.
+            let weekday = (days + 4) % 7;
+            if !self.matches_day(day, weekday) {
+                minute = (days + 1) * MINUTES_PER_DAY;
+                continue;
+            }
+
+            let hour = (minute % MINUTES_PER_DAY) / 60;
+            if self.hours & (1 << hour) == 0 {
+                minute = (minute / 60 + 1) * 60;
+                continue;
+            }
+
+            if self.minutes & (1 << (minute % 60)) == 0 {
+                minute += 1;
+                continue;
+            }
+
+            return Some(minute * MILLIS_PER_MINUTE);
+        }
+        None
+    }
+}
+
+// parse_cron_field parses comma separated values, ranges and steps like
+// `*/15`, `1-5` or `0,30` into a bitset of the matching values, a single
+// value with a step like `5/10` runs up to the maximum as in `5-max/10`.
+fn parse_cron_field(field: &str, min: u64, max: u64) -> Result<u64, &'static str> {
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().map_err(|_| "invalid step")?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err("invalid step");
+        }
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse().map_err(|_| "invalid value")?,
+                    end.parse().map_err(|_| "invalid value")?,
+                ),
+                None => {
+                    let value = range.parse().map_err(|_| "invalid value")?;
+                    if part.contains('/') {
+                        (value, max)
+                    } else {
+                        (value, value)
+                    }
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err("value out of range");
+        }
+
+        for value in (start..=end).step_by(step) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+// civil_from_days converts days since the unix epoch into a (year, month, day)
+// date, see http://howardhinnant.github.io/date_algorithms.html.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+// days_from_civil is the inverse of civil_from_days.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month_index = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scheduler::{
+        days_from_civil, FileScheduleStore, Schedule, ScheduleError, ScheduleStore,
+        ScheduledRequest,
+    };
+
+    const MINUTE: u64 = 60_000;
+
+    fn at(year: u64, month: u64, day: u64, hour: u64, minute: u64) -> u64 {
+        (days_from_civil(year, month, day) * 1_440 + hour * 60 + minute) * MINUTE
+    }
+
+    #[test]
+    fn cron_schedule_finds_next_matching_minute() {
+        let every_quarter = Schedule::cron("*/15 * * * *").expect("should parse");
+        assert_eq!(
+            every_quarter.next_after(at(2024, 3, 10, 8, 7)).unwrap(),
+            Some(at(2024, 3, 10, 8, 15))
+        );
+
+        let weekday_mornings = Schedule::cron("30 9 * * 1-5").expect("should parse");
+        // 2024-03-09 is a saturday.
+        assert_eq!(
+            weekday_mornings.next_after(at(2024, 3, 9, 10, 0)).unwrap(),
+            Some(at(2024, 3, 11, 9, 30))
+        );
+
+        let leap_day = Schedule::cron("0 0 29 2 *").expect("should parse");
+        assert_eq!(
+            leap_day.next_after(at(2024, 3, 1, 0, 0)).unwrap(),
+            Some(at(2028, 2, 29, 0, 0))
+        );
+    }
+
+    #[test]
+    fn cron_schedule_steps_from_a_start_value_and_star_steps_are_unrestricted() {
+        let from_five = Schedule::cron("5/10 * * * *").expect("should parse");
+        assert_eq!(
+            from_five.next_after(at(2024, 3, 10, 8, 7)).unwrap(),
+            Some(at(2024, 3, 10, 8, 15))
+        );
+        assert_eq!(
+            from_five.next_after(at(2024, 3, 10, 8, 55)).unwrap(),
+            Some(at(2024, 3, 10, 9, 5))
+        );
+
+        // a stepped star leaves the day of month unrestricted so only
+        // mondays match, 2024-03-11 is a monday.
+        let mondays = Schedule::cron("0 0 */1 * 1").expect("should parse");
+        assert_eq!(
+            mondays.next_after(at(2024, 3, 11, 1, 0)).unwrap(),
+            Some(at(2024, 3, 18, 0, 0))
+        );
+    }
+
+    #[test]
+    fn cron_schedule_rejects_invalid_expressions() {
+        for expression in ["* * * *", "60 * * * *", "*/0 * * * *", "a * * * *"] {
+            assert!(matches!(
+                Schedule::cron(expression),
+                Err(ScheduleError::InvalidCron(_, _))
+            ));
+        }
+    }
+
+    #[test]
+    fn one_off_and_interval_schedules_advance() {
+        let once = Schedule::At { at_ms: 100 };
+        assert_eq!(once.next_after(50).unwrap(), Some(100));
+        assert_eq!(once.next_after(100).unwrap(), None);
+
+        let every = Schedule::Every { interval_ms: 30 };
+        assert_eq!(every.next_after(100).unwrap(), Some(130));
+    }
+
+    #[test]
+    fn file_store_saves_lists_and_removes_schedules() {
+        let directory = tempfile::tempdir().expect("should create directory");
+        let store = FileScheduleStore::new(directory.path()).expect("should create store");
+
+        let scheduled = |schedule_id: &str, runs: u64| ScheduledRequest {
+            schedule_id: schedule_id.to_string(),
+            request: "tick".to_string(),
+            schedule: Schedule::Every { interval_ms: 30 },
+            next_run_ms: Some(130),
+            runs,
+        };
+        store.save(&scheduled("first", 0)).expect("should save");
+        store.save(&scheduled("second", 0)).expect("should save");
+        store.save(&scheduled("first", 1)).expect("should replace");
+
+        let mut listed: Vec<ScheduledRequest<String>> = store.list().expect("should list");
+        listed.sort_by(|a, b| a.schedule_id.cmp(&b.schedule_id));
+        assert_eq!(listed, vec![scheduled("first", 1), scheduled("second", 0)]);
+
+        ScheduleStore::<String>::remove(&store, "first").expect("should remove");
+        let listed: Vec<ScheduledRequest<String>> = store.list().expect("should list");
+        assert_eq!(listed, vec![scheduled("second", 0)]);
+
+        assert!(matches!(
+            store.save(&scheduled("../escape", 0)),
+            Err(ScheduleError::Io(_))
+        ));
+    }
+}
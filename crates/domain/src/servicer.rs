@@ -5,11 +5,28 @@ use ewe_channels::{
     mspc::{self, ChannelError},
 };
 
-use std::sync;
+use std::{
+    any::Any,
+    fmt::Debug,
+    panic, sync,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, error};
 
 use crate::{
-    domains::{self, DomainErrors, DomainResult, NamedEvent, NamedRequest},
+    core,
+    domains::{
+        self, DomainErrors, DomainFailure, DomainResponse, DomainResponseReceiver, DomainResult,
+        MasterShell, NamedEvent, NamedRequest,
+    },
+    introspection::{DomainSnapshot, Introspector, RecentEvents},
+    limits::{ConcurrencyLimits, RequestQueue},
     pending_chan::{self, PendingChannelError},
+    recording::{self, RecordedEntry},
+    scheduler::Scheduler,
+    supervisor::{self, Handler, Supervisor, SupervisorEvent, SupervisorPolicy},
 };
 
 const DEFAULT_SUBSCRIBER_START_CAPACITY: usize = 10;
@@ -18,10 +35,11 @@ pub fn create<
     App,
     E: Send + Clone + 'static,
     R: Send + Clone + 'static,
-    P: Default + Clone + 'static,
->() -> DServicer<App, E, R, P>
+    P: Default + Clone + Send + 'static,
+    Err: Clone + Debug + Send + 'static,
+>() -> DServicer<App, E, R, P, Err>
 where
-    App: domains::Domain<Events = E, Requests = R, Platform = P>,
+    App: domains::Domain<Events = E, Requests = R, Platform = P, Error = Err>,
 {
     let (incoming_request_sender, incoming_request_receiver) = mspc::create();
     let (incoming_event_sender, incoming_event_receiver) = mspc::create();
@@ -29,6 +47,7 @@ where
     let event_broadcast = broadcast::create::<NamedEvent<E>>(DEFAULT_SUBSCRIBER_START_CAPACITY);
     let request_broadcast = broadcast::create::<NamedRequest<R>>(DEFAULT_SUBSCRIBER_START_CAPACITY);
     let response_registry = pending_chan::PendingChannelsRegistry::new();
+    let notifier = core::Notifier::new();
 
     let executor_arc = sync::Arc::new(executor);
 
@@ -41,19 +60,28 @@ where
             request_broadcast: request_broadcast.clone(),
             event_broadcast: event_broadcast.clone(),
             response_registry: response_registry.clone(),
+            recorder: recording::Recorder::new(),
+            scheduler: Scheduler::new(notifier.clone()),
+            recent_events: RecentEvents::new(),
+            notifier,
+            pending_responses: pending_chan::PendingResponses::new(),
+            panics: supervisor::Panics::default(),
         },
         domain_provider: App::default(),
         incoming_request_receiver,
         incoming_event_receiver,
         response_registry,
         execution_service,
+        supervisor: Supervisor::default(),
+        request_queue: RequestQueue::new(),
     }
 }
 
 pub struct DShell<
     E: Send + Clone + 'static,
     R: Send + Clone + 'static,
-    P: Default + Clone + 'static,
+    P: Default + Clone + Send + 'static,
+    Err: Clone + Debug + Send + 'static,
 > {
     shell_platform: P,
     executor: sync::Arc<executor::Executor<NamedEvent<E>>>,
@@ -62,10 +90,20 @@ pub struct DShell<
     incoming_request_sender: mspc::SendChannel<NamedRequest<R>>,
     incoming_event_sender: mspc::SendChannel<NamedEvent<E>>,
     response_registry: pending_chan::PendingChannelsRegistry<NamedEvent<E>>,
+    recorder: recording::Recorder<E, R>,
+    notifier: core::Notifier,
+    pending_responses: pending_chan::PendingResponses<DomainResponse<E, Err>>,
+    scheduler: Scheduler<R>,
+    recent_events: RecentEvents<E>,
+    panics: supervisor::Panics,
 }
 
-impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone + 'static> Clone
-    for DShell<E, R, P>
+impl<
+        E: Send + Clone + 'static,
+        R: Send + Clone + 'static,
+        P: Default + Clone + Send + 'static,
+        Err: Clone + Debug + Send + 'static,
+    > Clone for DShell<E, R, P, Err>
 {
     fn clone(&self) -> Self {
         Self {
@@ -76,21 +114,66 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
             response_registry: self.response_registry.clone(),
             incoming_request_sender: self.incoming_request_sender.clone(),
             incoming_event_sender: self.incoming_event_sender.clone(),
+            recorder: self.recorder.clone(),
+            notifier: self.notifier.clone(),
+            pending_responses: self.pending_responses.clone(),
+            scheduler: self.scheduler.clone(),
+            recent_events: self.recent_events.clone(),
+            panics: self.panics.clone(),
+        }
+    }
+}
+
+impl<
+        E: Send + Clone + 'static,
+        R: Send + Clone + 'static,
+        P: Default + Clone + Send + 'static,
+        Err: Clone + Debug + Send + 'static,
+    > DShell<E, R, P, Err>
+{
+    /// scheduler returns the [`Scheduler`] delivering one-off and recurring
+    /// requests to the domain.
+    pub fn scheduler(&self) -> Scheduler<R> {
+        self.scheduler.clone()
+    }
+
+    fn send_to_domain(
+        &mut self,
+        req: NamedRequest<R>,
+        deadline: Option<Instant>,
+    ) -> domains::DomainOpsResult<DomainResponseReceiver<E, Err>, R> {
+        let receiver = self.pending_responses.register(req.id(), deadline);
+        match self.incoming_request_sender.try_send(req.clone()) {
+            Ok(_) => {
+                self.notifier.notify();
+                Ok(receiver)
+            }
+            Err(_) => {
+                _ = self.pending_responses.finish(&req.id(), None);
+                Err(domains::DomainOpsErrors::UnableToSendRequest(req))
+            }
         }
     }
 }
 
-impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone + 'static>
-    domains::MasterShell for DShell<E, R, P>
+impl<
+        E: Send + Sync + Clone + 'static,
+        R: Send + Sync + Clone + 'static,
+        P: Default + Clone + Send + 'static,
+        Err: Clone + Debug + Send + 'static,
+    > domains::MasterShell for DShell<E, R, P, Err>
 {
     fn send_request(
         &mut self,
         req: NamedRequest<Self::Requests>,
     ) -> domains::DomainOpsResult<mspc::ReceiveChannel<NamedEvent<Self::Events>>, Self::Requests>
     {
+        self.recorder
+            .record(RecordedEntry::OutgoingRequest(req.clone()));
         self.request_broadcast.broadcast(req.clone());
 
         let mut resolution_channel = self.response_registry.register(req.id());
+        self.notifier.notify();
         Ok(resolution_channel
             .1
             .take()
@@ -101,7 +184,11 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
         &mut self,
         event: NamedEvent<Self::Events>,
     ) -> domains::DomainOpsResult<(), Self::Events> {
+        self.recorder
+            .record(RecordedEntry::Published(event.clone()));
+        self.recent_events.push(&event);
         self.event_broadcast.broadcast(event.clone());
+        self.notifier.notify();
         Ok(())
     }
 
@@ -109,16 +196,53 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
         &mut self,
         event: NamedEvent<Self::Events>,
     ) -> domains::DomainOpsResult<(), Self::Events> {
-        self.incoming_event_sender
-            .try_send(event.clone())
-            .expect("send event");
+        if let Err(err) = self.incoming_event_sender.try_send(event.clone()) {
+            error!("Servicer failed to deliver event to the domain: {}", err);
+            return Err(domains::DomainOpsErrors::UnableToDeliverEvents(event));
+        }
+        self.recorder
+            .record(RecordedEntry::Published(event.clone()));
+        self.recent_events.push(&event);
         self.event_broadcast.broadcast(event);
+        self.notifier.notify();
         Ok(())
     }
+
+    fn fail(
+        &mut self,
+        id: domains::Id,
+        err: Self::Error,
+    ) -> domains::DomainOpsResult<(), domains::Id> {
+        match self
+            .pending_responses
+            .finish(&id, Some(Err(DomainFailure::Failed(err))))
+        {
+            Ok(()) => Ok(()),
+            Err(PendingChannelError::NotFound(_)) => Err(domains::DomainOpsErrors::NotFound(id)),
+            Err(PendingChannelError::ClosedSender(_)) => {
+                Err(domains::DomainOpsErrors::ClosedChannel(id))
+            }
+        }
+    }
+
+    fn report_panic(&mut self, id: domains::Id, handler: Handler, payload: Box<dyn Any + Send>) {
+        if handler == Handler::Request {
+            let message = supervisor::panic_message(payload.as_ref());
+            _ = self
+                .pending_responses
+                .finish(&id, Some(Err(DomainFailure::Panicked(message))));
+        }
+        self.panics.push(id, handler, payload);
+        self.notifier.notify();
+    }
 }
 
-impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone + 'static>
-    domains::DomainShell for DShell<E, R, P>
+impl<
+        E: Send + Sync + Clone + 'static,
+        R: Send + Sync + Clone + 'static,
+        P: Default + Clone + Send + 'static,
+        Err: Clone + Debug + Send + 'static,
+    > domains::DomainShell for DShell<E, R, P, Err>
 {
     type Events = E;
 
@@ -126,6 +250,8 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
 
     type Platform = P;
 
+    type Error = Err;
+
     fn platform(&self) -> Self::Platform
     where
         Self: Sized,
@@ -141,6 +267,12 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
         Self: Sized,
     {
         match self.response_registry.resolve(id.clone()) {
+            Ok(sender) if self.recorder.is_recording() => Ok(recording::forward(
+                self.recorder.clone(),
+                &self.executor,
+                RecordedEntry::Reply,
+                sender,
+            )),
             Ok(sender) => Ok(sender),
             Err(pending_chan::PendingChannelError::NotFound(_)) => {
                 Err(domains::DomainOpsErrors::NotFound(id))
@@ -154,19 +286,24 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
     fn do_request(
         &mut self,
         req: NamedRequest<Self::Requests>,
-    ) -> domains::DomainOpsResult<mspc::ReceiveChannel<NamedEvent<Self::Events>>, Self::Requests>
+    ) -> domains::DomainOpsResult<DomainResponseReceiver<Self::Events, Self::Error>, Self::Requests>
     where
         Self: Sized,
     {
-        // create resolution channel group, send the RetreiveChannel to the user.
-        let mut resolution_channel = self.response_registry.register(req.id());
-        match self.incoming_request_sender.try_send(req.clone()) {
-            Ok(_) => Ok(resolution_channel
-                .1
-                .take()
-                .expect("should have receiving channel")),
-            Err(_) => Err(domains::DomainOpsErrors::UnableToSendRequest(req)),
-        }
+        self.send_to_domain(req, None)
+    }
+
+    fn do_request_with_timeout(
+        &mut self,
+        req: NamedRequest<Self::Requests>,
+        timeout: Duration,
+    ) -> domains::DomainOpsResult<DomainResponseReceiver<Self::Events, Self::Error>, Self::Requests>
+    {
+        self.send_to_domain(req, Some(Instant::now() + timeout))
+    }
+
+    fn notifier(&self) -> Option<core::Notifier> {
+        Some(self.notifier.clone())
     }
 
     fn schedule<Fut>(
@@ -179,7 +316,10 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
         Self: Sized,
     {
         match self.executor.schedule(receiver, receiver_fn) {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.notifier.notify();
+                Ok(())
+            }
             Err(_) => Err(domains::DomainErrors::FailedScheduling),
         }
     }
@@ -192,7 +332,10 @@ impl<E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone +
         Self: Sized,
     {
         match self.executor.spawn(fut) {
-            Ok(_) => Ok(()),
+            Ok(_) => {
+                self.notifier.notify();
+                Ok(())
+            }
             Err(_) => Err(domains::DomainErrors::FailedScheduling),
         }
     }
@@ -220,28 +363,32 @@ pub struct DServicer<
     App,
     E: Send + Clone + 'static,
     R: Send + Clone + 'static,
-    P: Default + Clone + 'static,
+    P: Default + Clone + Send + 'static,
+    Err: Clone + Debug + Send + 'static,
 > where
-    App: domains::Domain<Events = E, Requests = R, Platform = P>,
+    App: domains::Domain<Events = E, Requests = R, Platform = P, Error = Err>,
 {
     domain_provider: App,
-    domain_shell: DShell<E, R, P>,
+    domain_shell: DShell<E, R, P, Err>,
     execution_service: executor::ExecutionService<NamedEvent<E>>,
     incoming_request_receiver: mspc::ReceiveChannel<NamedRequest<R>>,
     incoming_event_receiver: mspc::ReceiveChannel<NamedEvent<E>>,
     response_registry: pending_chan::PendingChannelsRegistry<NamedEvent<E>>,
+    supervisor: Supervisor,
+    request_queue: RequestQueue<R>,
 }
 
 pub fn create_shell<
     App,
     E: Send + Clone + 'static,
     R: Send + Clone + 'static,
-    P: Default + Clone + 'static,
+    P: Default + Clone + Send + 'static,
+    Err: Clone + Debug + Send + 'static,
 >(
-    _servicer: Box<DServicer<App, E, R, P>>,
-) -> DShell<E, R, P>
+    _servicer: Box<DServicer<App, E, R, P, Err>>,
+) -> DShell<E, R, P, Err>
 where
-    App: domains::Domain<Events = E, Requests = R, Platform = P>,
+    App: domains::Domain<Events = E, Requests = R, Platform = P, Error = Err>,
 {
     DShell {
         incoming_event_sender: _servicer.domain_shell.incoming_event_sender.clone(),
@@ -251,13 +398,24 @@ where
         request_broadcast: _servicer.domain_shell.request_broadcast.clone(),
         event_broadcast: _servicer.domain_shell.event_broadcast.clone(),
         response_registry: _servicer.domain_shell.response_registry.clone(),
+        recorder: _servicer.domain_shell.recorder.clone(),
+        notifier: _servicer.domain_shell.notifier.clone(),
+        pending_responses: _servicer.domain_shell.pending_responses.clone(),
+        scheduler: _servicer.domain_shell.scheduler.clone(),
+        recent_events: _servicer.domain_shell.recent_events.clone(),
+        panics: _servicer.domain_shell.panics.clone(),
     }
 }
 
-impl<A, E: Send + Clone + 'static, R: Send + Clone + 'static, P: Clone + Default + 'static> Clone
-    for DServicer<A, E, R, P>
+impl<
+        A,
+        E: Send + Clone + 'static,
+        R: Send + Clone + 'static,
+        P: Clone + Default + Send + 'static,
+        Err: Clone + Debug + Send + 'static,
+    > Clone for DServicer<A, E, R, P, Err>
 where
-    A: domains::Domain<Events = E, Requests = R, Platform = P>,
+    A: domains::Domain<Events = E, Requests = R, Platform = P, Error = Err>,
 {
     fn clone(&self) -> Self {
         Self {
@@ -267,20 +425,122 @@ where
             incoming_request_receiver: self.incoming_request_receiver.clone(),
             incoming_event_receiver: self.incoming_event_receiver.clone(),
             response_registry: self.response_registry.clone(),
+            supervisor: self.supervisor.clone(),
+            request_queue: self.request_queue.clone(),
         }
     }
 }
 
-impl<A, E: Send + Clone + 'static, R: Send + Clone + 'static, P: Clone + Default + 'static>
-    DServicer<A, E, R, P>
+impl<
+        A,
+        E: Send + Sync + Clone + 'static,
+        R: Send + Sync + Clone + 'static,
+        P: Clone + Default + Send + 'static,
+        Err: Clone + Debug + Send + 'static,
+    > DServicer<A, E, R, P, Err>
 where
-    A: domains::Domain<Events = E, Requests = R, Platform = P>,
+    A: domains::Domain<Events = E, Requests = R, Platform = P, Error = Err>,
 {
+    /// recorder returns the [`recording::Recorder`] capturing the requests,
+    /// events and responses flowing through this servicer once started.
+    pub fn recorder(&self) -> recording::Recorder<E, R> {
+        self.domain_shell.recorder.clone()
+    }
+
+    /// supervisor returns the [`Supervisor`] handling panics of the domain's
+    /// handlers, which restarts the domain from its `Default` unless
+    /// configured otherwise via [`DServicer::supervise`].
+    pub fn supervisor(&self) -> Supervisor {
+        self.supervisor.clone()
+    }
+
+    /// supervise applies the giving [`SupervisorPolicy`] to panics of the
+    /// domain's handlers, delivering the [`SupervisorEvent`]s to the domain's
+    /// listeners.
+    pub fn supervise(&self, policy: SupervisorPolicy)
+    where
+        E: From<SupervisorEvent>,
+    {
+        let mut shell = self.domain_shell.clone();
+        let notify = Box::new(move |id: &domains::Id, event: SupervisorEvent| {
+            let event = NamedEvent::new(&id.0, vec![E::from(event)]);
+            if let Err(err) = shell.send_others(event) {
+                error!("Servicer failed to deliver supervisor event: {}", err);
+            }
+        });
+        self.supervisor.configure(policy, notify);
+    }
+
+    /// introspector returns an [`Introspector`] taking snapshots of the
+    /// domain while it runs, e.g to serve them via a
+    /// [`crate::introspection::DebugServer`].
+    pub fn introspector(&self) -> Introspector<E, R, Err> {
+        let shell = &self.domain_shell;
+        Introspector {
+            response_registry: self.response_registry.clone(),
+            pending_responses: shell.pending_responses.clone(),
+            request_queue: self.request_queue.clone(),
+            event_broadcast: shell.event_broadcast.clone(),
+            request_broadcast: shell.request_broadcast.clone(),
+            executor: shell.executor.clone(),
+            recent_events: shell.recent_events.clone(),
+            supervisor: self.supervisor.clone(),
+            scheduler: shell.scheduler.clone(),
+        }
+    }
+
+    /// introspect returns a [`DomainSnapshot`] of the domain's pending
+    /// requests, subscribers, tasks and recent events.
+    pub fn introspect(&self) -> DomainSnapshot<E> {
+        self.introspector().snapshot()
+    }
+
+    /// limit applies the giving [`ConcurrencyLimits`] to requests handed to
+    /// the domain from now on.
+    pub fn limit(&self, limits: ConcurrencyLimits<R>) {
+        self.request_queue.set_limits(limits);
+    }
+
+    /// queue_depth returns how many requests wait to be handled by the domain.
+    pub fn queue_depth(&self) -> usize {
+        self.request_queue.depth()
+    }
+
+    /// in_flight returns the ids of the requests handled by the domain whose
+    /// response is not finished yet.
+    pub fn in_flight(&self) -> Vec<domains::Id> {
+        let responses = &self.domain_shell.pending_responses;
+        self.request_queue.settle(|id| responses.has(id));
+        self.request_queue.in_flight()
+    }
+
+    // recover applies the supervisor's policy to the panic of a handler.
+    fn recover(&mut self, id: &domains::Id, handler: Handler, payload: Box<dyn Any + Send>) {
+        let message = supervisor::panic_message(payload.as_ref());
+        match self.supervisor.on_panic(id, handler, message) {
+            SupervisorPolicy::Restart => self.domain_provider = A::default(),
+            SupervisorPolicy::Stop => debug!("Servicer stopped serving the domain"),
+            SupervisorPolicy::Escalate => panic::resume_unwind(payload),
+        }
+    }
+
     fn process_incoming_event(&mut self) -> DomainResult<()> {
         match self.incoming_event_receiver.try_receive() {
-            Ok(request) => {
-                self.domain_provider
-                    .handle_event(request, self.domain_shell.clone());
+            Ok(event) if self.supervisor.is_stopped() => {
+                debug!("Servicer is stopped, dropping event {}", event);
+                Ok(())
+            }
+            Ok(event) => {
+                let id = event.id();
+                self.domain_shell
+                    .recorder
+                    .record(RecordedEntry::Event(event.clone()));
+
+                let domain = &self.domain_provider;
+                let shell = self.domain_shell.clone();
+                if let Err(payload) = supervisor::catch(|| domain.handle_event(event, shell)) {
+                    self.recover(&id, Handler::Event, payload);
+                }
                 Ok(())
             }
             Err(ChannelError::ReceivedNoData) => Ok(()),
@@ -289,81 +549,253 @@ where
         }
     }
 
-    fn process_incoming_request(&mut self) -> DomainResult<()> {
-        match self.incoming_request_receiver.try_receive() {
-            Ok(request) => match self.response_registry.resolve(request.id()) {
-                Ok(sender) => {
-                    self.domain_provider
-                        .handle_request(request, sender, self.domain_shell.clone());
-                    Ok(())
+    // receive_requests queues all requests sent to the domain so far,
+    // handing them to the domain as they arrive as far as the concurrency
+    // limits allow and rejecting those arriving to a full queue.
+    fn receive_requests(&mut self) -> DomainResult<()> {
+        let responses = self.domain_shell.pending_responses.clone();
+        loop {
+            match self.incoming_request_receiver.try_receive() {
+                Ok(request) => {
+                    // the request's pending response expired while it
+                    // waited in the channel, no one awaits it anymore.
+                    if !responses.has(&request.id()) {
+                        debug!("Skipping request {} whose response expired", request);
+                        continue;
+                    }
+
+                    if let Err(request) = self.request_queue.push(request) {
+                        debug!("Servicer queue is full, rejecting {}", request);
+                        let failure =
+                            DomainFailure::Rejected(String::from("request queue is full"));
+                        _ = responses.finish(&request.id(), Some(Err(failure)));
+                    }
+                    self.process_queued_requests()?;
+                }
+                Err(mspc::ChannelError::Closed) => return Err(DomainErrors::ClosedRequestReceiver),
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    // process_queued_requests hands the queued requests to the domain as
+    // far as the concurrency limits allow.
+    fn process_queued_requests(&mut self) -> DomainResult<()> {
+        let responses = self.domain_shell.pending_responses.clone();
+        if self.supervisor.is_stopped() {
+            for request in self.request_queue.drain() {
+                _ = responses.finish(&request.id(), Some(Err(DomainFailure::Stopped)));
+            }
+            return Ok(());
+        }
+
+        loop {
+            self.request_queue.settle(|id| responses.has(id));
+            match self.request_queue.next() {
+                Some(request) => self.process_request(request)?,
+                None => return Ok(()),
+            }
+
+            // the request's handler panicked and stopped the domain.
+            if self.supervisor.is_stopped() {
+                return self.process_queued_requests();
+            }
+        }
+    }
+
+    fn process_request(&mut self, request: NamedRequest<R>) -> DomainResult<()> {
+        let id = request.id();
+        let recorder = self.domain_shell.recorder.clone();
+        recorder.record(RecordedEntry::Request(request.clone()));
+
+        let sender = self.forward_responses(id.clone())?;
+        let sender = if recorder.is_recording() {
+            recording::forward(
+                recorder,
+                &self.domain_shell.executor,
+                RecordedEntry::Response,
+                sender,
+            )
+        } else {
+            sender
+        };
+
+        let domain = &self.domain_provider;
+        let shell = self.domain_shell.clone();
+        let handled = supervisor::catch(|| domain.handle_request(request, sender, shell));
+
+        if let Err(payload) = handled {
+            let message = supervisor::panic_message(payload.as_ref());
+            _ = self
+                .domain_shell
+                .pending_responses
+                .finish(&id, Some(Err(DomainFailure::Panicked(message))));
+            self.recover(&id, Handler::Request, payload);
+        }
+        Ok(())
+    }
+
+    // forward_responses returns the channel the domain responds to the request
+    // with, relaying every response to the requester till the channel closes.
+    fn forward_responses(&self, id: domains::Id) -> DomainResult<mspc::SendChannel<NamedEvent<E>>> {
+        let (sender, mut receiver) = mspc::create::<NamedEvent<E>>();
+        let responses = self.domain_shell.pending_responses.clone();
+
+        let forwarding = self.domain_shell.executor.spawn(async move {
+            while let Ok(event) = receiver.async_receive().await {
+                // the request was already failed or timed out.
+                if responses.send(&id, Ok(event)).is_err() {
+                    return;
                 }
-                Err(PendingChannelError::ClosedSender(_)) => {
-                    Err(DomainErrors::UnexpectedSenderClosure)
+            }
+            _ = responses.finish(&id, None);
+        });
+
+        match forwarding {
+            Ok(_) => Ok(sender),
+            Err(_) => Err(DomainErrors::FailedSpawning),
+        }
+    }
+
+    // deliver_scheduled sends the due scheduled requests to the domain,
+    // their responses are only looked at for failures.
+    fn deliver_scheduled(&mut self) -> DomainResult<()> {
+        let due = self
+            .domain_shell
+            .scheduler
+            .take_due(recording::now_millis());
+
+        for request in due {
+            let id = request.id();
+            let mut receiver = match self.domain_shell.send_to_domain(request, None) {
+                Ok(receiver) => receiver,
+                Err(err) => {
+                    error!("Servicer failed to deliver scheduled request: {}", err);
+                    continue;
                 }
-                Err(PendingChannelError::NotFound(_)) => Err(DomainErrors::RequestSenderNotFound),
-            },
-            Err(mspc::ChannelError::Closed) => Err(DomainErrors::ClosedRequestReceiver),
-            _ => Ok(()),
+            };
+
+            let watching = self.domain_shell.executor.spawn(async move {
+                while let Ok(response) = receiver.async_receive().await {
+                    if let Err(failure) = response {
+                        error!("Scheduled request {} failed: {}", id, failure);
+                    }
+                }
+            });
+            if watching.is_err() {
+                return Err(DomainErrors::FailedSpawning);
+            }
+        }
+        Ok(())
+    }
+
+    // expire_requests fails the requests whose deadline passed.
+    fn expire_requests(&mut self) {
+        let responses = &self.domain_shell.pending_responses;
+        for id in responses.expired_ids(Instant::now()) {
+            _ = responses.finish(&id, Some(Err(DomainFailure::TimedOut)));
         }
     }
 
     fn close(&mut self) {
         self.execution_service.close();
         self.response_registry.clear();
+        self.domain_shell.pending_responses.clear();
     }
 
     pub fn serve(&mut self) -> domains::DomainResult<()> {
-        self.serve_events().expect("served events");
-        self.serve_requests().expect("served requests");
-        Ok(())
+        let served = self
+            .deliver_scheduled()
+            .and(self.serve_events())
+            .and(self.serve_requests());
+        self.expire_requests();
+        self.recover_spawned();
+        served
+    }
+
+    // recover_spawned applies the supervisor's policy to the panics caught
+    // within the futures the domain's handlers spawned.
+    fn recover_spawned(&mut self) {
+        for (id, handler, payload) in self.domain_shell.panics.take() {
+            self.recover(&id, handler, payload);
+        }
     }
 
     fn serve_events(&mut self) -> domains::DomainResult<()> {
-        (match self.process_incoming_event() {
-            Ok(_) => Ok(()),
+        match self.process_incoming_event() {
             Err(DomainErrors::RequestSenderNotFound) => Err(DomainErrors::ProblematicState),
             Err(DomainErrors::UnexpectedSenderClosure) => Err(DomainErrors::ProblematicState),
             _ => Ok(()),
-        })
-        .expect("event processing should have finished with no issues");
+        }?;
 
-        (match self.execution_service.schedule_serve() {
-            Ok(_) => Ok(()),
-            Err(executor::ExecutorError::Decommission) => Err(DomainErrors::ProblematicState),
-            _ => Ok(()),
-        })
-        .expect("execution service should have ended better");
-
-        Ok(())
+        self.serve_tasks()
     }
 
     fn serve_requests(&mut self) -> domains::DomainResult<()> {
-        (match self.process_incoming_request() {
-            Ok(_) => Ok(()),
+        match self
+            .process_queued_requests()
+            .and_then(|_| self.receive_requests())
+        {
             Err(DomainErrors::RequestSenderNotFound) => Err(DomainErrors::ProblematicState),
             Err(DomainErrors::UnexpectedSenderClosure) => Err(DomainErrors::ProblematicState),
             _ => Ok(()),
-        })
-        .expect("request processing should have finished with no issues");
+        }?;
+
+        self.serve_tasks()
+    }
 
-        (match self.execution_service.schedule_serve() {
-            Ok(_) => Ok(()),
+    fn serve_tasks(&mut self) -> domains::DomainResult<()> {
+        match self.execution_service.schedule_serve() {
             Err(executor::ExecutorError::Decommission) => Err(DomainErrors::ProblematicState),
             _ => Ok(()),
-        })
-        .expect("execution service should have ended better");
-
-        Ok(())
+        }
     }
 }
 
-impl<A, E: Send + Clone + 'static, R: Send + Clone + 'static, P: Default + Clone + 'static>
-    domains::TaskExecutor for DServicer<A, E, R, P>
+impl<
+        A,
+        E: Send + Sync + Clone + 'static,
+        R: Send + Sync + Clone + 'static,
+        P: Default + Clone + Send + 'static,
+        Err: Clone + Debug + Send + 'static,
+    > domains::TaskExecutor for DServicer<A, E, R, P, Err>
 where
-    A: domains::Domain<Events = E, Requests = R, Platform = P>,
+    A: domains::Domain<Events = E, Requests = R, Platform = P, Error = Err>,
 {
     fn run_tasks(&mut self) {
-        self.serve().expect("execute all tasks with no errors");
+        if let Err(err) = self.serve() {
+            error!("Servicer failed serving the domain: {}", err);
+        }
+    }
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        // registered first so work arriving while checking still wakes us.
+        self.domain_shell.notifier.register(cx.waker());
+
+        let responses = &self.domain_shell.pending_responses;
+        self.request_queue.settle(|id| responses.has(id));
+
+        let has_requests = matches!(self.incoming_request_receiver.is_empty(), Ok(false))
+            || self.request_queue.has_next();
+        let has_events = matches!(self.incoming_event_receiver.is_empty(), Ok(false));
+        let has_tasks = self.execution_service.poll_ready(cx).is_ready();
+        let has_expired = self
+            .domain_shell
+            .pending_responses
+            .next_deadline()
+            .is_some_and(|deadline| deadline <= Instant::now());
+
+        let has_scheduled = self.domain_shell.scheduler.is_due(recording::now_millis());
+        let has_panics = !self.domain_shell.panics.is_empty();
+
+        if has_requests || has_events || has_tasks || has_expired || has_scheduled || has_panics {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+
+    fn next_wake(&self) -> Option<Instant> {
+        self.domain_shell.pending_responses.next_deadline()
     }
 }
 
@@ -372,11 +804,15 @@ mod tests {
 
     use crate::{
         app,
-        domains::{self, DomainOpsResult, DomainShell},
+        domains::{self, DomainFailure, DomainOpsResult, DomainShell, MasterShell},
+        limits::ConcurrencyLimits,
+        policies,
+        scheduler::{MemoryScheduleStore, Schedule, ScheduleError, ScheduleStore},
         servicer,
+        supervisor::{Handler, SupervisorEvent, SupervisorPolicy},
     };
     use crossbeam::atomic;
-    use std::sync;
+    use std::{sync, time::Duration};
     use tracing::info;
 
     #[test]
@@ -394,7 +830,10 @@ mod tests {
 
         let mut receiver = result.expect("expected a receiver");
 
-        let item = receiver.block_receive().expect("should receive value");
+        let item = receiver
+            .block_receive()
+            .expect("should receive value")
+            .expect("should succeed");
 
         let items = item.items();
 
@@ -441,7 +880,10 @@ mod tests {
 
         let mut receiver = result.expect("expected a receiver");
 
-        let item = receiver.block_receive().expect("should receive value");
+        let item = receiver
+            .block_receive()
+            .expect("should receive value")
+            .expect("should succeed");
 
         let items = item.items();
 
@@ -497,121 +939,874 @@ mod tests {
         assert!(!count_render.data.lock().unwrap().is_empty());
     }
 
-    #[derive(Default, Clone)]
-    struct Platform {}
+    #[test]
+    fn can_handle_requests_with_an_async_domain() {
+        let (mut executor, server) = app::create_async::<AsyncCounterApp>();
+        let mut shell = servicer::create_shell(server);
 
-    #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
-    struct CounterModel {
-        pub count: i16,
-    }
+        let request = domains::NamedRequest::new("increment_count", CounterRequests::Increment);
 
-    #[derive(Clone)]
-    struct CounterRender {
-        pub data: sync::Arc<sync::Mutex<Vec<String>>>,
-    }
+        let mut receiver = shell.do_request(request).expect("expected a receiver");
 
-    impl CounterRender {
-        pub fn new() -> Self {
-            Self {
-                data: sync::Arc::new(sync::Mutex::new(Vec::new())),
-            }
-        }
+        executor.run_all();
+
+        let item = receiver
+            .block_receive()
+            .expect("should receive value")
+            .expect("should succeed");
+        assert_eq!(
+            item.items(),
+            vec![CounterEvents::Incremented(CounterModel::new(1))]
+        );
     }
 
-    impl domains::UseCase for CounterRender {
-        type Platform = Platform;
-        type Event = CounterEvents;
-        type Request = CounterRequests;
+    #[test]
+    fn can_fail_request_when_async_domain_fails() {
+        let (mut executor, server) = app::create_async::<AsyncCounterApp>();
+        let mut shell = servicer::create_shell(server);
 
-        fn is_request(&self, req: sync::Arc<domains::NamedRequest<Self::Request>>) -> bool {
-            match req.item() {
-                CounterRequests::Render(_) => true,
-                _ => false,
-            }
-        }
+        let request = domains::NamedRequest::new("decrement_count", CounterRequests::Decrement);
 
-        fn handle_request(
-            &mut self,
-            req: sync::Arc<domains::NamedRequest<Self::Request>>,
-            mut chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Event>>,
-            _shell: impl DomainShell<
-                Events = Self::Event,
-                Requests = Self::Request,
-                Platform = Self::Platform,
-            >,
-        ) {
-            if let CounterRequests::Render(model) = req.item() {
-                self.data
-                    .lock()
-                    .unwrap()
-                    .push(format!("Counter(count: {})", model.count));
-            }
-            chan.close().expect("close channel");
-        }
-    }
+        let mut receiver = shell.do_request(request).expect("expected a receiver");
 
-    impl CounterModel {
-        pub fn new(value: i16) -> Self {
-            Self { count: value }
-        }
-    }
+        executor.run_all();
 
-    #[derive(Clone, Debug, PartialEq, Eq)]
-    enum CounterEvents {
-        Incremented(CounterModel),
-        Decremented(CounterModel),
+        assert_eq!(
+            receiver.block_receive().expect("should receive failure"),
+            Err(DomainFailure::Failed(String::from("unsupported request")))
+        );
+        assert!(receiver.block_receive().is_err());
     }
 
-    #[derive(Clone, Debug, PartialEq, Eq)]
-    enum CounterRequests {
-        Increment,
-        Decrement,
-        Render(CounterModel),
-    }
+    #[test]
+    fn can_supervise_panics_of_async_handlers() {
+        let (mut executor, server) = app::create_async::<AsyncCounterApp>();
+        server.supervise(SupervisorPolicy::Restart);
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
 
-    #[derive(Clone)]
-    struct CounterApp {
-        state: sync::Arc<atomic::AtomicCell<CounterModel>>,
-    }
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new(
+                "render",
+                CounterRequests::Render(CounterModel::new(1)),
+            ))
+            .expect("expected a receiver");
+        executor.run_all();
+        executor.run_all();
 
-    impl Default for CounterApp {
-        fn default() -> Self {
-            Self {
-                state: sync::Arc::new(atomic::AtomicCell::new(CounterModel { count: 0 })),
-            }
-        }
+        assert_eq!(
+            receiver.block_receive().expect("should receive failure"),
+            Err(DomainFailure::Panicked(String::from("panicked on purpose")))
+        );
+        assert!(receiver.block_receive().is_err());
+
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new(
+                "increment",
+                CounterRequests::Increment,
+            ))
+            .expect("expected a receiver");
+        executor.run_all();
+        assert!(receiver
+            .block_receive()
+            .expect("should receive value")
+            .is_ok());
+
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut use_case_shell = servicer::create_shell(server);
+        let mut use_case = domains::UseCaseExecutor::new(
+            use_case_shell.clone(),
+            domains::AsyncUseCaseAdapter::new(PanickingAsyncRender {}),
+        );
+        use_case.supervise(SupervisorPolicy::Restart);
+        executor.register(Box::new(use_case));
+        let mut use_case_events = use_case_shell.listen().unwrap();
+
+        let mut render_response = use_case_shell
+            .send_request(domains::NamedRequest::new(
+                "render_count",
+                CounterRequests::Render(CounterModel::new(4)),
+            ))
+            .expect("sent request");
+        executor.run_all();
+        executor.run_all();
+        assert!(render_response.block_receive().is_err());
+
+        let supervisor_events: Vec<CounterEvents> = events
+            .drain()
+            .chain(use_case_events.drain())
+            .flat_map(|event| event.items())
+            .filter(|event| matches!(event, CounterEvents::Supervisor(_)))
+            .collect();
+        assert_eq!(
+            supervisor_events,
+            vec![
+                CounterEvents::Supervisor(SupervisorEvent::Panicked {
+                    handler: Handler::Request,
+                    message: String::from("panicked on purpose"),
+                }),
+                CounterEvents::Supervisor(SupervisorEvent::Restarted),
+                CounterEvents::Supervisor(SupervisorEvent::Panicked {
+                    handler: Handler::UseCase,
+                    message: String::from("panicked on purpose"),
+                }),
+                CounterEvents::Supervisor(SupervisorEvent::Restarted),
+            ]
+        );
     }
 
-    impl domains::Domain for CounterApp {
-        type Events = CounterEvents;
-        type Requests = CounterRequests;
-        type Platform = Platform;
+    #[test]
+    fn can_fail_request_explicitly_from_domain() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        let mut shell = servicer::create_shell(server);
 
-        fn handle_request(
-            &self,
-            req: domains::NamedRequest<Self::Requests>,
-            mut chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Events>>,
-            mut shell: impl domains::MasterShell<
-                Events = Self::Events,
-                Requests = Self::Requests,
-                Platform = Self::Platform,
-            >,
-        ) {
-            match req.item() {
-                CounterRequests::Increment => {
-                    let current = self.state.load();
-                    let next = CounterModel {
-                        count: current.count + 1,
-                    };
-                    self.state.swap(next.clone());
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new("fail", FaultyRequests::Fail))
+            .expect("expected a receiver");
 
-                    let event = req.to_one(CounterEvents::Incremented(next));
-                    chan.try_send(event.clone())
-                        .expect("should have sent message");
+        executor.run_all();
 
-                    shell
-                        .send_all(event)
-                        .expect("should notify interested parties on important change");
+        assert_eq!(
+            receiver.try_receive().expect("should receive failure"),
+            Err(DomainFailure::Failed(String::from("failed on purpose")))
+        );
+        assert!(shell
+            .fail(domains::Id(String::from("fail")), String::from("again"))
+            .is_err());
+    }
+
+    #[test]
+    fn can_surface_panics_of_request_handlers() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        let mut shell = servicer::create_shell(server);
+
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new("panic", FaultyRequests::Panic))
+            .expect("expected a receiver");
+
+        executor.run_all();
+
+        assert_eq!(
+            receiver.try_receive().expect("should receive failure"),
+            Err(DomainFailure::Panicked(String::from("panicked on purpose")))
+        );
+    }
+
+    #[test]
+    fn can_restart_domain_from_default_after_panics() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        server.supervise(SupervisorPolicy::Restart);
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
+
+        let count = |shell: &mut servicer::DShell<_, _, _, _>,
+                     executor: &mut crate::core::CoreExecutor,
+                     id: &str| {
+            let mut receiver = shell
+                .do_request(domains::NamedRequest::new(id, FaultyRequests::Count))
+                .expect("expected a receiver");
+            executor.run_all();
+            receiver
+                .try_receive()
+                .expect("should receive response")
+                .expect("should succeed")
+                .items()
+        };
+
+        count(&mut shell, &mut executor, "first");
+        count(&mut shell, &mut executor, "second");
+
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new("panic", FaultyRequests::Panic))
+            .expect("expected a receiver");
+        executor.run_all();
+        assert_eq!(
+            receiver.try_receive().expect("should receive failure"),
+            Err(DomainFailure::Panicked(String::from("panicked on purpose")))
+        );
+
+        assert_eq!(
+            count(&mut shell, &mut executor, "third"),
+            vec![CounterEvents::Incremented(CounterModel::new(1))]
+        );
+
+        let supervisor_events: Vec<CounterEvents> = events
+            .drain()
+            .flat_map(|event| event.items())
+            .filter(|event| matches!(event, CounterEvents::Supervisor(_)))
+            .collect();
+        assert_eq!(
+            supervisor_events,
+            vec![
+                CounterEvents::Supervisor(SupervisorEvent::Panicked {
+                    handler: Handler::Request,
+                    message: String::from("panicked on purpose"),
+                }),
+                CounterEvents::Supervisor(SupervisorEvent::Restarted),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_stop_domain_after_event_handler_panics() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        server.supervise(SupervisorPolicy::Stop);
+        let supervisor = server.supervisor();
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
+
+        shell
+            .send_all(domains::NamedEvent::new(
+                "decrement",
+                vec![CounterEvents::Decremented(CounterModel::new(1))],
+            ))
+            .expect("should send event");
+        executor.run_all();
+        assert!(supervisor.is_stopped());
+
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new("count", FaultyRequests::Count))
+            .expect("expected a receiver");
+        executor.run_all();
+        assert_eq!(
+            receiver.try_receive().expect("should receive failure"),
+            Err(DomainFailure::Stopped)
+        );
+
+        let supervisor_events: Vec<CounterEvents> = events
+            .drain()
+            .flat_map(|event| event.items())
+            .filter(|event| matches!(event, CounterEvents::Supervisor(_)))
+            .collect();
+        assert_eq!(
+            supervisor_events,
+            vec![
+                CounterEvents::Supervisor(SupervisorEvent::Panicked {
+                    handler: Handler::Event,
+                    message: String::from("cannot handle decrements"),
+                }),
+                CounterEvents::Supervisor(SupervisorEvent::Stopped),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_escalate_panics_to_the_executor() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        server.supervise(SupervisorPolicy::Escalate);
+        let mut shell = servicer::create_shell(server);
+
+        let _receiver = shell
+            .do_request(domains::NamedRequest::new("panic", FaultyRequests::Panic))
+            .expect("expected a receiver");
+
+        let escalated = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            executor.run_all();
+        }));
+        assert!(escalated.is_err());
+    }
+
+    #[test]
+    fn can_restart_use_case_after_panics() {
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
+
+        let mut use_case_executor =
+            domains::UseCaseExecutor::new(shell.clone(), PanickingRender::default());
+        use_case_executor.supervise(SupervisorPolicy::Restart);
+        executor.register(Box::new(use_case_executor));
+
+        let mut render = |id: &str, count: i16| {
+            let receiver = shell
+                .send_request(domains::NamedRequest::new(
+                    id,
+                    CounterRequests::Render(CounterModel::new(count)),
+                ))
+                .expect("sent request");
+            executor.run_all();
+            receiver
+        };
+
+        let mut first = render("first", 1);
+        assert_eq!(
+            first.block_receive().expect("should render").items(),
+            vec![CounterEvents::Incremented(CounterModel::new(1))]
+        );
+
+        let mut panicked = render("negative", -1);
+        assert!(panicked.block_receive().is_err());
+
+        let mut restarted = render("restarted", 2);
+        assert_eq!(
+            restarted.block_receive().expect("should render").items(),
+            vec![CounterEvents::Incremented(CounterModel::new(1))]
+        );
+
+        let supervisor_events: Vec<CounterEvents> = events
+            .drain()
+            .flat_map(|event| event.items())
+            .filter(|event| matches!(event, CounterEvents::Supervisor(_)))
+            .collect();
+        assert_eq!(
+            supervisor_events,
+            vec![
+                CounterEvents::Supervisor(SupervisorEvent::Panicked {
+                    handler: Handler::UseCase,
+                    message: String::from("cannot render negative counts"),
+                }),
+                CounterEvents::Supervisor(SupervisorEvent::Restarted),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_limit_in_flight_requests_and_reject_beyond_queue_depth() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        server.limit(
+            ConcurrencyLimits::new()
+                .with_max_in_flight(1)
+                .with_max_queued(1),
+        );
+        let mut shell = servicer::create_shell(server.clone());
+
+        let mut receivers: Vec<_> = ["first", "second", "third"]
+            .into_iter()
+            .map(|id| {
+                shell
+                    .do_request(domains::NamedRequest::new(id, FaultyRequests::Stall))
+                    .expect("expected a receiver")
+            })
+            .collect();
+
+        executor.run_all();
+
+        assert_eq!(server.in_flight(), vec![domains::Id(String::from("first"))]);
+        assert_eq!(server.queue_depth(), 1);
+        assert_eq!(
+            receivers[2]
+                .try_receive()
+                .expect("should receive rejection"),
+            Err(DomainFailure::Rejected(String::from(
+                "request queue is full"
+            )))
+        );
+
+        // failing the stalled request lets the queued one through.
+        shell
+            .fail(domains::Id(String::from("first")), String::from("gave up"))
+            .expect("should fail request");
+        executor.run_all();
+
+        assert!(receivers[0].try_receive().expect("should fail").is_err());
+        assert_eq!(
+            server.in_flight(),
+            vec![domains::Id(String::from("second"))]
+        );
+        assert_eq!(server.queue_depth(), 0);
+    }
+
+    #[test]
+    fn can_deliver_scheduled_requests_to_the_domain() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
+        let scheduler = shell.scheduler();
+
+        scheduler
+            .add("once", FaultyRequests::Count, Schedule::At { at_ms: 0 })
+            .expect("should schedule");
+        scheduler
+            .add(
+                "tick",
+                FaultyRequests::Count,
+                Schedule::every(Duration::from_millis(50)),
+            )
+            .expect("should schedule");
+        assert!(matches!(
+            scheduler.add("tick", FaultyRequests::Count, Schedule::At { at_ms: 0 }),
+            Err(ScheduleError::AlreadyExists(_))
+        ));
+
+        executor.run_all();
+
+        let listed: Vec<String> = scheduler
+            .list()
+            .into_iter()
+            .map(|scheduled| scheduled.schedule_id)
+            .collect();
+        assert_eq!(listed, vec![String::from("tick")]);
+
+        std::thread::sleep(Duration::from_millis(60));
+        executor.run_all();
+
+        let cancelled = scheduler.cancel("tick").expect("should cancel");
+        assert_eq!(cancelled.runs, 1);
+        assert!(scheduler.list().is_empty());
+
+        std::thread::sleep(Duration::from_millis(10));
+        executor.run_all();
+
+        let delivered: Vec<domains::Id> = events.drain().map(|event| event.id()).collect();
+        assert_eq!(
+            delivered,
+            vec![
+                domains::Id(String::from("once:1")),
+                domains::Id(String::from("tick:1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_restore_schedules_after_restart() {
+        let store = MemoryScheduleStore::new();
+
+        {
+            let (_executor, server) = app::create::<FaultyApp>();
+            let shell = servicer::create_shell(server);
+            let scheduler = shell.scheduler();
+            scheduler.restore(store.clone()).expect("should restore");
+
+            scheduler
+                .add("missed", FaultyRequests::Count, Schedule::At { at_ms: 0 })
+                .expect("should schedule");
+            scheduler
+                .add(
+                    "hourly",
+                    FaultyRequests::Count,
+                    Schedule::cron("0 * * * *").expect("should parse"),
+                )
+                .expect("should schedule");
+        }
+
+        let (mut executor, server) = app::create::<FaultyApp>();
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
+        let scheduler = shell.scheduler();
+        assert_eq!(scheduler.restore(store.clone()).expect("should restore"), 2);
+
+        executor.run_all();
+
+        let delivered: Vec<domains::Id> = events.drain().map(|event| event.id()).collect();
+        assert_eq!(delivered, vec![domains::Id(String::from("missed:1"))]);
+
+        let remaining: Vec<String> = store
+            .list()
+            .expect("should list")
+            .into_iter()
+            .map(|scheduled| scheduled.schedule_id)
+            .collect();
+        assert_eq!(remaining, vec![String::from("hourly")]);
+    }
+
+    #[test]
+    fn can_time_out_requests_without_response() {
+        let (mut executor, server) = app::create::<FaultyApp>();
+        let mut shell = servicer::create_shell(server);
+
+        let mut receiver = shell
+            .do_request_with_timeout(
+                domains::NamedRequest::new("stall", FaultyRequests::Stall),
+                Duration::from_millis(10),
+            )
+            .expect("expected a receiver");
+
+        executor.run_all();
+        assert!(receiver.try_receive().is_err());
+
+        std::thread::sleep(Duration::from_millis(20));
+        executor.run_all();
+
+        assert_eq!(
+            receiver.try_receive().expect("should receive failure"),
+            Err(DomainFailure::TimedOut)
+        );
+    }
+
+    #[test]
+    fn can_fail_to_send_events_once_the_servicer_is_gone() {
+        let (executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+        drop(executor);
+
+        let event = domains::NamedEvent::new(
+            "incremented",
+            vec![CounterEvents::Incremented(CounterModel::new(1))],
+        );
+        assert!(matches!(
+            shell.send_all(event),
+            Err(domains::DomainOpsErrors::UnableToDeliverEvents(_))
+        ));
+    }
+
+    #[test]
+    fn can_skip_queued_requests_whose_response_expired() {
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+
+        let mut expired = shell
+            .do_request(domains::NamedRequest::new(
+                "expired",
+                CounterRequests::Increment,
+            ))
+            .expect("expected a receiver");
+
+        // the response expires before the servicer got to the request.
+        _ = shell.pending_responses.finish(
+            &domains::Id(String::from("expired")),
+            Some(Err(DomainFailure::TimedOut)),
+        );
+
+        let mut receiver = shell
+            .do_request(domains::NamedRequest::new(
+                "increment",
+                CounterRequests::Increment,
+            ))
+            .expect("expected a receiver");
+
+        executor.run_all();
+
+        assert_eq!(
+            expired.try_receive().expect("should receive failure"),
+            Err(DomainFailure::TimedOut)
+        );
+        assert_eq!(
+            receiver
+                .try_receive()
+                .expect("should receive value")
+                .expect("should succeed")
+                .items(),
+            vec![CounterEvents::Incremented(CounterModel::new(1))]
+        );
+    }
+
+    #[test]
+    fn can_use_async_use_case_implementation_with_an_app() {
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+
+        // the second use-case matching the request leaves it to the first.
+        for _ in 0..2 {
+            executor.register(Box::new(domains::UseCaseExecutor::new(
+                shell.clone(),
+                domains::AsyncUseCaseAdapter::new(AsyncCounterRender {}),
+            )));
+        }
+
+        let mut render_response = shell
+            .send_request(domains::NamedRequest::new(
+                "render_count",
+                CounterRequests::Render(CounterModel::new(4)),
+            ))
+            .expect("sent request");
+
+        // first run hands the request to the use-case, second polls its future.
+        executor.run_all();
+        executor.run_all();
+
+        let item = render_response
+            .block_receive()
+            .expect("should receive value");
+        assert_eq!(
+            item.items(),
+            vec![CounterEvents::Incremented(CounterModel::new(4))]
+        );
+    }
+
+    #[test]
+    fn can_answer_requests_failed_by_async_use_case_with_failure_event() {
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+
+        executor.register(Box::new(domains::UseCaseExecutor::new(
+            shell.clone(),
+            domains::AsyncUseCaseAdapter::new(FailingRender::default()),
+        )));
+
+        let mut render_response = shell
+            .send_request(domains::NamedRequest::new(
+                "render_count",
+                CounterRequests::Render(CounterModel::new(4)),
+            ))
+            .expect("sent request");
+
+        executor.run_all();
+        executor.run_all();
+
+        let item = render_response
+            .block_receive()
+            .expect("should receive failure");
+        assert_eq!(
+            item.items(),
+            vec![CounterEvents::Failed(domains::UseCaseFailed {
+                error: String::from("\"render service unavailable\"")
+            })]
+        );
+    }
+
+    #[test]
+    fn can_retry_failing_use_case_and_deliver_fallback() {
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
+
+        let failing_render = FailingRender::default();
+        let policy = policies::UseCasePolicy::new()
+            .with_retry(policies::RetryPolicy::new(
+                2,
+                Duration::from_millis(0),
+                Duration::from_millis(0),
+            ))
+            .with_fallback(|_| vec![CounterEvents::Incremented(CounterModel::new(0))])
+            .with_failure_check(is_use_case_failure);
+
+        executor.register(Box::new(domains::UseCaseExecutor::with_policy(
+            shell.clone(),
+            domains::AsyncUseCaseAdapter::new(failing_render.clone()),
+            policy,
+        )));
+
+        let mut render_response = shell
+            .send_request(domains::NamedRequest::new(
+                "render_count",
+                CounterRequests::Render(CounterModel::new(4)),
+            ))
+            .expect("sent request");
+
+        for _ in 0..10 {
+            executor.run_all();
+        }
+
+        assert_eq!(*failing_render.attempts.lock().unwrap(), 3);
+
+        let item = render_response
+            .block_receive()
+            .expect("should receive fallback");
+        assert_eq!(
+            item.items(),
+            vec![CounterEvents::Incremented(CounterModel::new(0))]
+        );
+
+        let policy_events: Vec<CounterEvents> =
+            events.drain().flat_map(|event| event.items()).collect();
+        assert_eq!(
+            policy_events,
+            vec![
+                CounterEvents::Policy(policies::PolicyEvent::Retrying {
+                    attempt: 1,
+                    backoff: Duration::from_millis(0)
+                }),
+                CounterEvents::Policy(policies::PolicyEvent::Retrying {
+                    attempt: 2,
+                    backoff: Duration::from_millis(0)
+                }),
+                CounterEvents::Policy(policies::PolicyEvent::Exhausted { attempts: 3 }),
+                CounterEvents::Policy(policies::PolicyEvent::FallbackUsed),
+            ]
+        );
+    }
+
+    #[test]
+    fn can_reject_requests_when_use_case_circuit_is_open() {
+        let (mut executor, server) = app::create::<CounterApp>();
+        let mut shell = servicer::create_shell(server);
+        let mut events = shell.listen().unwrap();
+
+        let failing_render = FailingRender::default();
+        let policy = policies::UseCasePolicy::new()
+            .with_circuit_breaker(policies::CircuitBreakerPolicy::new(
+                1,
+                Duration::from_secs(60),
+            ))
+            .with_failure_check(is_use_case_failure);
+
+        executor.register(Box::new(domains::UseCaseExecutor::with_policy(
+            shell.clone(),
+            domains::AsyncUseCaseAdapter::new(failing_render.clone()),
+            policy,
+        )));
+
+        let mut first_response = shell
+            .send_request(domains::NamedRequest::new(
+                "render_first",
+                CounterRequests::Render(CounterModel::new(1)),
+            ))
+            .expect("sent request");
+
+        for _ in 0..5 {
+            executor.run_all();
+        }
+
+        let mut second_response = shell
+            .send_request(domains::NamedRequest::new(
+                "render_second",
+                CounterRequests::Render(CounterModel::new(2)),
+            ))
+            .expect("sent request");
+
+        for _ in 0..5 {
+            executor.run_all();
+        }
+
+        assert!(first_response.block_receive().is_err());
+        assert!(second_response.block_receive().is_err());
+        assert_eq!(*failing_render.attempts.lock().unwrap(), 1);
+
+        let policy_events: Vec<CounterEvents> =
+            events.drain().flat_map(|event| event.items()).collect();
+        assert!(policy_events.contains(&CounterEvents::Policy(
+            policies::PolicyEvent::CircuitOpened { failures: 1 }
+        )));
+        assert!(policy_events.contains(&CounterEvents::Policy(
+            policies::PolicyEvent::CircuitRejected
+        )));
+    }
+
+    #[derive(Default, Clone)]
+    struct Platform {}
+
+    #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+    struct CounterModel {
+        pub count: i16,
+    }
+
+    #[derive(Clone)]
+    struct CounterRender {
+        pub data: sync::Arc<sync::Mutex<Vec<String>>>,
+    }
+
+    impl CounterRender {
+        pub fn new() -> Self {
+            Self {
+                data: sync::Arc::new(sync::Mutex::new(Vec::new())),
+            }
+        }
+    }
+
+    impl domains::UseCase for CounterRender {
+        type Platform = Platform;
+        type Event = CounterEvents;
+        type Request = CounterRequests;
+
+        fn is_request(&self, req: sync::Arc<domains::NamedRequest<Self::Request>>) -> bool {
+            match req.item() {
+                CounterRequests::Render(_) => true,
+                _ => false,
+            }
+        }
+
+        fn handle_request(
+            &mut self,
+            req: sync::Arc<domains::NamedRequest<Self::Request>>,
+            mut chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Event>>,
+            _shell: impl DomainShell<
+                Events = Self::Event,
+                Requests = Self::Request,
+                Platform = Self::Platform,
+            >,
+        ) {
+            if let CounterRequests::Render(model) = req.item() {
+                self.data
+                    .lock()
+                    .unwrap()
+                    .push(format!("Counter(count: {})", model.count));
+            }
+            chan.close().expect("close channel");
+        }
+    }
+
+    impl CounterModel {
+        pub fn new(value: i16) -> Self {
+            Self { count: value }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum CounterEvents {
+        Incremented(CounterModel),
+        Decremented(CounterModel),
+        Policy(policies::PolicyEvent),
+        Supervisor(SupervisorEvent),
+        Failed(domains::UseCaseFailed),
+    }
+
+    impl From<domains::UseCaseFailed> for CounterEvents {
+        fn from(value: domains::UseCaseFailed) -> Self {
+            CounterEvents::Failed(value)
+        }
+    }
+
+    impl From<policies::PolicyEvent> for CounterEvents {
+        fn from(value: policies::PolicyEvent) -> Self {
+            CounterEvents::Policy(value)
+        }
+    }
+
+    impl From<SupervisorEvent> for CounterEvents {
+        fn from(value: SupervisorEvent) -> Self {
+            CounterEvents::Supervisor(value)
+        }
+    }
+
+    fn is_use_case_failure(event: &domains::NamedEvent<CounterEvents>) -> bool {
+        event
+            .items()
+            .iter()
+            .any(|item| matches!(item, CounterEvents::Failed(_)))
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum CounterRequests {
+        Increment,
+        Decrement,
+        Render(CounterModel),
+    }
+
+    #[derive(Clone)]
+    struct CounterApp {
+        state: sync::Arc<atomic::AtomicCell<CounterModel>>,
+    }
+
+    impl Default for CounterApp {
+        fn default() -> Self {
+            Self {
+                state: sync::Arc::new(atomic::AtomicCell::new(CounterModel { count: 0 })),
+            }
+        }
+    }
+
+    impl domains::Domain for CounterApp {
+        type Events = CounterEvents;
+        type Requests = CounterRequests;
+        type Platform = Platform;
+        type Error = String;
+
+        fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            mut chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+            mut shell: impl domains::MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            match req.item() {
+                CounterRequests::Increment => {
+                    let current = self.state.load();
+                    let next = CounterModel {
+                        count: current.count + 1,
+                    };
+                    self.state.swap(next.clone());
+
+                    let event = req.to_one(CounterEvents::Incremented(next));
+                    chan.try_send(event.clone())
+                        .expect("should have sent message");
+
+                    shell
+                        .send_all(event)
+                        .expect("should notify interested parties on important change");
                 }
                 CounterRequests::Decrement => {
                     let current = self.state.load();
@@ -640,6 +1835,7 @@ mod tests {
                 Events = Self::Events,
                 Requests = Self::Requests,
                 Platform = Self::Platform,
+                Error = Self::Error,
             >,
         ) {
             for item in events.items() {
@@ -662,8 +1858,249 @@ mod tests {
                             ))
                             .expect("sent request");
                     }
+                    CounterEvents::Policy(_)
+                    | CounterEvents::Supervisor(_)
+                    | CounterEvents::Failed(_) => {}
                 }
             }
         }
     }
+
+    #[derive(Clone)]
+    struct AsyncCounterRender {}
+
+    impl domains::AsyncUseCase for AsyncCounterRender {
+        type Platform = Platform;
+        type Event = CounterEvents;
+        type Request = CounterRequests;
+        type Error = String;
+
+        fn is_request(&self, req: sync::Arc<domains::NamedRequest<Self::Request>>) -> bool {
+            matches!(req.item(), CounterRequests::Render(_))
+        }
+
+        async fn handle_request(
+            &self,
+            req: sync::Arc<domains::NamedRequest<Self::Request>>,
+            _shell: impl DomainShell<
+                Events = Self::Event,
+                Requests = Self::Request,
+                Platform = Self::Platform,
+            >,
+        ) -> Result<Vec<Self::Event>, Self::Error> {
+            match req.item() {
+                CounterRequests::Render(model) => Ok(vec![CounterEvents::Incremented(model)]),
+                _ => Err(String::from("not a render request")),
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct AsyncCounterApp {
+        state: sync::Arc<atomic::AtomicCell<CounterModel>>,
+    }
+
+    impl domains::AsyncDomain for AsyncCounterApp {
+        type Events = CounterEvents;
+        type Requests = CounterRequests;
+        type Platform = Platform;
+        type Error = String;
+
+        async fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            _shell: impl domains::MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) -> Result<Vec<Self::Events>, Self::Error> {
+            match req.item() {
+                CounterRequests::Increment => {
+                    let current = self.state.load();
+                    let next = CounterModel::new(current.count + 1);
+                    self.state.store(next);
+                    Ok(vec![CounterEvents::Incremented(next)])
+                }
+                CounterRequests::Render(_) => panic!("panicked on purpose"),
+                _ => Err(String::from("unsupported request")),
+            }
+        }
+
+        async fn handle_event(
+            &self,
+            _events: domains::NamedEvent<Self::Events>,
+            _shell: impl domains::MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+        }
+    }
+
+    #[derive(Clone)]
+    struct PanickingAsyncRender {}
+
+    impl domains::AsyncUseCase for PanickingAsyncRender {
+        type Platform = Platform;
+        type Event = CounterEvents;
+        type Request = CounterRequests;
+        type Error = String;
+
+        fn is_request(&self, req: sync::Arc<domains::NamedRequest<Self::Request>>) -> bool {
+            matches!(req.item(), CounterRequests::Render(_))
+        }
+
+        async fn handle_request(
+            &self,
+            _req: sync::Arc<domains::NamedRequest<Self::Request>>,
+            _shell: impl DomainShell<
+                Events = Self::Event,
+                Requests = Self::Request,
+                Platform = Self::Platform,
+            >,
+        ) -> Result<Vec<Self::Event>, Self::Error> {
+            panic!("panicked on purpose")
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct FailingRender {
+        pub attempts: sync::Arc<sync::Mutex<u32>>,
+    }
+
+    impl domains::AsyncUseCase for FailingRender {
+        type Platform = Platform;
+        type Event = CounterEvents;
+        type Request = CounterRequests;
+        type Error = String;
+
+        fn is_request(&self, req: sync::Arc<domains::NamedRequest<Self::Request>>) -> bool {
+            matches!(req.item(), CounterRequests::Render(_))
+        }
+
+        async fn handle_request(
+            &self,
+            _req: sync::Arc<domains::NamedRequest<Self::Request>>,
+            _shell: impl DomainShell<
+                Events = Self::Event,
+                Requests = Self::Request,
+                Platform = Self::Platform,
+            >,
+        ) -> Result<Vec<Self::Event>, Self::Error> {
+            *self.attempts.lock().unwrap() += 1;
+            Err(String::from("render service unavailable"))
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum FaultyRequests {
+        Count,
+        Fail,
+        Panic,
+        Stall,
+    }
+
+    #[derive(Clone, Default)]
+    struct FaultyApp {
+        count: sync::Arc<atomic::AtomicCell<i16>>,
+        stalled: sync::Arc<
+            sync::Mutex<Vec<ewe_channels::mspc::SendChannel<domains::NamedEvent<CounterEvents>>>>,
+        >,
+    }
+
+    impl domains::Domain for FaultyApp {
+        type Events = CounterEvents;
+        type Requests = FaultyRequests;
+        type Platform = Platform;
+        type Error = String;
+
+        fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+            mut shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            match req.item() {
+                FaultyRequests::Count => {
+                    let next = self.count.load() + 1;
+                    self.count.store(next);
+                    let event = req.to_one(CounterEvents::Incremented(CounterModel::new(next)));
+                    shell.send_others(event.clone()).expect("should send event");
+
+                    let mut chan = chan;
+                    chan.try_send(event).expect("should respond");
+                    _ = chan.close();
+                }
+                FaultyRequests::Fail => shell
+                    .fail(req.id(), String::from("failed on purpose"))
+                    .expect("should fail request"),
+                FaultyRequests::Panic => panic!("panicked on purpose"),
+                // keeps the response open without ever responding.
+                FaultyRequests::Stall => self.stalled.lock().unwrap().push(chan),
+            }
+        }
+
+        fn handle_event(
+            &self,
+            events: domains::NamedEvent<Self::Events>,
+            _shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            for event in events.items() {
+                if let CounterEvents::Decremented(_) = event {
+                    panic!("cannot handle decrements");
+                }
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct PanickingRender {
+        renders: i16,
+    }
+
+    impl domains::UseCase for PanickingRender {
+        type Platform = Platform;
+        type Event = CounterEvents;
+        type Request = CounterRequests;
+
+        fn is_request(&self, req: sync::Arc<domains::NamedRequest<Self::Request>>) -> bool {
+            matches!(req.item(), CounterRequests::Render(_))
+        }
+
+        fn handle_request(
+            &mut self,
+            req: sync::Arc<domains::NamedRequest<Self::Request>>,
+            mut chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Event>>,
+            _shell: impl DomainShell<
+                Events = Self::Event,
+                Requests = Self::Request,
+                Platform = Self::Platform,
+            >,
+        ) {
+            if let CounterRequests::Render(model) = req.item() {
+                if model.count < 0 {
+                    panic!("cannot render negative counts");
+                }
+            }
+
+            self.renders += 1;
+            chan.try_send(req.to_one(CounterEvents::Incremented(CounterModel::new(self.renders))))
+                .expect("should respond");
+            chan.close().expect("close channel");
+        }
+    }
 }
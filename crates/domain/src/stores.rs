@@ -0,0 +1,125 @@
+// Module implementing the directory of JSON files backing the file stores.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// JsonDir keeps one JSON document per id within a directory, the file
+/// stores encode and decode the documents, JsonDir only owns the files.
+#[derive(Clone, Debug)]
+pub(crate) struct JsonDir {
+    directory: PathBuf,
+}
+
+impl JsonDir {
+    /// new returns the store of the directory, creating it if missing.
+    pub(crate) fn new(directory: impl Into<PathBuf>) -> io::Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    /// path returns the file of the id, `None` for ids which could name
+    /// files outside of the directory or of another kind.
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| self.directory.join(format!("{}.json", id)))
+    }
+
+    /// write replaces the document of the id, the content is written to a
+    /// uniquely named file within the directory and then moved in place so
+    /// readers and concurrent writers never see a partial document.
+    pub(crate) fn write(&self, id: &str, content: &[u8]) -> io::Result<()> {
+        let Some(path) = self.path(id) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid store id {:?}", id),
+            ));
+        };
+
+        let mut staging = tempfile::NamedTempFile::new_in(&self.directory)?;
+        staging.write_all(content)?;
+        staging.persist(path).map_err(|err| err.error)?;
+        Ok(())
+    }
+
+    /// read returns the document of the id, `None` if there is none or the
+    /// id is invalid.
+    pub(crate) fn read(&self, id: &str) -> io::Result<Option<Vec<u8>>> {
+        let Some(path) = self.path(id) else {
+            return Ok(None);
+        };
+        match fs::read(path) {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// remove deletes the document of the id if there is one.
+    pub(crate) fn remove(&self, id: &str) -> io::Result<()> {
+        let Some(path) = self.path(id) else {
+            return Ok(());
+        };
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// documents returns the content of every document in the directory.
+    pub(crate) fn documents(&self) -> io::Result<Vec<Vec<u8>>> {
+        let mut documents = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                documents.push(fs::read(path)?);
+            }
+        }
+        Ok(documents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::JsonDir;
+
+    #[test]
+    fn json_dir_rejects_ids_naming_other_files() {
+        let directory = tempfile::tempdir().expect("should create directory");
+        let store = JsonDir::new(directory.path()).expect("should create store");
+
+        for id in ["", "../secret", "nested/id", "dotted.id"] {
+            let err = store.write(id, b"{}").expect_err("should reject id");
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            assert_eq!(store.read(id).unwrap(), None);
+        }
+        assert!(store.documents().unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_dir_replaces_documents_without_leaving_staging_files() {
+        let directory = tempfile::tempdir().expect("should create directory");
+        let store = JsonDir::new(directory.path()).expect("should create store");
+
+        store.write("first", b"1").expect("should write");
+        store.write("first", b"2").expect("should write");
+
+        assert_eq!(store.read("first").unwrap(), Some(b"2".to_vec()));
+        assert_eq!(store.documents().unwrap(), vec![b"2".to_vec()]);
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+
+        store.remove("first").expect("should remove");
+        store
+            .remove("first")
+            .expect("should ignore missing documents");
+        assert_eq!(store.read("first").unwrap(), None);
+    }
+}
@@ -0,0 +1,177 @@
+// Module implementing supervision of panicking domain and use-case handlers.
+
+use std::{
+    any::Any,
+    fmt::Display,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+
+use futures::{Future, FutureExt};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::domains::Id;
+
+/// SupervisorPolicy declares how a supervised handler reacts once one of
+/// its invocations panicked, the panic itself is always caught and
+/// reported first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SupervisorPolicy {
+    /// Restart replaces the state of the handler with a fresh one, e.g the
+    /// `Default` of a domain, and continues serving.
+    #[default]
+    Restart,
+
+    /// Stop stops serving further requests and events, requests sent
+    /// afterwards fail with [`crate::domains::DomainFailure::Stopped`].
+    Stop,
+
+    /// Escalate resumes the panic once reported, taking down whoever runs
+    /// the [`crate::core::CoreExecutor`].
+    Escalate,
+}
+
+/// Handler identifies the kind of handler which panicked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Handler {
+    Request,
+    Event,
+    UseCase,
+}
+
+/// SupervisorEvent describes what a [`Supervisor`] observed and did, these
+/// are delivered on the domain's [`crate::domains::DomainShell::listen`]
+/// stream via the domain's event type using its `From<SupervisorEvent>`
+/// implementation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SupervisorEvent {
+    /// The handler panicked handling the request or event.
+    Panicked { handler: Handler, message: String },
+
+    /// The handler's state was replaced after a panic.
+    Restarted,
+
+    /// The handler stopped serving after a panic.
+    Stopped,
+}
+
+impl Display for SupervisorEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SupervisorEvent::Panicked { handler, message } => {
+                write!(f, "Panicked(handler={:?}, message={})", handler, message)
+            }
+            SupervisorEvent::Restarted => write!(f, "Restarted"),
+            SupervisorEvent::Stopped => write!(f, "Stopped"),
+        }
+    }
+}
+
+pub(crate) type NotifyFn = Box<dyn FnMut(&Id, SupervisorEvent) + Send>;
+
+#[derive(Default)]
+struct SupervisorState {
+    policy: SupervisorPolicy,
+    stopped: bool,
+    notify: Option<NotifyFn>,
+}
+
+/// Supervisor applies a [`SupervisorPolicy`] to panics of the handlers it
+/// supervises, clones share the same policy and state.
+#[derive(Clone, Default)]
+pub struct Supervisor {
+    state: Arc<Mutex<SupervisorState>>,
+}
+
+impl Supervisor {
+    pub fn new(policy: SupervisorPolicy) -> Self {
+        let supervisor = Self::default();
+        supervisor.state.lock().unwrap().policy = policy;
+        supervisor
+    }
+
+    pub fn policy(&self) -> SupervisorPolicy {
+        self.state.lock().unwrap().policy
+    }
+
+    /// is_stopped returns true once a panic stopped the supervised handler.
+    pub fn is_stopped(&self) -> bool {
+        self.state.lock().unwrap().stopped
+    }
+
+    pub(crate) fn configure(&self, policy: SupervisorPolicy, notify: NotifyFn) {
+        let mut state = self.state.lock().unwrap();
+        state.policy = policy;
+        state.notify = Some(notify);
+    }
+
+    /// on_panic reports the panic of the handler invoked for the giving id,
+    /// returning the policy the caller must apply.
+    pub(crate) fn on_panic(&self, id: &Id, handler: Handler, message: String) -> SupervisorPolicy {
+        error!("{:?} handler panicked for {}: {}", handler, id, message);
+
+        let mut state = self.state.lock().unwrap();
+        let policy = state.policy;
+        if policy == SupervisorPolicy::Stop {
+            state.stopped = true;
+        }
+
+        if let Some(notify) = state.notify.as_mut() {
+            notify(id, SupervisorEvent::Panicked { handler, message });
+            match policy {
+                SupervisorPolicy::Restart => notify(id, SupervisorEvent::Restarted),
+                SupervisorPolicy::Stop => notify(id, SupervisorEvent::Stopped),
+                SupervisorPolicy::Escalate => {}
+            }
+        }
+
+        policy
+    }
+}
+
+/// catch calls the handler, returning the payload of its panic if it panicked.
+pub(crate) fn catch<T>(handler: impl FnOnce() -> T) -> Result<T, Box<dyn Any + Send>> {
+    panic::catch_unwind(AssertUnwindSafe(handler))
+}
+
+/// catch_async awaits the handler's future, returning the payload of its
+/// panic if polling it panicked.
+pub(crate) async fn catch_async<F: Future>(handler: F) -> Result<F::Output, Box<dyn Any + Send>> {
+    AssertUnwindSafe(handler).catch_unwind().await
+}
+
+/// CaughtPanic is the panic of a handler invoked for the giving id.
+pub type CaughtPanic = (Id, Handler, Box<dyn Any + Send>);
+
+/// Panics collects the panics caught within spawned handler futures for
+/// whoever supervises the handler, clones share the same panics.
+#[derive(Clone, Default)]
+pub struct Panics {
+    caught: Arc<Mutex<Vec<CaughtPanic>>>,
+}
+
+impl Panics {
+    pub(crate) fn push(&self, id: Id, handler: Handler, payload: Box<dyn Any + Send>) {
+        self.caught.lock().unwrap().push((id, handler, payload));
+    }
+
+    pub(crate) fn take(&self) -> Vec<CaughtPanic> {
+        std::mem::take(&mut *self.caught.lock().unwrap())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.caught.lock().unwrap().is_empty()
+    }
+}
+
+/// panic_message extracts the message of a caught panic.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+    String::from("unknown panic")
+}
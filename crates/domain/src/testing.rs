@@ -0,0 +1,414 @@
+// Module implementing a test harness for unit testing Domain implementations.
+
+use std::{fmt::Debug, sync::Arc};
+
+use ewe_channels::mspc;
+use tracing::error;
+
+use crate::{
+    app, core,
+    domains::{
+        self, DomainFailure, DomainOpsResult, DomainResponseReceiver, DomainShell, NamedEvent,
+        NamedRequest,
+    },
+    servicer,
+};
+
+// number of consecutive rounds without activity before the harness
+// considers the domain idle.
+const IDLE_ROUNDS: usize = 2;
+
+// upper bound of rounds ran by [`DomainHarness::run_until_idle`], reaching
+// it indicates the domain keeps producing work.
+const MAX_ROUNDS: usize = 1000;
+
+type ReplyMatcher<R> = Box<dyn Fn(&R) -> bool>;
+
+type ReplyFn<E, R> = Box<dyn FnMut(&NamedRequest<R>) -> Vec<E>>;
+
+struct ScriptedReply<E: Clone, R: Clone> {
+    matcher: ReplyMatcher<R>,
+    reply: ReplyFn<E, R>,
+}
+
+/// DomainHarness wires a [`domains::Domain`] with its servicer and executor,
+/// allowing domain logic to be unit tested by sending requests, scripting
+/// fake use-case replies and asserting on the emitted events and outgoing
+/// requests.
+///
+/// # Example:
+///
+/// ```ignore
+/// let mut harness = DomainHarness::<CounterApp>::new();
+/// harness.on_request(
+///     |req| matches!(req, CounterRequests::Render(_)),
+///     |req| vec![CounterEvents::Rendered],
+/// );
+///
+/// let response = harness.request("increment", CounterRequests::Increment);
+/// harness.assert_requested(&CounterRequests::Render(1));
+/// ```
+pub struct DomainHarness<App>
+where
+    App: domains::Domain + 'static,
+{
+    executor: core::CoreExecutor,
+    shell: servicer::DShell<App::Events, App::Requests, App::Platform, App::Error>,
+    event_receiver: mspc::ReceiveChannel<Arc<NamedEvent<App::Events>>>,
+    request_receiver: mspc::ReceiveChannel<Arc<NamedRequest<App::Requests>>>,
+    replies: Vec<ScriptedReply<App::Events, App::Requests>>,
+    events: Vec<NamedEvent<App::Events>>,
+    requests: Vec<NamedRequest<App::Requests>>,
+}
+
+impl<App> Default for DomainHarness<App>
+where
+    App: domains::Domain + 'static,
+    App::Events: Sync,
+    App::Requests: Sync,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<App> DomainHarness<App>
+where
+    App: domains::Domain + 'static,
+    App::Events: Sync,
+    App::Requests: Sync,
+{
+    pub fn new() -> Self {
+        let (executor, server) = app::create::<App>();
+        let mut shell = servicer::create_shell(server);
+
+        Self {
+            event_receiver: shell.listen().expect("should listen to events"),
+            request_receiver: shell.requests().expect("should listen to requests"),
+            replies: Vec::new(),
+            events: Vec::new(),
+            requests: Vec::new(),
+            executor,
+            shell,
+        }
+    }
+
+    /// on_request scripts the events delivered as reply to every outgoing
+    /// request of the domain matching the matcher, replacing the use-case
+    /// that would usually handle it.
+    pub fn on_request(
+        &mut self,
+        matcher: impl Fn(&App::Requests) -> bool + 'static,
+        reply: impl FnMut(&NamedRequest<App::Requests>) -> Vec<App::Events> + 'static,
+    ) -> &mut Self {
+        self.replies.push(ScriptedReply {
+            matcher: Box::new(matcher),
+            reply: Box::new(reply),
+        });
+        self
+    }
+
+    /// send delivers the request to the domain without advancing the executor.
+    pub fn send(
+        &mut self,
+        req: NamedRequest<App::Requests>,
+    ) -> DomainOpsResult<DomainResponseReceiver<App::Events, App::Error>, App::Requests> {
+        self.shell.do_request(req)
+    }
+
+    /// request delivers the request to the domain, runs the executor till idle
+    /// and returns all events the domain responded with, panicking if the
+    /// request failed.
+    pub fn request(&mut self, id: &str, item: App::Requests) -> Vec<App::Events> {
+        match self.try_request(id, item) {
+            Ok(events) => events,
+            Err(failure) => panic!("request {} failed: {}", id, failure),
+        }
+    }
+
+    /// try_request delivers the request to the domain, runs the executor till
+    /// idle and returns all events the domain responded with or the failure
+    /// of the request.
+    pub fn try_request(
+        &mut self,
+        id: &str,
+        item: App::Requests,
+    ) -> Result<Vec<App::Events>, DomainFailure<App::Error>> {
+        let Ok(mut receiver) = self.send(NamedRequest::new(id, item)) else {
+            panic!("failed to deliver request {} to domain", id);
+        };
+
+        self.run_until_idle();
+
+        // the domain closes the response channel once done, which would
+        // make a drain panic.
+        let mut responses = Vec::new();
+        while let Ok(response) = receiver.try_receive() {
+            responses.extend(response?.items());
+        }
+        Ok(responses)
+    }
+
+    /// run_until_idle advances the executor until a few consecutive rounds
+    /// produce no events, outgoing requests or replies, returning the number
+    /// of rounds ran.
+    pub fn run_until_idle(&mut self) -> usize {
+        let mut quiet_rounds = 0;
+        for round in 1..=MAX_ROUNDS {
+            self.executor.run_all();
+
+            if self.collect() {
+                quiet_rounds = 0;
+                continue;
+            }
+
+            quiet_rounds += 1;
+            if quiet_rounds >= IDLE_ROUNDS {
+                return round;
+            }
+        }
+
+        panic!("domain did not become idle after {} rounds", MAX_ROUNDS);
+    }
+
+    // collect captures emitted events and outgoing requests, answering
+    // the requests with scripted replies, returning true if anything happened.
+    fn collect(&mut self) -> bool {
+        let mut active = false;
+
+        for event in self.event_receiver.drain() {
+            self.events.push(event.as_ref().clone());
+            active = true;
+        }
+
+        let outgoing: Vec<Arc<NamedRequest<App::Requests>>> =
+            self.request_receiver.drain().collect();
+        for req in outgoing {
+            self.requests.push(req.as_ref().clone());
+            self.reply(&req);
+            active = true;
+        }
+
+        active
+    }
+
+    fn reply(&mut self, req: &NamedRequest<App::Requests>) {
+        let item = req.item();
+        let Some(scripted) = self
+            .replies
+            .iter_mut()
+            .find(|scripted| (scripted.matcher)(&item))
+        else {
+            return;
+        };
+
+        let events = (scripted.reply)(req);
+        match self.shell.respond(req.id()) {
+            Ok(mut sender) => {
+                if let Err(err) = sender.try_send(req.to(events)) {
+                    error!("DomainHarness failed to deliver reply for {}: {}", req, err);
+                }
+                _ = sender.close();
+            }
+            Err(err) => error!("DomainHarness found no pending request {}: {}", req, err),
+        }
+    }
+
+    /// events returns all events the domain emitted to its listeners.
+    pub fn events(&self) -> &[NamedEvent<App::Events>] {
+        &self.events
+    }
+
+    /// emitted returns the items of all events the domain emitted to its listeners.
+    pub fn emitted(&self) -> Vec<App::Events> {
+        self.events.iter().flat_map(|event| event.items()).collect()
+    }
+
+    /// outgoing_requests returns all requests the domain sent to its use-cases.
+    pub fn outgoing_requests(&self) -> &[NamedRequest<App::Requests>] {
+        &self.requests
+    }
+
+    /// clear forgets all captured events and outgoing requests.
+    pub fn clear(&mut self) {
+        self.events.clear();
+        self.requests.clear();
+    }
+
+    pub fn shell(
+        &mut self,
+    ) -> &mut servicer::DShell<App::Events, App::Requests, App::Platform, App::Error> {
+        &mut self.shell
+    }
+
+    /// executor allows registering real use-cases along side scripted replies.
+    pub fn executor(&mut self) -> &mut core::CoreExecutor {
+        &mut self.executor
+    }
+
+    pub fn assert_emitted(&self, expected: &App::Events)
+    where
+        App::Events: PartialEq + Debug,
+    {
+        let emitted = self.emitted();
+        assert!(
+            emitted.contains(expected),
+            "expected domain to emit {:?}, emitted: {:?}",
+            expected,
+            emitted
+        );
+    }
+
+    pub fn assert_not_emitted(&self, unexpected: &App::Events)
+    where
+        App::Events: PartialEq + Debug,
+    {
+        let emitted = self.emitted();
+        assert!(
+            !emitted.contains(unexpected),
+            "expected domain not to emit {:?}, emitted: {:?}",
+            unexpected,
+            emitted
+        );
+    }
+
+    pub fn assert_requested(&self, expected: &App::Requests)
+    where
+        App::Requests: PartialEq + Debug,
+    {
+        let requested: Vec<App::Requests> = self.requests.iter().map(|req| req.item()).collect();
+        assert!(
+            requested.contains(expected),
+            "expected domain to request {:?}, requested: {:?}",
+            expected,
+            requested
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync;
+
+    use crossbeam::atomic;
+
+    use crate::{
+        domains::{self, DomainShell, MasterShell},
+        fixtures::Platform,
+        testing::DomainHarness,
+    };
+
+    #[test]
+    fn harness_captures_responses_events_and_outgoing_requests() {
+        let mut harness = DomainHarness::<CounterApp>::new();
+
+        let response = harness.request("increment", CounterRequests::Increment);
+
+        assert_eq!(response, vec![CounterEvents::Incremented(1)]);
+        harness.assert_emitted(&CounterEvents::Incremented(1));
+        harness.assert_requested(&CounterRequests::Render(1));
+        harness.assert_not_emitted(&CounterEvents::Rendered(1));
+    }
+
+    #[test]
+    fn harness_delivers_scripted_replies_to_the_domain() {
+        let mut harness = DomainHarness::<CounterApp>::new();
+        harness.on_request(
+            |req| matches!(req, CounterRequests::Render(_)),
+            |req| match req.item() {
+                CounterRequests::Render(count) => vec![CounterEvents::Rendered(count)],
+                _ => vec![],
+            },
+        );
+
+        harness.request("increment", CounterRequests::Increment);
+        harness.request("increment", CounterRequests::Increment);
+
+        assert_eq!(
+            harness.emitted(),
+            vec![
+                CounterEvents::Incremented(1),
+                CounterEvents::Rendered(1),
+                CounterEvents::Incremented(2),
+                CounterEvents::Rendered(2),
+            ]
+        );
+        assert_eq!(harness.outgoing_requests().len(), 2);
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum CounterEvents {
+        Incremented(i16),
+        Rendered(i16),
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    enum CounterRequests {
+        Increment,
+        Render(i16),
+    }
+
+    #[derive(Clone, Default)]
+    struct CounterApp {
+        state: sync::Arc<atomic::AtomicCell<i16>>,
+    }
+
+    impl domains::Domain for CounterApp {
+        type Events = CounterEvents;
+        type Requests = CounterRequests;
+        type Platform = Platform;
+        type Error = String;
+
+        fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            mut chan: ewe_channels::mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+            mut shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            if let CounterRequests::Increment = req.item() {
+                let next = self.state.load() + 1;
+                self.state.store(next);
+
+                let event = req.to_one(CounterEvents::Incremented(next));
+                chan.try_send(event.clone()).expect("should respond");
+                shell.send_all(event).expect("should send event");
+            }
+        }
+
+        fn handle_event(
+            &self,
+            events: domains::NamedEvent<Self::Events>,
+            mut shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            for event in events.items() {
+                if let CounterEvents::Incremented(count) = event {
+                    let rendered = shell
+                        .send_request(domains::NamedRequest::new(
+                            "render",
+                            CounterRequests::Render(count),
+                        ))
+                        .expect("should send request");
+
+                    let mut notifier = shell.clone();
+                    shell
+                        .schedule(rendered, move |reply| async move {
+                            if let Ok(reply) = reply {
+                                notifier.send_others(reply).expect("should publish reply");
+                            }
+                        })
+                        .expect("should schedule reply");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,552 @@
+// Module implementing the Body of requests and responses, letting them
+// stream their content in chunks instead of buffering it whole.
+
+use std::{
+    fmt,
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bytes::{Bytes, BytesMut};
+use ewe_channels::mspc::{self, ChannelError, ReceiveChannel, SendChannel};
+use http::HeaderMap;
+
+use crate::{Request, Response};
+
+// largest chunk read at once from the reader of a body.
+const READ_CHUNK: usize = 16 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BodyError {
+    #[error("body is larger than the limit of {0} bytes")]
+    TooLarge(usize),
+
+    #[error("body stream failed: {0}")]
+    Failed(String),
+}
+
+pub type BodyResult<T> = std::result::Result<T, BodyError>;
+
+impl From<ChannelError> for BodyError {
+    fn from(err: ChannelError) -> Self {
+        BodyError::Failed(err.to_string())
+    }
+}
+
+/// SizeHint describes the bounds of the length of what remains of a body,
+/// letting a server pick between `Content-Length` and chunked encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeHint {
+    lower: u64,
+    upper: Option<u64>,
+}
+
+impl SizeHint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn exact(len: u64) -> Self {
+        Self {
+            lower: len,
+            upper: Some(len),
+        }
+    }
+
+    pub fn with_lower(mut self, lower: u64) -> Self {
+        self.lower = lower;
+        self
+    }
+
+    pub fn with_upper(mut self, upper: u64) -> Self {
+        self.upper = Some(upper);
+        self
+    }
+
+    pub fn lower(&self) -> u64 {
+        self.lower
+    }
+
+    pub fn upper(&self) -> Option<u64> {
+        self.upper
+    }
+
+    /// exact_len returns the length when both bounds agree on it.
+    pub fn exact_len(&self) -> Option<u64> {
+        self.upper.filter(|upper| *upper == self.lower)
+    }
+
+    fn consume(&mut self, len: u64) {
+        self.lower = self.lower.saturating_sub(len);
+        self.upper = self.upper.map(|upper| upper.saturating_sub(len));
+    }
+}
+
+type Trailers = Arc<Mutex<Option<HeaderMap>>>;
+
+struct ChunkStream {
+    chunks: ReceiveChannel<Bytes>,
+    trailers: Trailers,
+    // set by the [`BodySender`] of channel bodies once finished, closing
+    // before then means the sender was dropped midway.
+    finished: Option<Arc<AtomicBool>>,
+    hint: SizeHint,
+    ended: bool,
+}
+
+struct ReaderStream {
+    reader: Box<dyn Read + Send>,
+    remaining: u64,
+}
+
+enum Kind {
+    Empty,
+    Full(Bytes),
+    Stream(ChunkStream),
+    Reader(ReaderStream),
+}
+
+/// Body is the content of a [`Request`] or [`Response`], being either
+/// empty, fully in memory, a stream of chunks received from a
+/// [`ReceiveChannel`] or read from a [`Read`]er, e.g a file.
+///
+/// Streams made with [`Body::channel`] are bounded, their [`BodySender`]
+/// waits for the reader to catch up once the buffer is full, and can end
+/// the body with trailers.
+///
+/// # Example:
+///
+/// ```ignore
+/// let (mut sender, body) = Body::channel(8);
+/// thread::spawn(move || {
+///     for event in events {
+///         sender.send(format!("data: {}\n\n", event))?;
+///     }
+///     sender.finish();
+/// });
+///
+/// Response::new(body, head)
+/// ```
+pub struct Body {
+    kind: Kind,
+}
+
+impl Body {
+    pub fn empty() -> Self {
+        Self { kind: Kind::Empty }
+    }
+
+    pub fn full(bytes: impl Into<Bytes>) -> Self {
+        Self {
+            kind: Kind::Full(bytes.into()),
+        }
+    }
+
+    /// stream returns a body reading its chunks from the channel till it
+    /// is closed.
+    pub fn stream(chunks: ReceiveChannel<Bytes>) -> Self {
+        Self {
+            kind: Kind::Stream(ChunkStream {
+                chunks,
+                trailers: Trailers::default(),
+                finished: None,
+                hint: SizeHint::new(),
+                ended: false,
+            }),
+        }
+    }
+
+    /// reader returns a body of the next `len` bytes of the reader, read
+    /// in chunks as the body is consumed.
+    pub fn reader(reader: impl Read + Send + 'static, len: u64) -> Self {
+        Self {
+            kind: Kind::Reader(ReaderStream {
+                reader: Box::new(reader),
+                remaining: len,
+            }),
+        }
+    }
+
+    /// channel returns a streaming body with the [`BodySender`] feeding
+    /// it, buffering at most `capacity` chunks, a capacity of 0 buffers
+    /// one chunk.
+    pub fn channel(capacity: usize) -> (BodySender, Self) {
+        let (sender, chunks) = mspc::create_bounded(capacity.max(1));
+        let mut body = Self::stream(chunks);
+        let finished = Arc::new(AtomicBool::new(false));
+        let trailers = match &mut body.kind {
+            Kind::Stream(stream) => {
+                stream.finished = Some(finished.clone());
+                stream.trailers.clone()
+            }
+            _ => unreachable!("stream bodies always stream"),
+        };
+        (
+            BodySender {
+                chunks: sender,
+                trailers,
+                finished,
+            },
+            body,
+        )
+    }
+
+    /// with_size_hint sets the size hint of a streaming body, the length
+    /// of other bodies is always known.
+    pub fn with_size_hint(mut self, hint: SizeHint) -> Self {
+        if let Kind::Stream(stream) = &mut self.kind {
+            stream.hint = hint;
+        }
+        self
+    }
+
+    pub fn size_hint(&self) -> SizeHint {
+        match &self.kind {
+            Kind::Empty => SizeHint::exact(0),
+            Kind::Full(bytes) => SizeHint::exact(bytes.len() as u64),
+            Kind::Stream(stream) => stream.hint,
+            Kind::Reader(reader) => SizeHint::exact(reader.remaining),
+        }
+    }
+
+    pub fn is_stream(&self) -> bool {
+        matches!(self.kind, Kind::Stream(_) | Kind::Reader(_))
+    }
+
+    /// is_end_stream returns true once no more chunks can be read.
+    pub fn is_end_stream(&self) -> bool {
+        match &self.kind {
+            Kind::Empty => true,
+            Kind::Full(bytes) => bytes.is_empty(),
+            Kind::Stream(stream) => stream.ended,
+            Kind::Reader(reader) => reader.remaining == 0,
+        }
+    }
+
+    /// next_chunk returns the next chunk of the body, waiting for it when
+    /// streaming and returning `None` once the body ended, failing if its
+    /// [`BodySender`] was dropped without finishing.
+    pub fn next_chunk(&mut self) -> BodyResult<Option<Bytes>> {
+        match &mut self.kind {
+            Kind::Empty => Ok(None),
+            Kind::Full(_) => match std::mem::replace(&mut self.kind, Kind::Empty) {
+                Kind::Full(bytes) if !bytes.is_empty() => Ok(Some(bytes)),
+                _ => Ok(None),
+            },
+            Kind::Stream(stream) => loop {
+                if stream.ended {
+                    return Ok(None);
+                }
+                match stream.chunks.block_receive() {
+                    Ok(chunk) if chunk.is_empty() => continue,
+                    Ok(chunk) => {
+                        stream.hint.consume(chunk.len() as u64);
+                        return Ok(Some(chunk));
+                    }
+                    Err(ChannelError::Closed) => {
+                        stream.ended = true;
+                        return match &stream.finished {
+                            Some(finished) if !finished.load(Ordering::SeqCst) => {
+                                Err(BodyError::Failed(String::from(
+                                    "body sender dropped before finishing",
+                                )))
+                            }
+                            _ => Ok(None),
+                        };
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            },
+            Kind::Reader(stream) => {
+                if stream.remaining == 0 {
+                    return Ok(None);
+                }
+                let len = stream.remaining.min(READ_CHUNK as u64) as usize;
+                let mut chunk = vec![0; len];
+                let read = loop {
+                    match stream.reader.read(&mut chunk) {
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                        read => break read.map_err(|err| BodyError::Failed(err.to_string()))?,
+                    }
+                };
+                if read == 0 {
+                    return Err(BodyError::Failed(String::from(
+                        "reader ended before the body's length",
+                    )));
+                }
+                chunk.truncate(read);
+                stream.remaining -= read as u64;
+                Ok(Some(Bytes::from(chunk)))
+            }
+        }
+    }
+
+    /// trailers returns the trailers the stream ended with, only known
+    /// once the body was read to its end.
+    pub fn trailers(&self) -> Option<HeaderMap> {
+        match &self.kind {
+            Kind::Stream(stream) if stream.ended => stream.trailers.lock().unwrap().clone(),
+            _ => None,
+        }
+    }
+
+    /// into_bytes reads the whole body into memory.
+    pub fn into_bytes(self) -> BodyResult<Bytes> {
+        self.into_bytes_limited(usize::MAX)
+    }
+
+    /// into_bytes_limited reads the whole body into memory, failing once it
+    /// grows past `limit` bytes.
+    pub fn into_bytes_limited(mut self, limit: usize) -> BodyResult<Bytes> {
+        if self.size_hint().lower() > limit as u64 {
+            return Err(BodyError::TooLarge(limit));
+        }
+        if let Kind::Full(bytes) = self.kind {
+            return match bytes.len() > limit {
+                true => Err(BodyError::TooLarge(limit)),
+                false => Ok(bytes),
+            };
+        }
+
+        let mut buffer = BytesMut::new();
+        while let Some(chunk) = self.next_chunk()? {
+            if buffer.len() + chunk.len() > limit {
+                return Err(BodyError::TooLarge(limit));
+            }
+            buffer.extend_from_slice(&chunk);
+        }
+        Ok(buffer.freeze())
+    }
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            Kind::Empty => f.write_str("Body::Empty"),
+            Kind::Full(bytes) => f.debug_tuple("Body::Full").field(bytes).finish(),
+            Kind::Stream(stream) => f
+                .debug_struct("Body::Stream")
+                .field("hint", &stream.hint)
+                .field("ended", &stream.ended)
+                .finish(),
+            Kind::Reader(reader) => f
+                .debug_struct("Body::Reader")
+                .field("remaining", &reader.remaining)
+                .finish(),
+        }
+    }
+}
+
+impl Iterator for Body {
+    type Item = BodyResult<Bytes>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_chunk().transpose()
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(bytes: Bytes) -> Self {
+        Self::full(bytes)
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::full(bytes)
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Self::full(text)
+    }
+}
+
+impl From<&'static str> for Body {
+    fn from(text: &'static str) -> Self {
+        Self::full(text)
+    }
+}
+
+impl From<&'static [u8]> for Body {
+    fn from(bytes: &'static [u8]) -> Self {
+        Self::full(bytes)
+    }
+}
+
+/// BodySender writes the chunks of a body made with [`Body::channel`],
+/// ending it when finished, dropping it unfinished fails the body so a
+/// truncated stream is never mistaken for a complete one.
+pub struct BodySender {
+    chunks: SendChannel<Bytes>,
+    trailers: Trailers,
+    finished: Arc<AtomicBool>,
+}
+
+impl BodySender {
+    /// send writes the chunk, waiting while the body's buffer is full and
+    /// failing once the body was dropped.
+    pub fn send(&mut self, chunk: impl Into<Bytes>) -> BodyResult<()> {
+        Ok(self.chunks.block_send(chunk.into())?)
+    }
+
+    /// try_send writes the chunk, failing instead of waiting if the body's
+    /// buffer is full.
+    pub fn try_send(&mut self, chunk: impl Into<Bytes>) -> BodyResult<()> {
+        Ok(self.chunks.try_send(chunk.into())?)
+    }
+
+    /// is_closed returns true once the body was dropped.
+    pub fn is_closed(&self) -> bool {
+        self.chunks.is_closed()
+    }
+
+    /// finish_with_trailers ends the body with the trailers.
+    pub fn finish_with_trailers(self, trailers: HeaderMap) {
+        *self.trailers.lock().unwrap() = Some(trailers);
+        self.finish();
+    }
+
+    /// finish ends the body.
+    pub fn finish(mut self) {
+        self.finished.store(true, Ordering::SeqCst);
+        _ = self.chunks.close();
+    }
+}
+
+impl Request<Body> {
+    /// collect_body reads the streaming body into memory, for handlers
+    /// and extractors taking whole bodies.
+    pub fn collect_body(self, limit: usize) -> BodyResult<Request<Vec<u8>>> {
+        let body = match self.body {
+            Some(body) => Some(Vec::from(body.into_bytes_limited(limit)?)),
+            None => None,
+        };
+        Ok(Request::from(body, self.head))
+    }
+}
+
+impl Response<Body> {
+    /// collect_body reads the streaming body into memory.
+    pub fn collect_body(self, limit: usize) -> BodyResult<Response<Vec<u8>>> {
+        let body = match self.body {
+            Some(body) => Some(Vec::from(body.into_bytes_limited(limit)?)),
+            None => None,
+        };
+        Ok(Response::from(body, self.head))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, thread};
+
+    use http::{HeaderMap, HeaderValue, Method, Uri, Version};
+
+    use crate::{
+        body::{Body, BodyError, SizeHint},
+        Request, RequestHead,
+    };
+
+    #[test]
+    fn streams_chunks_with_backpressure_and_trailers() {
+        let (mut sender, body) = Body::channel(1);
+        let mut body = body.with_size_hint(SizeHint::exact(11));
+
+        sender.try_send("hello").unwrap();
+        assert!(sender.try_send(" ").is_err());
+
+        let writer = thread::spawn(move || {
+            sender.send(" ").unwrap();
+            sender.send("world").unwrap();
+
+            let mut trailers = HeaderMap::new();
+            trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+            sender.finish_with_trailers(trailers);
+        });
+
+        assert_eq!(body.next_chunk().unwrap().as_deref(), Some(&b"hello"[..]));
+        assert_eq!(body.size_hint().exact_len(), Some(6));
+        assert_eq!(body.trailers(), None);
+
+        let rest: Vec<_> = body.by_ref().map(|chunk| chunk.unwrap()).collect();
+        assert_eq!(rest.concat(), b" world");
+        assert!(body.is_end_stream());
+        assert_eq!(body.trailers().unwrap().get("x-checksum").unwrap(), "abc");
+
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn channels_of_no_capacity_buffer_one_chunk() {
+        let (mut sender, mut body) = Body::channel(0);
+        sender.try_send("a").unwrap();
+        assert!(sender.try_send("b").is_err());
+        sender.finish();
+
+        assert_eq!(body.next_chunk().unwrap().as_deref(), Some(&b"a"[..]));
+        assert_eq!(body.next_chunk().unwrap(), None);
+    }
+
+    #[test]
+    fn fails_bodies_whose_sender_was_dropped_unfinished() {
+        let (mut sender, mut body) = Body::channel(2);
+        sender.send("partial").unwrap();
+        drop(sender);
+
+        assert_eq!(body.next_chunk().unwrap().as_deref(), Some(&b"partial"[..]));
+        assert!(matches!(body.next_chunk(), Err(BodyError::Failed(_))));
+        assert_eq!(body.next_chunk().unwrap(), None);
+    }
+
+    #[test]
+    fn reads_bodies_of_a_known_length_from_readers() {
+        let mut body = Body::reader(io::Cursor::new(vec![7; 40 * 1024]), 20 * 1024);
+        assert_eq!(body.size_hint().exact_len(), Some(20 * 1024));
+
+        let first = body.next_chunk().unwrap().unwrap();
+        assert_eq!(first.len(), 16 * 1024);
+        assert_eq!(body.size_hint().exact_len(), Some(4 * 1024));
+        assert_eq!(body.into_bytes().unwrap().len(), 4 * 1024);
+
+        let short = Body::reader(io::Cursor::new(vec![7; 4]), 8);
+        assert!(matches!(short.into_bytes(), Err(BodyError::Failed(_))));
+    }
+
+    #[test]
+    fn collects_bodies_within_limits() {
+        let (mut sender, body) = Body::channel(4);
+        sender.send("abc").unwrap();
+        sender.send("def").unwrap();
+        sender.finish();
+        assert_eq!(body.into_bytes_limited(4), Err(BodyError::TooLarge(4)));
+
+        let request = Request::new(
+            Vec::from("{\"done\":true}"),
+            RequestHead::new(
+                Method::POST,
+                Version::HTTP_11,
+                Uri::from_static("/todos"),
+                String::from("/todos"),
+            ),
+        )
+        .map(|body| body.map(Body::from));
+        assert_eq!(
+            request.body().as_ref().unwrap().size_hint().exact_len(),
+            Some(13)
+        );
+
+        let request = request.collect_body(1024).unwrap();
+        assert_eq!(request.body().as_deref(), Some(&b"{\"done\":true}"[..]));
+    }
+}
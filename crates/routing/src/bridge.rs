@@ -0,0 +1,377 @@
+// Module implementing the adapter between routing requests and a domain's
+// NamedRequests, keeping HTTP out of the domain.
+
+use std::{
+    fmt::Debug,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ewe_channels::mspc::ChannelError;
+use ewe_domain::domains::{DomainFailure, DomainShell, NamedEvent, NamedRequest};
+use http::StatusCode;
+
+use crate::{
+    extract::{ExtractResult, Rejection},
+    handler::Handler,
+    middleware::{generate_request_id, status_response, RequestId},
+    Request, Response,
+};
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum BridgeError<Err: Debug> {
+    #[error(transparent)]
+    Rejected(#[from] Rejection),
+
+    #[error("domain is unavailable: {0}")]
+    Unavailable(String),
+
+    #[error("domain did not respond in time")]
+    TimedOut,
+
+    #[error(transparent)]
+    Failed(DomainFailure<Err>),
+}
+
+pub type BridgeResult<T, Err> = std::result::Result<T, BridgeError<Err>>;
+
+type MapFn<T, R> = Box<dyn Fn(Request<T>) -> ExtractResult<R> + Send + Sync>;
+type RenderFn<E, U> = Box<dyn Fn(Vec<NamedEvent<E>>) -> Response<U> + Send + Sync>;
+type StatusFn<Err> = Box<dyn Fn(&Err) -> StatusCode + Send + Sync>;
+
+/// DomainBridge is a [`Handler`] turning requests into the domain's
+/// requests with a mapping function, performing them via
+/// [`DomainShell::do_request_with_timeout`] and rendering the events of the domain's
+/// response back into a response.
+///
+/// The domain is driven by whoever runs its
+/// [`ewe_domain::core::CoreExecutor`], the bridge waits up to its timeout
+/// for the response to finish. The domain's requests get a unique id,
+/// prefixed with the request's [`RequestId`] when it has one.
+///
+/// # Example:
+///
+/// ```ignore
+/// let bridge = DomainBridge::new(
+///     shell,
+///     |req: Request<Vec<u8>>| {
+///         let Path(id) = req.extract::<Path<u64>>()?;
+///         Ok(TodoRequests::Show(id))
+///     },
+///     |events| render_todo(events),
+/// )
+/// .with_timeout(Duration::from_secs(5));
+///
+/// router.get("/todos/:id", bridge.boxed())?;
+/// ```
+pub struct DomainBridge<S: DomainShell, T, U> {
+    shell: Mutex<S>,
+    map: MapFn<T, S::Requests>,
+    render: RenderFn<S::Events, U>,
+    error_status: StatusFn<S::Error>,
+    timeout: Duration,
+}
+
+impl<S: DomainShell, T, U> DomainBridge<S, T, U> {
+    pub fn new(
+        shell: S,
+        map: impl Fn(Request<T>) -> ExtractResult<S::Requests> + Send + Sync + 'static,
+        render: impl Fn(Vec<NamedEvent<S::Events>>) -> Response<U> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            shell: Mutex::new(shell),
+            map: Box::new(map),
+            render: Box::new(render),
+            error_status: Box::new(|_| StatusCode::INTERNAL_SERVER_ERROR),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// with_timeout sets how long to wait for the domain to finish its
+    /// response, answering with `504 Gateway Timeout` after.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// with_error_status sets the status to answer with when the domain
+    /// fails the request with its error, defaults to `500 Internal Server
+    /// Error`.
+    pub fn with_error_status(
+        mut self,
+        error_status: impl Fn(&S::Error) -> StatusCode + Send + Sync + 'static,
+    ) -> Self {
+        self.error_status = Box::new(error_status);
+        self
+    }
+
+    /// request performs the request against the domain, returning the
+    /// events of its response.
+    pub fn request(&self, req: Request<T>) -> BridgeResult<Vec<NamedEvent<S::Events>>, S::Error> {
+        // request ids come from clients and may repeat, the domain's id
+        // only carries them along.
+        let id = match req.extensions().get::<RequestId>() {
+            Some(RequestId(id)) => format!("{}/{}", id, generate_request_id()),
+            None => generate_request_id(),
+        };
+        let request = NamedRequest::new(&id, (self.map)(req)?);
+
+        let mut shell = self.shell.lock().unwrap().clone();
+        let mut receiver = shell
+            .do_request_with_timeout(request, self.timeout)
+            .map_err(|err| BridgeError::Unavailable(err.to_string()))?;
+
+        // the domain fails the request once its deadline passes, the
+        // bridge only stops waiting on its own when the domain is not run.
+        let deadline = Instant::now() + self.timeout;
+        let mut events = Vec::new();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match receiver.block_receive_timeout(remaining) {
+                Ok(Ok(event)) => events.push(event),
+                Ok(Err(failure)) => return Err(BridgeError::Failed(failure)),
+                Err(ChannelError::Closed) => return Ok(events),
+                Err(_) => return Err(BridgeError::TimedOut),
+            }
+        }
+    }
+
+    /// status returns the response status matching the error.
+    pub fn status(&self, err: &BridgeError<S::Error>) -> StatusCode {
+        match err {
+            BridgeError::Rejected(rejection) => rejection.status(),
+            BridgeError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            BridgeError::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+            BridgeError::Failed(failure) => match failure {
+                DomainFailure::Failed(err) => (self.error_status)(err),
+                DomainFailure::Panicked(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                DomainFailure::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+                DomainFailure::Stopped | DomainFailure::Rejected(_) => {
+                    StatusCode::SERVICE_UNAVAILABLE
+                }
+            },
+        }
+    }
+}
+
+impl<S, T, U> Handler<T, U> for DomainBridge<S, T, U>
+where
+    S: DomainShell,
+    T: 'static,
+    U: From<String> + 'static,
+{
+    fn call(&self, req: Request<T>) -> Response<U> {
+        let err = match self.request(req) {
+            Ok(events) => return (self.render)(events),
+            Err(err) => err,
+        };
+
+        // server errors are described by their status alone, keeping the
+        // domain's internals out of the response.
+        let status = self.status(&err);
+        let message = match status.is_server_error() {
+            true => String::from(status.canonical_reason().unwrap_or_default()),
+            false => err.to_string(),
+        };
+
+        let mut response = status_response(status);
+        *response.body_mut() = Some(U::from(message));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use ewe_channels::mspc;
+    use ewe_domain::{
+        app,
+        domains::{self, MasterShell, NamedEvent},
+        servicer,
+    };
+    use http::{Method, StatusCode, Uri, Version};
+
+    use crate::{
+        bridge::DomainBridge,
+        extract::{Path, Query},
+        handler::{Handler, HandlerExt},
+        middleware::{status_response, RequestId},
+        router::Router,
+        Request, RequestHead, Response,
+    };
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TodoEvents {
+        Found(u64),
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TodoRequests {
+        Show(u64),
+        Stall,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum TodoErrors {
+        Missing,
+    }
+
+    #[derive(Default, Clone)]
+    struct Platform;
+
+    #[derive(Default, Clone)]
+    struct TodoApp {
+        stalled: Arc<Mutex<Vec<mspc::SendChannel<NamedEvent<TodoEvents>>>>>,
+    }
+
+    impl domains::Domain for TodoApp {
+        type Events = TodoEvents;
+        type Requests = TodoRequests;
+        type Platform = Platform;
+        type Error = TodoErrors;
+
+        fn handle_request(
+            &self,
+            req: domains::NamedRequest<Self::Requests>,
+            mut chan: mspc::SendChannel<domains::NamedEvent<Self::Events>>,
+            mut shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+            match req.item() {
+                TodoRequests::Show(0) => {
+                    _ = shell.fail(req.id(), TodoErrors::Missing);
+                }
+                TodoRequests::Show(id) => {
+                    _ = chan.try_send(req.to_one(TodoEvents::Found(id)));
+                    _ = chan.close();
+                }
+                TodoRequests::Stall => self.stalled.lock().unwrap().push(chan),
+            }
+        }
+
+        fn handle_event(
+            &self,
+            _event: domains::NamedEvent<Self::Events>,
+            _shell: impl MasterShell<
+                Events = Self::Events,
+                Requests = Self::Requests,
+                Platform = Self::Platform,
+                Error = Self::Error,
+            >,
+        ) {
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Options {
+        stall: Option<bool>,
+    }
+
+    fn request(path: &'static str) -> Request<Vec<u8>> {
+        Request::from_head(RequestHead::new(
+            Method::GET,
+            Version::HTTP_11,
+            Uri::from_static(path),
+            String::from(path),
+        ))
+    }
+
+    fn render(events: Vec<NamedEvent<TodoEvents>>) -> Response<Vec<u8>> {
+        let mut response = status_response(StatusCode::OK);
+        let found: Vec<String> = events
+            .iter()
+            .flat_map(|event| event.items())
+            .map(|TodoEvents::Found(id)| format!("todo {}", id))
+            .collect();
+        *response.body_mut() = Some(found.join(",").into_bytes());
+        response
+    }
+
+    #[test]
+    fn bridges_requests_to_the_domain_and_back() {
+        let (mut executor, server) = app::create::<TodoApp>();
+        let introspector = server.introspector();
+        let shell = servicer::create_shell(server);
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let driver_stopped = stopped.clone();
+        let driver = thread::spawn(move || {
+            while !driver_stopped.load(Ordering::SeqCst) {
+                executor.run_all();
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+
+        let ids = Arc::new(Mutex::new(Vec::new()));
+        let rendered_ids = ids.clone();
+        let bridge = DomainBridge::new(
+            shell,
+            |req: Request<Vec<u8>>| {
+                let Query(options) = req.extract::<Query<Options>>()?;
+                if options.stall == Some(true) {
+                    return Ok(TodoRequests::Stall);
+                }
+                let Path(id) = req.extract::<Path<u64>>()?;
+                Ok(TodoRequests::Show(id))
+            },
+            move |events: Vec<NamedEvent<TodoEvents>>| {
+                let mut ids = rendered_ids.lock().unwrap();
+                ids.extend(events.iter().map(|event| event.id().0));
+                render(events)
+            },
+        )
+        .with_timeout(Duration::from_millis(100))
+        .with_error_status(|err| match err {
+            TodoErrors::Missing => StatusCode::NOT_FOUND,
+        });
+
+        let mut router = Router::new();
+        router.get("/todos/:id", bridge.boxed()).unwrap();
+
+        let response: Response<Vec<u8>> = router.call(request("/todos/7"));
+        assert_eq!(*response.head().status(), StatusCode::OK);
+        assert_eq!(response.body().as_deref(), Some(&b"todo 7"[..]));
+
+        let response: Response<Vec<u8>> = router.call(request("/todos/0"));
+        assert_eq!(*response.head().status(), StatusCode::NOT_FOUND);
+
+        let response: Response<Vec<u8>> = router.call(request("/todos/seven"));
+        assert_eq!(*response.head().status(), StatusCode::NOT_FOUND);
+
+        let response: Response<Vec<u8>> = router.call(request("/todos/1?stall=true"));
+        assert_eq!(*response.head().status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(response.body().as_deref(), Some(&b"Gateway Timeout"[..]));
+
+        // the domain forgets the request it failed with its deadline.
+        thread::sleep(Duration::from_millis(20));
+        assert!(introspector.snapshot().pending_responses.is_empty());
+
+        // clients repeating a request id still get their own responses.
+        for (path, body) in [("/todos/3", "todo 3"), ("/todos/4", "todo 4")] {
+            let mut req = request(path);
+            req.head
+                .extensions
+                .insert(RequestId(String::from("repeated")));
+            let response: Response<Vec<u8>> = router.call(req);
+            assert_eq!(response.body().as_deref(), Some(body.as_bytes()));
+        }
+        let ids = ids.lock().unwrap();
+        assert!(ids[1].starts_with("repeated/") && ids[2].starts_with("repeated/"));
+        assert_ne!(ids[1], ids[2]);
+
+        stopped.store(true, Ordering::SeqCst);
+        driver.join().unwrap();
+    }
+}
@@ -0,0 +1,489 @@
+// Module implementing cookies, reading them from requests, setting them on
+// responses and protecting their values by signing or encrypting them.
+
+use std::{
+    fmt,
+    time::{Duration, SystemTime},
+};
+
+use aes_gcm::{aead::Aead, Aes256Gcm, KeyInit, Nonce};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use http::{header, HeaderMap, HeaderValue};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use sha2::Sha256;
+
+use crate::{
+    extract::{ExtractResult, FromRequest},
+    Request, Response,
+};
+
+/// COOKIE_OCTETS are the characters encoded in cookie names and values,
+/// the ones RFC 6265 does not allow in values and separators in names.
+const COOKIE_OCTETS: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'%')
+    .add(b',')
+    .add(b';')
+    .add(b'=')
+    .add(b'\\');
+
+/// MAC_LEN is the length of a base64 encoded HMAC-SHA256 signature.
+const MAC_LEN: usize = 43;
+const NONCE_LEN: usize = 12;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CookieError {
+    #[error("cookie keys require at least 32 bytes, got {0}")]
+    KeyTooShort(usize),
+
+    #[error("failed to generate random bytes: {0}")]
+    Random(String),
+
+    #[error("failed to encrypt cookie {0:?}")]
+    Encryption(String),
+}
+
+pub type CookieResult<T> = std::result::Result<T, CookieError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        }
+    }
+}
+
+/// Cookie is a cookie set on a response with its attributes, its name
+/// and value are percent-encoded when written so any text can be stored.
+///
+/// # Example:
+///
+/// ```ignore
+/// let cookie = Cookie::new("theme", "dark")
+///     .with_path("/")
+///     .with_max_age(Duration::from_secs(3600))
+///     .with_same_site(SameSite::Lax);
+///
+/// let response = Response::text("ok").with_cookie(&cookie);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// removal returns the cookie telling clients to drop the named one,
+    /// it must have the same path and domain as the cookie it removes.
+    pub fn removal(name: impl Into<String>) -> Self {
+        Self::new(name, "")
+            .with_max_age(Duration::ZERO)
+            .with_expires(SystemTime::UNIX_EPOCH)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn with_expires(mut self, expires: SystemTime) -> Self {
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn with_http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn with_same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+}
+
+/// Formats the cookie as the value of a `Set-Cookie` header.
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}={}",
+            utf8_percent_encode(&self.name, COOKIE_OCTETS),
+            utf8_percent_encode(&self.value, COOKIE_OCTETS)
+        )?;
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", httpdate::fmt_http_date(expires))?;
+        }
+        if self.secure {
+            f.write_str("; Secure")?;
+        }
+        if self.http_only {
+            f.write_str("; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site.as_str())?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Response<T> {
+    /// with_cookie adds a `Set-Cookie` header for the cookie, attributes
+    /// which are not valid header text are left out by the client.
+    pub fn with_cookie(mut self, cookie: &Cookie) -> Self {
+        self.add_cookie(cookie);
+        self
+    }
+
+    pub fn add_cookie(&mut self, cookie: &Cookie) {
+        match HeaderValue::from_str(&cookie.to_string()) {
+            Ok(value) => {
+                self.head.headers.append(header::SET_COOKIE, value);
+            }
+            Err(_) => tracing::warn!("Skipping cookie {:?} with invalid attributes", cookie.name),
+        }
+    }
+}
+
+/// CookieJar holds the cookies a request was sent with, from its `Cookie`
+/// headers, decoded from their percent-encoding.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieJar {
+    cookies: Vec<(String, String)>,
+}
+
+impl CookieJar {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let decode = |text: &str| percent_decode_str(text).decode_utf8_lossy().into_owned();
+
+        let cookies = headers
+            .get_all(header::COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+                Some((decode(name.trim()), decode(value)))
+            })
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+        Self { cookies }
+    }
+
+    /// get returns the value of the first cookie with the name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.cookies
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// get_signed returns the value of the cookie signed with the key,
+    /// `None` if it is missing or was tampered with.
+    pub fn get_signed(&self, name: &str, key: &Key) -> Option<String> {
+        key.verify(name, self.get(name)?)
+    }
+
+    /// get_private returns the value of the cookie encrypted with the key,
+    /// `None` if it is missing or was tampered with.
+    pub fn get_private(&self, name: &str, key: &Key) -> Option<String> {
+        key.decrypt(name, self.get(name)?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.cookies
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+}
+
+impl<B> FromRequest<B> for CookieJar {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        Ok(Self::from_headers(req.headers()))
+    }
+}
+
+/// Key is the secret signing and encrypting cookies, both keys are derived
+/// from one master key.
+///
+/// Signed cookies are readable by clients but cannot be altered, the
+/// cookie's name is part of the signature so values cannot be moved
+/// between cookies either. Private cookies are encrypted with AES-256-GCM
+/// and can neither be read nor altered.
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+}
+
+impl Key {
+    /// new derives the keys from the master key, which must have at least
+    /// 32 bytes of randomness.
+    pub fn new(master: &[u8]) -> CookieResult<Self> {
+        if master.len() < 32 {
+            return Err(CookieError::KeyTooShort(master.len()));
+        }
+        Ok(Self {
+            signing: derive(master, b"ewe.cookies.signing"),
+            encryption: derive(master, b"ewe.cookies.encryption"),
+        })
+    }
+
+    /// generate returns a random key, cookies protected with it do not
+    /// outlive the process.
+    pub fn generate() -> CookieResult<Self> {
+        Self::new(&random_bytes::<64>()?)
+    }
+
+    /// sign returns the cookie with its value signed.
+    pub fn sign(&self, cookie: Cookie) -> Cookie {
+        let mac = self.mac(&cookie.name, &cookie.value);
+        Cookie {
+            value: format!("{}{}", mac, cookie.value),
+            ..cookie
+        }
+    }
+
+    /// verify returns the value of the signed cookie if its signature
+    /// holds.
+    pub fn verify(&self, name: &str, signed: &str) -> Option<String> {
+        if signed.len() < MAC_LEN || !signed.is_char_boundary(MAC_LEN) {
+            return None;
+        }
+        let (mac, value) = signed.split_at(MAC_LEN);
+        let mac = URL_SAFE_NO_PAD.decode(mac).ok()?;
+
+        let mut verifier = <HmacSha256 as Mac>::new_from_slice(&self.signing).ok()?;
+        verifier.update(name.as_bytes());
+        verifier.update(b"=");
+        verifier.update(value.as_bytes());
+        verifier.verify_slice(&mac).ok()?;
+        Some(value.to_string())
+    }
+
+    /// encrypt returns the cookie with its value encrypted.
+    pub fn encrypt(&self, cookie: Cookie) -> CookieResult<Cookie> {
+        let nonce = random_bytes::<NONCE_LEN>()?;
+        let cipher = Aes256Gcm::new((&self.encryption).into());
+        let payload = aes_gcm::aead::Payload {
+            msg: cookie.value.as_bytes(),
+            aad: cookie.name.as_bytes(),
+        };
+        let sealed = cipher
+            .encrypt(Nonce::from_slice(&nonce), payload)
+            .map_err(|_| CookieError::Encryption(cookie.name.clone()))?;
+
+        let mut value = nonce.to_vec();
+        value.extend_from_slice(&sealed);
+        Ok(Cookie {
+            value: URL_SAFE_NO_PAD.encode(value),
+            ..cookie
+        })
+    }
+
+    /// decrypt returns the value of the encrypted cookie if it was
+    /// encrypted with the key for a cookie of the name.
+    pub fn decrypt(&self, name: &str, encrypted: &str) -> Option<String> {
+        let sealed = URL_SAFE_NO_PAD.decode(encrypted).ok()?;
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, sealed) = sealed.split_at(NONCE_LEN);
+
+        let cipher = Aes256Gcm::new((&self.encryption).into());
+        let payload = aes_gcm::aead::Payload {
+            msg: sealed,
+            aad: name.as_bytes(),
+        };
+        let value = cipher.decrypt(Nonce::from_slice(nonce), payload).ok()?;
+        String::from_utf8(value).ok()
+    }
+
+    fn mac(&self, name: &str, value: &str) -> String {
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(&self.signing)
+            .expect("HMAC takes keys of any size");
+        mac.update(name.as_bytes());
+        mac.update(b"=");
+        mac.update(value.as_bytes());
+        URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Key(..)")
+    }
+}
+
+fn derive(master: &[u8], label: &[u8]) -> [u8; 32] {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(master).expect("HMAC takes keys of any size");
+    mac.update(label);
+    mac.finalize().into_bytes().into()
+}
+
+pub(crate) fn random_bytes<const N: usize>() -> CookieResult<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|err| CookieError::Random(err.to_string()))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::{header, HeaderMap, HeaderValue, StatusCode};
+
+    use crate::{
+        cookies::{Cookie, CookieError, CookieJar, Key, SameSite},
+        Response,
+    };
+
+    fn jar(cookies: &[&Cookie]) -> CookieJar {
+        let mut headers = HeaderMap::new();
+        let pairs: Vec<String> = cookies
+            .iter()
+            .map(|cookie| cookie.to_string().split(';').next().unwrap().to_string())
+            .collect();
+        headers.insert(
+            header::COOKIE,
+            HeaderValue::from_str(&pairs.join("; ")).unwrap(),
+        );
+        CookieJar::from_headers(&headers)
+    }
+
+    #[test]
+    fn writes_and_reads_cookies() {
+        let cookie = Cookie::new("theme", "dark; blue")
+            .with_path("/")
+            .with_max_age(Duration::from_secs(60))
+            .with_http_only(true)
+            .with_same_site(SameSite::Lax);
+        assert_eq!(
+            cookie.to_string(),
+            "theme=dark%3B%20blue; Path=/; Max-Age=60; HttpOnly; SameSite=Lax"
+        );
+
+        let response: Response<Vec<u8>> = Response::text("ok")
+            .with_cookie(&cookie)
+            .with_cookie(&Cookie::removal("old"));
+        let set: Vec<_> = response
+            .headers()
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .collect();
+        assert_eq!(set.len(), 2);
+        assert!(set[1]
+            .to_str()
+            .unwrap()
+            .starts_with("old=; Max-Age=0; Expires=Thu, 01 Jan 1970"));
+        assert_eq!(*response.head().status(), StatusCode::OK);
+
+        let jar = jar(&[&cookie, &Cookie::new("lang", "en")]);
+        assert_eq!(jar.get("theme"), Some("dark; blue"));
+        assert_eq!(jar.get("lang"), Some("en"));
+        assert_eq!(jar.len(), 2);
+    }
+
+    #[test]
+    fn signs_and_encrypts_cookies() {
+        assert_eq!(Key::new(b"short").err(), Some(CookieError::KeyTooShort(5)));
+        let key = Key::new(&[7u8; 32]).unwrap();
+        let other = Key::generate().unwrap();
+
+        let signed = key.sign(Cookie::new("user", "42"));
+        let encrypted = key.encrypt(Cookie::new("token", "s3cr3t")).unwrap();
+        assert!(!encrypted.value().contains("s3cr3t"));
+
+        let jar = jar(&[&signed, &encrypted]);
+        assert_eq!(jar.get_signed("user", &key).as_deref(), Some("42"));
+        assert_eq!(jar.get_private("token", &key).as_deref(), Some("s3cr3t"));
+        assert_eq!(jar.get_signed("user", &other), None);
+        assert_eq!(jar.get_private("token", &other), None);
+
+        // values are bound to the cookie they were protected for.
+        assert_eq!(key.verify("admin", signed.value()), None);
+        assert_eq!(key.decrypt("session", encrypted.value()), None);
+
+        let tampered = format!("{}43", &signed.value()[..signed.value().len() - 2]);
+        assert_eq!(key.verify("user", &tampered), None);
+    }
+}
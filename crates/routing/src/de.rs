@@ -0,0 +1,323 @@
+// Module implementing a serde deserializer over string keys and values as
+// found in path parameters, query strings, forms and headers.
+
+use std::{collections::HashMap, fmt};
+
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, SeqDeserializer},
+        DeserializeOwned, IntoDeserializer, Visitor,
+    },
+    forward_to_deserialize_any,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DeError(String);
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        DeError(msg.to_string())
+    }
+}
+
+/// Entries are the keys in the order they were first seen, each with all
+/// the values given for it.
+#[derive(Debug, Default)]
+pub(crate) struct Entries {
+    entries: Vec<(String, Vec<String>)>,
+    positions: HashMap<String, usize>,
+}
+
+impl Entries {
+    pub(crate) fn push(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        let key = key.into();
+        match self.positions.get(&key) {
+            Some(&position) => self.entries[position].1.push(value.into()),
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, vec![value.into()]));
+            }
+        }
+    }
+
+    /// deserialize deserializes the entries as a struct or map of their
+    /// keys, a tuple of their values or a single value if there is only
+    /// one entry.
+    pub(crate) fn deserialize<T: DeserializeOwned>(self) -> Result<T, DeError> {
+        T::deserialize(self)
+    }
+
+    fn single(self) -> Result<Values, DeError> {
+        let count = self.entries.len();
+        match self.entries.into_iter().next() {
+            Some((_, values)) if count == 1 => Ok(Values(values)),
+            _ => Err(DeError(format!("expected a single value, found {}", count))),
+        }
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Entries {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut entries = Entries::default();
+        for (key, value) in iter {
+            entries.push(key, value);
+        }
+        entries
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Entries {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut map = MapDeserializer::new(
+            self.entries
+                .into_iter()
+                .map(|(key, values)| (key, Values(values))),
+        );
+        let value = visitor.visit_map(&mut map)?;
+        map.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut seq =
+            SeqDeserializer::new(self.entries.into_iter().map(|(_, values)| Values(values)));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_unit deserialize_identifier
+    }
+}
+
+/// Values are the values of a single key, all but sequences use the first.
+struct Values(Vec<String>);
+
+impl Values {
+    fn first(self) -> Result<String, DeError> {
+        self.0
+            .into_iter()
+            .next()
+            .ok_or_else(|| DeError(String::from("expected a value")))
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Values {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+macro_rules! parse_first {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+                let value = self.first()?;
+                match value.parse() {
+                    Ok(parsed) => visitor.$visit(parsed),
+                    Err(err) => Err(DeError(format!("cannot parse {:?}: {}", value, err))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Values {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_string(self.first()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let mut seq = SeqDeserializer::new(self.0.into_iter().map(|value| Values(vec![value])));
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_enum(self.first()?.into_deserializer())
+    }
+
+    parse_first! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit_struct tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::de::Entries;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Order {
+        Asc,
+        Desc,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Listing {
+        page: u32,
+        order: Order,
+        tag: Vec<String>,
+        search: Option<String>,
+    }
+
+    #[test]
+    fn deserializes_structs_tuples_and_single_values() {
+        let entries: Entries = [("page", "2"), ("order", "desc"), ("tag", "a"), ("tag", "b")]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            entries.deserialize::<Listing>().unwrap(),
+            Listing {
+                page: 2,
+                order: Order::Desc,
+                tag: vec!["a".into(), "b".into()],
+                search: None,
+            }
+        );
+
+        let entries: Entries = [("id", "7"), ("slug", "hello")].into_iter().collect();
+        assert_eq!(
+            entries.deserialize::<(u64, String)>().unwrap(),
+            (7, String::from("hello"))
+        );
+
+        let entries: Entries = [("id", "7")].into_iter().collect();
+        assert_eq!(entries.deserialize::<u64>().unwrap(), 7);
+
+        let entries: Entries = [("id", "seven")].into_iter().collect();
+        assert!(entries.deserialize::<u64>().is_err());
+    }
+}
@@ -0,0 +1,457 @@
+// Module implementing typed extractors pulling values out of a Request.
+
+use std::ops::{Deref, DerefMut};
+
+use http::{header, HeaderMap, HeaderValue, StatusCode};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    de::Entries,
+    forms::{FormError, FormLimits},
+    router::Params,
+    Extensions, Request, Response, ResponseHead, Version,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Rejection {
+    #[error("request was not routed, it has no path parameters")]
+    MissingParams,
+
+    #[error("invalid path parameters: {0}")]
+    InvalidPath(String),
+
+    #[error("invalid query string: {0}")]
+    InvalidQuery(String),
+
+    #[error("invalid headers: {0}")]
+    InvalidHeaders(String),
+
+    #[error("expected a {0} body")]
+    UnsupportedMediaType(&'static str),
+
+    #[error("request has no body")]
+    MissingBody,
+
+    #[error("malformed json body: {0}")]
+    MalformedJson(String),
+
+    #[error("invalid json body: {0}")]
+    InvalidJson(String),
+
+    #[error("invalid form body: {0}")]
+    InvalidForm(String),
+
+    #[error("malformed form body: {0}")]
+    MalformedForm(String),
+
+    #[error("request body is too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("failed to store form body: {0}")]
+    FormStorage(String),
+
+    #[error("request has no {0} extension")]
+    MissingExtension(&'static str),
+}
+
+impl Rejection {
+    /// status returns the response status matching the rejection.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Rejection::InvalidPath(_) => StatusCode::NOT_FOUND,
+            Rejection::InvalidQuery(_)
+            | Rejection::InvalidHeaders(_)
+            | Rejection::MissingBody
+            | Rejection::MalformedJson(_)
+            | Rejection::MalformedForm(_) => StatusCode::BAD_REQUEST,
+            Rejection::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Rejection::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Rejection::InvalidJson(_) | Rejection::InvalidForm(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Rejection::MissingParams
+            | Rejection::MissingExtension(_)
+            | Rejection::FormStorage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// into_response renders the rejection as a plain text response.
+    pub fn into_response(self) -> Response<String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+
+        let head = ResponseHead::new(self.status(), Version::HTTP_11, headers, Extensions::new());
+        Response::new(self.to_string(), head)
+    }
+}
+
+pub type ExtractResult<T> = std::result::Result<T, Rejection>;
+
+/// FromRequest is implemented by the types which can be pulled out of a
+/// [`Request`] with a body of type `B`, see [`Request::extract`].
+pub trait FromRequest<B>: Sized {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self>;
+}
+
+impl<B> Request<B> {
+    /// extract pulls the giving extractor, or a tuple of them, out of the
+    /// request.
+    ///
+    /// # Example:
+    ///
+    /// ```ignore
+    /// let (Path(id), Query(page)) = req.extract::<(Path<u64>, Query<Page>)>()?;
+    /// ```
+    pub fn extract<E: FromRequest<B>>(&self) -> ExtractResult<E> {
+        E::from_request(self)
+    }
+}
+
+macro_rules! extractor {
+    ($name:ident) => {
+        impl<T> $name<T> {
+            pub fn into_inner(self) -> T {
+                self.0
+            }
+        }
+
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &T {
+                &self.0
+            }
+        }
+
+        impl<T> DerefMut for $name<T> {
+            fn deref_mut(&mut self) -> &mut T {
+                &mut self.0
+            }
+        }
+    };
+}
+
+/// Path deserializes the [`Params`] the [`crate::router::Router`] matched
+/// into a struct of the parameter names, a tuple of their values in order
+/// or a single value if the route has one parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path<T>(pub T);
+
+extractor!(Path);
+
+impl<B, T: DeserializeOwned> FromRequest<B> for Path<T> {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        let params = req
+            .extensions()
+            .get::<Params>()
+            .ok_or(Rejection::MissingParams)?;
+
+        let entries: Entries = params.iter().collect();
+        entries
+            .deserialize()
+            .map(Path)
+            .map_err(|err| Rejection::InvalidPath(err.to_string()))
+    }
+}
+
+/// Query deserializes the query string of the request target, repeated
+/// keys can be collected into a `Vec`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query<T>(pub T);
+
+extractor!(Query);
+
+impl<B, T: DeserializeOwned> FromRequest<B> for Query<T> {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        let query = req.url().query().unwrap_or_default();
+        let entries: Entries = form_urlencoded::parse(query.as_bytes()).collect();
+        entries
+            .deserialize()
+            .map(Query)
+            .map_err(|err| Rejection::InvalidQuery(err.to_string()))
+    }
+}
+
+/// Header deserializes the request headers into a struct whose fields are
+/// named, or renamed with `#[serde(rename = "...")]`, after lower-case
+/// header names. Headers given more than once can be collected into a
+/// `Vec`, headers whose values are not visible ASCII are skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Header<T>(pub T);
+
+extractor!(Header);
+
+impl<B, T: DeserializeOwned> FromRequest<B> for Header<T> {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        let entries: Entries = req
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?)))
+            .collect();
+        entries
+            .deserialize()
+            .map(Header)
+            .map_err(|err| Rejection::InvalidHeaders(err.to_string()))
+    }
+}
+
+/// Json deserializes a request body of the `application/json` content type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T>(pub T);
+
+extractor!(Json);
+
+impl<B: AsRef<[u8]>, T: DeserializeOwned> FromRequest<B> for Json<T> {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        let mime = content_type(req.headers()).ok_or(Rejection::UnsupportedMediaType(JSON))?;
+        let is_json = mime == JSON
+            || mime
+                .split_once('/')
+                .map(|(kind, subtype)| kind == "application" && subtype.ends_with("+json"))
+                .unwrap_or(false);
+        if !is_json {
+            return Err(Rejection::UnsupportedMediaType(JSON));
+        }
+
+        let body = req.body().as_ref().ok_or(Rejection::MissingBody)?;
+        serde_json::from_slice(body.as_ref())
+            .map(Json)
+            .map_err(|err| match err.classify() {
+                serde_json::error::Category::Data => Rejection::InvalidJson(err.to_string()),
+                _ => Rejection::MalformedJson(err.to_string()),
+            })
+    }
+}
+
+/// Form deserializes a request body of the
+/// `application/x-www-form-urlencoded` content type, refusing bodies with
+/// more fields than the default [`FormLimits::max_fields`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Form<T>(pub T);
+
+extractor!(Form);
+
+impl<B: AsRef<[u8]>, T: DeserializeOwned> FromRequest<B> for Form<T> {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        if content_type(req.headers()).as_deref() != Some(FORM) {
+            return Err(Rejection::UnsupportedMediaType(FORM));
+        }
+
+        let body = req.body().as_ref().ok_or(Rejection::MissingBody)?;
+        let max_fields = FormLimits::default().max_fields();
+        let mut entries = Entries::default();
+        for (fields, (key, value)) in form_urlencoded::parse(body.as_ref()).enumerate() {
+            if fields == max_fields {
+                return Err(FormError::TooManyFields(max_fields).into());
+            }
+            entries.push(key, value);
+        }
+        entries
+            .deserialize()
+            .map(Form)
+            .map_err(|err| Rejection::InvalidForm(err.to_string()))
+    }
+}
+
+/// Extension clones the value of type `T` from the request's extensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension<T>(pub T);
+
+extractor!(Extension);
+
+impl<B, T: Clone + Send + Sync + 'static> FromRequest<B> for Extension<T> {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        req.extensions()
+            .get::<T>()
+            .cloned()
+            .map(Extension)
+            .ok_or(Rejection::MissingExtension(std::any::type_name::<T>()))
+    }
+}
+
+macro_rules! tuple_extractor {
+    ($($ty:ident),*) => {
+        impl<B, $($ty: FromRequest<B>),*> FromRequest<B> for ($($ty,)*) {
+            fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+                Ok(($($ty::from_request(req)?,)*))
+            }
+        }
+    };
+}
+
+tuple_extractor!(T1, T2);
+tuple_extractor!(T1, T2, T3);
+tuple_extractor!(T1, T2, T3, T4);
+tuple_extractor!(T1, T2, T3, T4, T5);
+tuple_extractor!(T1, T2, T3, T4, T5, T6);
+
+const JSON: &str = "application/json";
+const FORM: &str = "application/x-www-form-urlencoded";
+
+/// content_type returns the lower-cased media type of the content type
+/// header without its parameters.
+pub(crate) fn content_type(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+    let mime = value.split(';').next()?.trim();
+    Some(mime.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header, HeaderValue, Method, StatusCode, Uri, Version};
+    use serde::Deserialize;
+
+    use crate::{
+        extract::{Extension, Form, Header, Json, Path, Query, Rejection},
+        router::Router,
+        Request, RequestHead,
+    };
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Page {
+        page: u32,
+        tag: Vec<String>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Todo {
+        title: String,
+        done: bool,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Client {
+        #[serde(rename = "user-agent")]
+        agent: String,
+        #[serde(rename = "x-request-id")]
+        request_id: Option<u64>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct User(&'static str);
+
+    fn request(method: Method, uri: &'static str, body: Option<&str>) -> Request<Vec<u8>> {
+        let mut router = Router::new();
+        router
+            .route(method.clone(), "/todos/:id/notes/:note", ())
+            .unwrap();
+
+        let target = Uri::from_static(uri);
+        let mut head = RequestHead::new(method, Version::HTTP_11, target, String::from(uri));
+        router.route_head(&mut head).unwrap();
+        Request::from(body.map(|body| body.as_bytes().to_vec()), head)
+    }
+
+    #[test]
+    fn extracts_path_query_headers_and_extensions() {
+        let mut req = request(Method::GET, "/todos/7/notes/first?page=2&tag=a&tag=b", None);
+        req.head
+            .headers
+            .insert(header::USER_AGENT, HeaderValue::from_static("tests"));
+        req.head.extensions.insert(User("ewe"));
+
+        let (Path((id, note)), Query(page)) =
+            req.extract::<(Path<(u64, String)>, Query<Page>)>().unwrap();
+        assert_eq!((id, note.as_str()), (7, "first"));
+        assert_eq!(
+            page,
+            Page {
+                page: 2,
+                tag: vec!["a".into(), "b".into()]
+            }
+        );
+
+        let Header(client) = req.extract::<Header<Client>>().unwrap();
+        assert_eq!(client.agent, "tests");
+        assert_eq!(client.request_id, None);
+
+        let Extension(user) = req.extract::<Extension<User>>().unwrap();
+        assert_eq!(user, User("ewe"));
+
+        let rejection = req.extract::<Path<(u64, u64)>>().unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::NOT_FOUND);
+
+        let rejection = req.extract::<Extension<String>>().unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn extracts_json_and_form_bodies() {
+        let mut req = request(
+            Method::POST,
+            "/todos/1/notes/2",
+            Some(r#"{"title": "write", "done": false}"#),
+        );
+        assert_eq!(
+            req.extract::<Json<Todo>>().unwrap_err(),
+            Rejection::UnsupportedMediaType("application/json")
+        );
+
+        req.head.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json; charset=utf-8"),
+        );
+        let Json(todo) = req.extract::<Json<Todo>>().unwrap();
+        assert_eq!(todo.title, "write");
+
+        *req.body_mut() = Some(br#"{"title": "write"}"#.to_vec());
+        let rejection = req.extract::<Json<Todo>>().unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        *req.body_mut() = Some(br#"{"title": "#.to_vec());
+        let rejection = req.extract::<Json<Todo>>().unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+
+        let mut req = request(
+            Method::POST,
+            "/todos/1/notes/2",
+            Some("title=write+tests&done=true"),
+        );
+        req.head.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        let Form(todo) = req.extract::<Form<Todo>>().unwrap();
+        assert_eq!(
+            todo,
+            Todo {
+                title: "write tests".into(),
+                done: true
+            }
+        );
+
+        let response = req.extract::<Json<Todo>>().unwrap_err().into_response();
+        assert_eq!(
+            *response.head().status(),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        );
+    }
+
+    #[test]
+    fn refuses_forms_with_too_many_fields() {
+        let fields = |count: usize| {
+            (0..count)
+                .map(|field| format!("field{}=x", field))
+                .collect::<Vec<_>>()
+                .join("&")
+        };
+        let form = |body: &str| {
+            let mut req = request(Method::POST, "/todos/1/notes/2", Some(body));
+            req.head.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("application/x-www-form-urlencoded"),
+            );
+            req.extract::<Form<std::collections::HashMap<String, String>>>()
+        };
+
+        let Form(accepted) = form(&fields(256)).unwrap();
+        assert_eq!(accepted.len(), 256);
+
+        let refused = form(&fields(257)).unwrap_err();
+        assert!(matches!(refused, Rejection::PayloadTooLarge(_)));
+        assert_eq!(refused.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
@@ -0,0 +1,562 @@
+// Module implementing the StaticFiles handler serving the files of a
+// directory with caching, conditional and range requests.
+
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use percent_encoding::percent_decode_str;
+
+use crate::{
+    body::Body, handler::Handler, middleware::status_response, respond::guess_content_type,
+    router::Params, Request, Response,
+};
+
+/// ENCODINGS are the precompressed variants looked for, by preference.
+const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// FileBody is a response body [`StaticFiles`] serves files with, a
+/// [`Body`] streams the file while a `Vec<u8>` holds it whole.
+pub trait FileBody: From<Vec<u8>> + Sized {
+    /// from_file returns the body of the next `len` bytes of the file.
+    fn from_file(file: File, len: u64) -> io::Result<Self>;
+}
+
+impl FileBody for Vec<u8> {
+    fn from_file(file: File, len: u64) -> io::Result<Self> {
+        let mut body = Vec::with_capacity(len as usize);
+        file.take(len).read_to_end(&mut body)?;
+        Ok(body)
+    }
+}
+
+impl FileBody for Body {
+    fn from_file(file: File, len: u64) -> io::Result<Self> {
+        Ok(Body::reader(file, len))
+    }
+}
+
+/// StaticFiles is a [`Handler`] serving the files under its root directory
+/// to `GET` and `HEAD` requests.
+///
+/// The file is taken from the route's `*path` parameter, see
+/// [`StaticFiles::with_param`], or the whole request path when the route
+/// has none. Paths leaving the root, including through symlinks, are
+/// answered with `404 Not Found` like missing files.
+///
+/// Responses carry an `ETag` and `Last-Modified`, answering conditional
+/// requests with `304 Not Modified`, and single byte ranges with `206
+/// Partial Content`. With [`StaticFiles::with_precompressed`] the `.br` or
+/// `.gz` variants next to a file are served to clients accepting them.
+///
+/// Served as a handler of [`Body`] responses files are streamed from disk,
+/// see [`FileBody`].
+///
+/// # Example:
+///
+/// ```ignore
+/// router.get(
+///     "/assets/*path",
+///     StaticFiles::new("examples/todo/assets")
+///         .with_precompressed(true)
+///         .with_cache_control("public, max-age=3600")
+///         .boxed(),
+/// )?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct StaticFiles {
+    root: PathBuf,
+    param: String,
+    index: Option<String>,
+    precompressed: bool,
+    cache_control: Option<HeaderValue>,
+}
+
+impl StaticFiles {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            param: String::from("path"),
+            index: Some(String::from("index.html")),
+            precompressed: false,
+            cache_control: None,
+        }
+    }
+
+    /// with_param sets the route parameter holding the file's path,
+    /// defaults to `path`.
+    pub fn with_param(mut self, param: &str) -> Self {
+        self.param = param.into();
+        self
+    }
+
+    /// with_index sets the file served for directories, defaults to
+    /// `index.html`, `None` answers directories with `404 Not Found`.
+    pub fn with_index(mut self, index: Option<&str>) -> Self {
+        self.index = index.map(String::from);
+        self
+    }
+
+    pub fn with_precompressed(mut self, precompressed: bool) -> Self {
+        self.precompressed = precompressed;
+        self
+    }
+
+    /// with_cache_control sets the `Cache-Control` header of the served
+    /// files, ignored if it is not a valid header value.
+    pub fn with_cache_control(mut self, value: &str) -> Self {
+        self.cache_control = HeaderValue::from_str(value).ok();
+        self
+    }
+
+    /// resolve returns the file under the root the request path points to,
+    /// refusing any path that could leave it.
+    fn resolve(&self, path: &str) -> Option<PathBuf> {
+        let mut relative = PathBuf::new();
+        for part in path.split('/') {
+            if part.contains(['\\', '\0']) {
+                return None;
+            }
+            match Path::new(part).components().next() {
+                None | Some(Component::CurDir) => continue,
+                Some(Component::Normal(part)) => relative.push(part),
+                Some(_) => return None,
+            }
+        }
+
+        let mut file = self.contain(&self.root.join(relative))?;
+        if file.is_dir() {
+            file = self.contain(&file.join(self.index.as_ref()?))?;
+        }
+        file.is_file().then_some(file)
+    }
+
+    /// contain returns the canonical path, following symlinks, if it is
+    /// under the root.
+    fn contain(&self, path: &Path) -> Option<PathBuf> {
+        let root = self.root.canonicalize().ok()?;
+        let path = path.canonicalize().ok()?;
+        path.starts_with(&root).then_some(path)
+    }
+
+    /// variant returns the precompressed variant of the file the request
+    /// accepts, with its encoding.
+    fn variant(&self, file: &Path, headers: &HeaderMap) -> Option<(PathBuf, &'static str)> {
+        if !self.precompressed {
+            return None;
+        }
+        let accepted = accepted_encodings(headers);
+        ENCODINGS.iter().find_map(|(encoding, extension)| {
+            if !accepted.iter().any(|accepted| accepted == encoding) {
+                return None;
+            }
+            let mut name = file.file_name()?.to_os_string();
+            name.push(".");
+            name.push(extension);
+            let variant = self.contain(&file.with_file_name(name))?;
+            variant.is_file().then_some((variant, *encoding))
+        })
+    }
+
+    fn serve<U: FileBody>(&self, method: &Method, headers: &HeaderMap, path: &str) -> Response<U> {
+        let Some(file) = self.resolve(path) else {
+            return Response::not_found();
+        };
+
+        let content_type = guess_content_type(&file);
+        let (served, encoding) = match self.variant(&file, headers) {
+            Some((variant, encoding)) => (variant, Some(encoding)),
+            None => (file, None),
+        };
+        let Ok(metadata) = fs::metadata(&served) else {
+            return Response::not_found();
+        };
+
+        let len = metadata.len();
+        let modified = metadata.modified().ok();
+        let etag = etag(len, modified, encoding);
+
+        let mut response = status_response::<U>(StatusCode::OK);
+        let response_headers = response.head_mut().headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&etag) {
+            response_headers.insert(header::ETAG, value);
+        }
+        if let Some(modified) = modified {
+            if let Ok(value) = HeaderValue::from_str(&httpdate::fmt_http_date(modified)) {
+                response_headers.insert(header::LAST_MODIFIED, value);
+            }
+        }
+        if let Some(value) = &self.cache_control {
+            response_headers.insert(header::CACHE_CONTROL, value.clone());
+        }
+        if self.precompressed {
+            response_headers.insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+        }
+
+        if is_not_modified(headers, &etag, modified) {
+            *response.head_mut().status_mut() = StatusCode::NOT_MODIFIED;
+            return response;
+        }
+
+        let response_headers = response.head_mut().headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&content_type) {
+            response_headers.insert(header::CONTENT_TYPE, value);
+        }
+        if let Some(encoding) = encoding {
+            response_headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
+        response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+        let range = match headers.get(header::RANGE) {
+            Some(range) if if_range_matches(headers, &etag, modified) => parse_range(range, len),
+            _ => Ok(None),
+        };
+        let (start, end) = match range {
+            Ok(Some((start, end))) => {
+                *response.head_mut().status_mut() = StatusCode::PARTIAL_CONTENT;
+                let content_range = format!("bytes {}-{}/{}", start, end - 1, len);
+                if let Ok(value) = HeaderValue::from_str(&content_range) {
+                    response
+                        .head_mut()
+                        .headers_mut()
+                        .insert(header::CONTENT_RANGE, value);
+                }
+                (start, end)
+            }
+            Ok(None) => (0, len),
+            Err(()) => {
+                let mut response = status_response::<U>(StatusCode::RANGE_NOT_SATISFIABLE);
+                if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                    response
+                        .head_mut()
+                        .headers_mut()
+                        .insert(header::CONTENT_RANGE, value);
+                }
+                return response;
+            }
+        };
+
+        response
+            .head_mut()
+            .headers_mut()
+            .insert(header::CONTENT_LENGTH, HeaderValue::from(end - start));
+        if method == Method::HEAD {
+            return response;
+        }
+
+        match open_range(&served, start).and_then(|file| U::from_file(file, end - start)) {
+            Ok(body) => {
+                *response.body_mut() = Some(body);
+                response
+            }
+            Err(err) => {
+                tracing::error!("failed to read {:?}: {}", served, err);
+                status_response(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+impl<T, U> Handler<T, U> for StaticFiles
+where
+    T: 'static,
+    U: FileBody + 'static,
+{
+    fn call(&self, req: Request<T>) -> Response<U> {
+        let method = req.head().method();
+        if method != Method::GET && method != Method::HEAD {
+            let mut response = status_response(StatusCode::METHOD_NOT_ALLOWED);
+            response
+                .head_mut()
+                .headers_mut()
+                .insert(header::ALLOW, HeaderValue::from_static("GET, HEAD"));
+            return response;
+        }
+
+        let path = match req
+            .extensions()
+            .get::<Params>()
+            .and_then(|params| params.get(&self.param))
+        {
+            Some(path) => path.to_string(),
+            None => percent_decode_str(req.url().path())
+                .decode_utf8_lossy()
+                .into_owned(),
+        };
+        self.serve(method, req.headers(), &path)
+    }
+}
+
+/// etag returns a strong validator from the file's length and modification
+/// time, distinct for every encoding of it.
+fn etag(len: u64, modified: Option<SystemTime>, encoding: Option<&str>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos());
+    match encoding {
+        Some(encoding) => format!("\"{:x}-{:x}-{}\"", len, modified, encoding),
+        None => format!("\"{:x}-{:x}\"", len, modified),
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// is_not_modified evaluates `If-None-Match`, falling back to
+/// `If-Modified-Since` when absent as RFC 9110 requires.
+fn is_not_modified(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(value) = headers.get(header::IF_NONE_MATCH) {
+        let Ok(value) = value.to_str() else {
+            return false;
+        };
+        return value
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag);
+    }
+
+    let since = headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| httpdate::parse_http_date(value).ok());
+    match (since, modified) {
+        (Some(since), Some(modified)) => unix_seconds(modified) <= unix_seconds(since),
+        _ => false,
+    }
+}
+
+/// if_range_matches returns false when `If-Range` names another version of
+/// the file, which is then served whole.
+fn if_range_matches(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    let Some(value) = headers.get(header::IF_RANGE) else {
+        return true;
+    };
+    let Ok(value) = value.to_str() else {
+        return false;
+    };
+    if value.starts_with('"') {
+        return value == etag;
+    }
+    match (httpdate::parse_http_date(value), modified) {
+        (Ok(date), Some(modified)) => unix_seconds(modified) == unix_seconds(date),
+        _ => false,
+    }
+}
+
+/// parse_range returns the bounds of a single byte range of the file,
+/// `None` for ranges it ignores and an error for unsatisfiable ones.
+fn parse_range(value: &HeaderValue, len: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(range) = value
+        .to_str()
+        .ok()
+        .and_then(|value| value.strip_prefix("bytes="))
+    else {
+        return Ok(None);
+    };
+    // multiple ranges are served as the whole file.
+    if range.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = range.trim().split_once('-') else {
+        return Ok(None);
+    };
+
+    let bounds = match (start.parse::<u64>(), end.parse::<u64>()) {
+        (Ok(start), Ok(end)) if start <= end => (start, end.saturating_add(1).min(len)),
+        (Ok(start), Err(_)) if end.is_empty() => (start, len),
+        (Err(_), Ok(suffix)) if start.is_empty() && suffix > 0 => (len.saturating_sub(suffix), len),
+        _ => return Ok(None),
+    };
+
+    match bounds.0 < len {
+        true => Ok(Some(bounds)),
+        false => Err(()),
+    }
+}
+
+fn open_range(path: &Path, start: u64) -> io::Result<File> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    Ok(file)
+}
+
+fn accepted_encodings(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next()?.trim().to_ascii_lowercase();
+            let refused = parts.any(|param| {
+                matches!(param.trim().split_once('='), Some(("q", q)) if q.trim().parse::<f32>().map_or(true, |q| q <= 0.0))
+            });
+            (!refused).then_some(name)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use http::{header, HeaderValue, Method, StatusCode, Uri, Version};
+
+    use crate::{
+        body::Body,
+        files::StaticFiles,
+        handler::{BoxHandler, Handler, HandlerExt},
+        router::Router,
+        Request, RequestHead, Response,
+    };
+
+    fn request(path: &str, headers: &[(header::HeaderName, &'static str)]) -> Request<Vec<u8>> {
+        let mut head = RequestHead::new(
+            Method::GET,
+            Version::HTTP_11,
+            Uri::try_from(path).unwrap(),
+            String::from(path),
+        );
+        for (name, value) in headers {
+            head.headers
+                .insert(name.clone(), HeaderValue::from_static(value));
+        }
+        Request::from_head(head)
+    }
+
+    type Assets = Router<BoxHandler<Vec<u8>, Vec<u8>>>;
+
+    fn assets() -> (tempfile::TempDir, Assets) {
+        let dir = tempfile::tempdir().unwrap();
+        let public = dir.path().join("public");
+        fs::create_dir_all(public.join("css")).unwrap();
+        fs::write(public.join("index.html"), "<h1>todos</h1>").unwrap();
+        fs::write(public.join("css/site.css"), "body { margin: 0 }").unwrap();
+        fs::write(public.join("css/site.css.gz"), "gzipped").unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+
+        let mut router = Router::new();
+        router
+            .get(
+                "/assets/*path",
+                StaticFiles::new(&public).with_precompressed(true).boxed(),
+            )
+            .unwrap();
+        router.get("/", StaticFiles::new(&public).boxed()).unwrap();
+        (dir, router)
+    }
+
+    #[test]
+    fn serves_files_with_validators_and_refuses_traversal() {
+        let (_dir, router) = assets();
+
+        let response: Response<Vec<u8>> = router.call(request("/assets/css/site.css", &[]));
+        assert_eq!(*response.head().status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/css; charset=utf-8"
+        );
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "18");
+        assert_eq!(response.body().as_deref(), Some(&b"body { margin: 0 }"[..]));
+        let etag = response.headers()[header::ETAG].clone();
+        assert!(response.headers().contains_key(header::LAST_MODIFIED));
+
+        let mut conditional = request("/assets/css/site.css", &[]);
+        conditional.head.headers.insert(header::IF_NONE_MATCH, etag);
+        let response: Response<Vec<u8>> = router.call(conditional);
+        assert_eq!(*response.head().status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.body(), &None);
+
+        let response: Response<Vec<u8>> = router.call(request("/", &[]));
+        assert_eq!(response.body().as_deref(), Some(&b"<h1>todos</h1>"[..]));
+
+        for path in [
+            "/assets/../secret.txt",
+            "/assets/%2e%2e/secret.txt",
+            "/assets/css/..%2F..%2Fsecret.txt",
+            "/assets/%2Fetc%2Fpasswd",
+            "/assets/css/missing.css",
+        ] {
+            let response: Response<Vec<u8>> = router.call(request(path, &[]));
+            assert_eq!(*response.head().status(), StatusCode::NOT_FOUND, "{}", path);
+        }
+    }
+
+    #[test]
+    fn serves_ranges_and_precompressed_variants() {
+        let (_dir, router) = assets();
+
+        let response: Response<Vec<u8>> = router.call(request(
+            "/assets/css/site.css",
+            &[(header::RANGE, "bytes=0-3")],
+        ));
+        assert_eq!(*response.head().status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 0-3/18");
+        assert_eq!(response.body().as_deref(), Some(&b"body"[..]));
+
+        let response: Response<Vec<u8>> = router.call(request(
+            "/assets/css/site.css",
+            &[(header::RANGE, "bytes=-2")],
+        ));
+        assert_eq!(response.body().as_deref(), Some(&b" }"[..]));
+
+        let response: Response<Vec<u8>> = router.call(request(
+            "/assets/css/site.css",
+            &[(header::RANGE, "bytes=40-")],
+        ));
+        assert_eq!(*response.head().status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */18");
+
+        let response: Response<Vec<u8>> = router.call(request(
+            "/assets/css/site.css",
+            &[(header::ACCEPT_ENCODING, "br;q=0, gzip")],
+        ));
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/css; charset=utf-8"
+        );
+        assert_eq!(response.headers()[header::VARY], "accept-encoding");
+        assert_eq!(response.body().as_deref(), Some(&b"gzipped"[..]));
+    }
+
+    #[test]
+    fn streams_files_into_bodies() {
+        let (dir, _) = assets();
+        let files = StaticFiles::new(dir.path().join("public"));
+
+        let response: Response<Body> =
+            files.call(request("/css/site.css", &[(header::RANGE, "bytes=5-")]));
+        assert_eq!(*response.head().status(), StatusCode::PARTIAL_CONTENT);
+
+        let body = response.into_body().unwrap();
+        assert!(body.is_stream());
+        assert_eq!(body.size_hint().exact_len(), Some(13));
+        assert_eq!(&body.into_bytes().unwrap()[..], b"{ margin: 0 }");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_variants_linking_out_of_the_root() {
+        let (dir, router) = assets();
+        std::os::unix::fs::symlink(
+            dir.path().join("secret.txt"),
+            dir.path().join("public/css/site.css.br"),
+        )
+        .unwrap();
+
+        let response: Response<Vec<u8>> = router.call(request(
+            "/assets/css/site.css",
+            &[(header::ACCEPT_ENCODING, "br")],
+        ));
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+        assert_eq!(response.body().as_deref(), Some(&b"body { margin: 0 }"[..]));
+    }
+}
@@ -0,0 +1,875 @@
+// Module implementing the parsing of form bodies, url-encoded and streaming
+// multipart, within size limits.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use bytes::Bytes;
+use http::{header, HeaderMap};
+use serde::de::DeserializeOwned;
+use tempfile::NamedTempFile;
+
+use crate::{
+    body::{Body, BodyError, BodyResult},
+    de::Entries,
+    extract::{ExtractResult, FromRequest, Rejection},
+    Request,
+};
+
+const FORM: &str = "application/x-www-form-urlencoded";
+const MULTIPART: &str = "multipart/form-data";
+
+/// MAX_PART_HEAD is the largest head a multipart part may have.
+const MAX_PART_HEAD: usize = 16 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FormError {
+    #[error("expected a {FORM} or {MULTIPART} body")]
+    UnsupportedMediaType,
+
+    #[error("{0} is larger than the limit of {1} bytes")]
+    TooLarge(String, usize),
+
+    #[error("form has more than {0} fields")]
+    TooManyFields(usize),
+
+    #[error("malformed form body: {0}")]
+    Malformed(String),
+
+    #[error("invalid form body: {0}")]
+    Invalid(String),
+
+    #[error(transparent)]
+    Body(#[from] BodyError),
+
+    #[error("failed to spool form file: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type FormResult<T> = std::result::Result<T, FormError>;
+
+impl From<FormError> for Rejection {
+    fn from(err: FormError) -> Self {
+        match err {
+            FormError::UnsupportedMediaType => Rejection::UnsupportedMediaType(MULTIPART),
+            FormError::TooLarge(..) | FormError::TooManyFields(_) => {
+                Rejection::PayloadTooLarge(err.to_string())
+            }
+            FormError::Malformed(_) | FormError::Body(_) => {
+                Rejection::MalformedForm(err.to_string())
+            }
+            FormError::Invalid(_) => Rejection::InvalidForm(err.to_string()),
+            FormError::Io(_) => Rejection::FormStorage(err.to_string()),
+        }
+    }
+}
+
+/// FormLimits bounds what a form body may hold, refusing bodies past them
+/// as soon as they are read.
+#[derive(Debug, Clone)]
+pub struct FormLimits {
+    max_total_size: usize,
+    max_field_size: usize,
+    max_file_size: usize,
+    max_fields: usize,
+    spool_threshold: usize,
+    temp_dir: Option<PathBuf>,
+}
+
+impl Default for FormLimits {
+    fn default() -> Self {
+        Self {
+            max_total_size: 20 * 1024 * 1024,
+            max_field_size: 64 * 1024,
+            max_file_size: 10 * 1024 * 1024,
+            max_fields: 256,
+            spool_threshold: 256 * 1024,
+            temp_dir: None,
+        }
+    }
+}
+
+impl FormLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// with_max_total_size sets the largest body, defaults to 20MiB.
+    pub fn with_max_total_size(mut self, size: usize) -> Self {
+        self.max_total_size = size;
+        self
+    }
+
+    /// with_max_field_size sets the largest text field, defaults to 64KiB.
+    pub fn with_max_field_size(mut self, size: usize) -> Self {
+        self.max_field_size = size;
+        self
+    }
+
+    /// with_max_file_size sets the largest file, defaults to 10MiB.
+    pub fn with_max_file_size(mut self, size: usize) -> Self {
+        self.max_file_size = size;
+        self
+    }
+
+    /// with_max_fields sets how many fields and files a form may have,
+    /// defaults to 256.
+    pub fn with_max_fields(mut self, fields: usize) -> Self {
+        self.max_fields = fields;
+        self
+    }
+
+    pub fn max_fields(&self) -> usize {
+        self.max_fields
+    }
+
+    /// with_spool_threshold sets the size past which files are written to
+    /// a temporary file instead of being kept in memory, defaults to
+    /// 256KiB.
+    pub fn with_spool_threshold(mut self, size: usize) -> Self {
+        self.spool_threshold = size;
+        self
+    }
+
+    /// with_temp_dir sets the directory files are spooled to, defaults to
+    /// the system's temporary directory.
+    pub fn with_temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = Some(dir.into());
+        self
+    }
+}
+
+#[derive(Debug)]
+enum FileContent {
+    Memory(Vec<u8>),
+    Spooled(NamedTempFile),
+}
+
+/// FilePart is a file uploaded with a multipart form, kept in memory or
+/// spooled to a temporary file removed once it is dropped.
+#[derive(Debug)]
+pub struct FilePart {
+    name: String,
+    filename: String,
+    content_type: Option<String>,
+    len: usize,
+    content: FileContent,
+}
+
+impl FilePart {
+    /// name returns the name of the form field the file was sent as.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// filename returns the name the client gave the file, it must not be
+    /// trusted as a path.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// path returns the temporary file the content was spooled to.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.content {
+            FileContent::Memory(_) => None,
+            FileContent::Spooled(file) => Some(file.path()),
+        }
+    }
+
+    /// bytes reads the whole content of the file.
+    pub fn bytes(&self) -> io::Result<Vec<u8>> {
+        match &self.content {
+            FileContent::Memory(content) => Ok(content.clone()),
+            FileContent::Spooled(file) => fs::read(file.path()),
+        }
+    }
+
+    /// persist moves the content of the file to the path.
+    pub fn persist(self, path: impl AsRef<Path>) -> io::Result<()> {
+        match self.content {
+            FileContent::Memory(content) => fs::write(path, content),
+            FileContent::Spooled(file) => file.persist(path).map(|_| ()).map_err(|err| err.error),
+        }
+    }
+}
+
+/// FormData are the text fields and files of a form body, parsed within
+/// its [`FormLimits`].
+///
+/// As an extractor it parses buffered url-encoded or multipart bodies with
+/// the default limits, [`Request::into_form_data`] streams a [`Body`]
+/// instead.
+///
+/// # Example:
+///
+/// ```ignore
+/// let form = req.extract::<FormData>()?;
+/// let todo: NewTodo = form.deserialize()?;
+/// for (index, file) in form.into_files().into_iter().enumerate() {
+///     file.persist(uploads.join(format!("{}-{}", todo.id, index)))?;
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct FormData {
+    fields: Vec<(String, String)>,
+    files: Vec<FilePart>,
+}
+
+impl FormData {
+    /// urlencoded parses an `application/x-www-form-urlencoded` body.
+    pub fn urlencoded(body: &[u8], limits: &FormLimits) -> FormResult<Self> {
+        if body.len() > limits.max_total_size {
+            return Err(FormError::TooLarge(
+                String::from("form"),
+                limits.max_total_size,
+            ));
+        }
+
+        let mut form = Self::default();
+        for (name, value) in form_urlencoded::parse(body) {
+            if form.fields.len() == limits.max_fields {
+                return Err(FormError::TooManyFields(limits.max_fields));
+            }
+            if value.len() > limits.max_field_size {
+                return Err(FormError::TooLarge(
+                    format!("field {:?}", name),
+                    limits.max_field_size,
+                ));
+            }
+            form.fields.push((name.into_owned(), value.into_owned()));
+        }
+        Ok(form)
+    }
+
+    /// multipart parses a `multipart/form-data` body with the boundary as
+    /// its chunks arrive, holding no more than the current part in memory
+    /// until it is spooled.
+    pub fn multipart<I>(chunks: I, boundary: &str, limits: &FormLimits) -> FormResult<Self>
+    where
+        I: IntoIterator<Item = BodyResult<Bytes>>,
+    {
+        let mut parser = MultipartParser::new(boundary, limits);
+        for chunk in chunks {
+            parser.feed(&chunk?)?;
+        }
+        parser.finish()
+    }
+
+    /// parse parses the body as the form of the content type, the value of
+    /// the request's `Content-Type` header.
+    pub fn parse<I>(chunks: I, content_type: &str, limits: &FormLimits) -> FormResult<Self>
+    where
+        I: IntoIterator<Item = BodyResult<Bytes>>,
+    {
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        match essence.as_str() {
+            FORM => {
+                let mut body = Vec::new();
+                for chunk in chunks {
+                    let chunk = chunk?;
+                    if body.len() + chunk.len() > limits.max_total_size {
+                        return Err(FormError::TooLarge(
+                            String::from("form"),
+                            limits.max_total_size,
+                        ));
+                    }
+                    body.extend_from_slice(&chunk);
+                }
+                Self::urlencoded(&body, limits)
+            }
+            MULTIPART => match boundary(content_type) {
+                Some(boundary) => Self::multipart(chunks, &boundary, limits),
+                None => Err(FormError::Malformed(String::from(
+                    "missing multipart boundary",
+                ))),
+            },
+            _ => Err(FormError::UnsupportedMediaType),
+        }
+    }
+
+    /// get returns the value of the first text field with the name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// get_all returns the values of the text fields with the name.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// file returns the first file sent as the field with the name.
+    pub fn file(&self, name: &str) -> Option<&FilePart> {
+        self.files.iter().find(|file| file.name == name)
+    }
+
+    pub fn files(&self) -> &[FilePart] {
+        &self.files
+    }
+
+    pub fn into_files(self) -> Vec<FilePart> {
+        self.files
+    }
+
+    /// deserialize deserializes the text fields into `T`, the way the
+    /// [`crate::extract::Form`] extractor does.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> FormResult<T> {
+        let entries: Entries = self
+            .fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        entries
+            .deserialize()
+            .map_err(|err| FormError::Invalid(err.to_string()))
+    }
+}
+
+impl<B: AsRef<[u8]>> FromRequest<B> for FormData {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let body = req.body().as_ref().ok_or(Rejection::MissingBody)?;
+
+        let chunk = Bytes::copy_from_slice(body.as_ref());
+        Ok(Self::parse(
+            [Ok(chunk)],
+            content_type,
+            &FormLimits::default(),
+        )?)
+    }
+}
+
+impl Request<Body> {
+    /// into_form_data parses the streaming body as the form its
+    /// `Content-Type` names, reading it only as far as the limits allow.
+    pub fn into_form_data(self, limits: &FormLimits) -> FormResult<FormData> {
+        let content_type = self
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        match self.body {
+            Some(body) => FormData::parse(body, &content_type, limits),
+            None => FormData::parse(std::iter::empty(), &content_type, limits),
+        }
+    }
+}
+
+/// boundary returns the boundary parameter of a multipart content type.
+fn boundary(content_type: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.trim().split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("boundary") {
+            return None;
+        }
+        let value = value.trim().trim_matches('"');
+        (!value.is_empty() && value.len() <= 70).then(|| value.to_string())
+    })
+}
+
+/// parameter returns the value of the parameter of a header value such as
+/// `form-data; name="title"`.
+fn parameter(value: &str, name: &str) -> Option<String> {
+    let mut rest = value.split_once(';')?.1;
+    loop {
+        let (key, after) = rest.split_once('=')?;
+        let after = after.trim_start();
+
+        let (param, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut param = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = None;
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => param.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            end = Some(index + 1);
+                            break;
+                        }
+                        c => param.push(c),
+                    }
+                }
+                let remaining = &quoted[end?..];
+                (
+                    param,
+                    remaining.split_once(';').map_or("", |(_, rest)| rest),
+                )
+            }
+            None => match after.split_once(';') {
+                Some((param, remaining)) => (param.trim().to_string(), remaining),
+                None => (after.trim().to_string(), ""),
+            },
+        };
+
+        if key.trim().eq_ignore_ascii_case(name) {
+            return Some(param);
+        }
+        if remaining.is_empty() {
+            return None;
+        }
+        rest = remaining;
+    }
+}
+
+enum Sink {
+    Field(Vec<u8>),
+    File(Vec<u8>),
+    Spooled(NamedTempFile),
+}
+
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    len: usize,
+    sink: Sink,
+}
+
+enum State {
+    Preamble,
+    Boundary,
+    Head,
+    Content(Part),
+    Done,
+}
+
+/// MultipartParser parses a multipart body incrementally, every part's
+/// content is written out as soon as it cannot be the start of the next
+/// delimiter.
+struct MultipartParser<'a> {
+    delimiter: Vec<u8>,
+    limits: &'a FormLimits,
+    buffer: Vec<u8>,
+    state: State,
+    total: usize,
+    form: FormData,
+}
+
+impl<'a> MultipartParser<'a> {
+    fn new(boundary: &str, limits: &'a FormLimits) -> Self {
+        Self {
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            limits,
+            // the first delimiter has no line break before it.
+            buffer: b"\r\n".to_vec(),
+            state: State::Preamble,
+            total: 0,
+            form: FormData::default(),
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> FormResult<()> {
+        self.total += chunk.len();
+        if self.total > self.limits.max_total_size {
+            return Err(FormError::TooLarge(
+                String::from("form"),
+                self.limits.max_total_size,
+            ));
+        }
+        self.buffer.extend_from_slice(chunk);
+
+        loop {
+            let state = std::mem::replace(&mut self.state, State::Done);
+            match self.step(state)? {
+                Some(state) => self.state = state,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// step advances the parser over the buffer, returning `None` with the
+    /// parser's state set once it needs more input.
+    fn step(&mut self, state: State) -> FormResult<Option<State>> {
+        match state {
+            State::Preamble => match find(&self.buffer, &self.delimiter) {
+                Some(index) => {
+                    self.buffer.drain(..index + self.delimiter.len());
+                    Ok(Some(State::Boundary))
+                }
+                None => {
+                    let keep = self.delimiter.len() - 1;
+                    if self.buffer.len() > keep {
+                        self.buffer.drain(..self.buffer.len() - keep);
+                    }
+                    self.state = State::Preamble;
+                    Ok(None)
+                }
+            },
+            State::Boundary => {
+                if self.buffer.len() < 2 {
+                    self.state = State::Boundary;
+                    return Ok(None);
+                }
+                match &self.buffer[..2] {
+                    b"--" => {
+                        self.buffer.clear();
+                        self.state = State::Done;
+                        Ok(None)
+                    }
+                    b"\r\n" => {
+                        self.buffer.drain(..2);
+                        Ok(Some(State::Head))
+                    }
+                    _ => Err(FormError::Malformed(String::from("invalid delimiter"))),
+                }
+            }
+            State::Head => match find(&self.buffer, b"\r\n\r\n") {
+                Some(index) => {
+                    let head: Vec<u8> = self.buffer.drain(..index + 4).collect();
+                    Ok(Some(State::Content(self.part(&head[..index])?)))
+                }
+                None if self.buffer.len() > MAX_PART_HEAD => Err(FormError::TooLarge(
+                    String::from("part head"),
+                    MAX_PART_HEAD,
+                )),
+                None => {
+                    self.state = State::Head;
+                    Ok(None)
+                }
+            },
+            State::Content(mut part) => match find(&self.buffer, &self.delimiter) {
+                Some(index) => {
+                    let content: Vec<u8> =
+                        self.buffer.drain(..index + self.delimiter.len()).collect();
+                    self.write(&mut part, &content[..index])?;
+                    self.complete(part)?;
+                    Ok(Some(State::Boundary))
+                }
+                None => {
+                    let keep = self.delimiter.len() - 1;
+                    if self.buffer.len() > keep {
+                        let content: Vec<u8> =
+                            self.buffer.drain(..self.buffer.len() - keep).collect();
+                        self.write(&mut part, &content)?;
+                    }
+                    self.state = State::Content(part);
+                    Ok(None)
+                }
+            },
+            State::Done => {
+                self.buffer.clear();
+                Ok(None)
+            }
+        }
+    }
+
+    fn part(&self, head: &[u8]) -> FormResult<Part> {
+        let fields = self.form.fields.len() + self.form.files.len();
+        if fields == self.limits.max_fields {
+            return Err(FormError::TooManyFields(self.limits.max_fields));
+        }
+
+        let head = std::str::from_utf8(head)
+            .map_err(|_| FormError::Malformed(String::from("part head is not utf-8")))?;
+        let mut headers = HeaderMap::new();
+        for line in head.split("\r\n") {
+            let Some((name, value)) = line.split_once(':') else {
+                return Err(FormError::Malformed(format!(
+                    "invalid part header {:?}",
+                    line
+                )));
+            };
+            let name = header::HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| FormError::Malformed(format!("invalid part header {:?}", line)))?;
+            let value = header::HeaderValue::from_str(value.trim())
+                .map_err(|_| FormError::Malformed(format!("invalid part header {:?}", line)))?;
+            headers.append(name, value);
+        }
+
+        let disposition = headers
+            .get(header::CONTENT_DISPOSITION)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| {
+                value
+                    .trim_start()
+                    .to_ascii_lowercase()
+                    .starts_with("form-data")
+            })
+            .ok_or_else(|| FormError::Malformed(String::from("part is not form-data")))?;
+        let name = parameter(disposition, "name")
+            .ok_or_else(|| FormError::Malformed(String::from("part has no name")))?;
+        let filename = parameter(disposition, "filename");
+
+        Ok(Part {
+            sink: match filename {
+                Some(_) => Sink::File(Vec::new()),
+                None => Sink::Field(Vec::new()),
+            },
+            content_type: headers
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            len: 0,
+            name,
+            filename,
+        })
+    }
+
+    fn write(&self, part: &mut Part, content: &[u8]) -> FormResult<()> {
+        part.len += content.len();
+        let limit = match part.sink {
+            Sink::Field(_) => self.limits.max_field_size,
+            Sink::File(_) | Sink::Spooled(_) => self.limits.max_file_size,
+        };
+        if part.len > limit {
+            return Err(FormError::TooLarge(format!("field {:?}", part.name), limit));
+        }
+
+        match &mut part.sink {
+            Sink::Field(buffer) => buffer.extend_from_slice(content),
+            Sink::File(buffer) if part.len > self.limits.spool_threshold => {
+                let mut file = match &self.limits.temp_dir {
+                    Some(dir) => NamedTempFile::new_in(dir)?,
+                    None => NamedTempFile::new()?,
+                };
+                file.write_all(buffer)?;
+                file.write_all(content)?;
+                part.sink = Sink::Spooled(file);
+            }
+            Sink::File(buffer) => buffer.extend_from_slice(content),
+            Sink::Spooled(file) => file.write_all(content)?,
+        }
+        Ok(())
+    }
+
+    fn complete(&mut self, part: Part) -> FormResult<()> {
+        let content = match part.sink {
+            Sink::Field(content) => {
+                let value = String::from_utf8(content).map_err(|_| {
+                    FormError::Malformed(format!("field {:?} is not utf-8", part.name))
+                })?;
+                self.form.fields.push((part.name, value));
+                return Ok(());
+            }
+            Sink::File(content) => FileContent::Memory(content),
+            Sink::Spooled(mut file) => {
+                file.flush()?;
+                FileContent::Spooled(file)
+            }
+        };
+
+        self.form.files.push(FilePart {
+            name: part.name,
+            filename: part.filename.unwrap_or_default(),
+            content_type: part.content_type,
+            len: part.len,
+            content,
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> FormResult<FormData> {
+        match self.state {
+            State::Done => Ok(self.form),
+            _ => Err(FormError::Malformed(String::from(
+                "body ended before the last part",
+            ))),
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http::{header, HeaderValue, Method, Uri, Version};
+
+    use crate::{
+        body::Body,
+        extract::Rejection,
+        forms::{FormData, FormError, FormLimits},
+        Request, RequestHead,
+    };
+
+    const BODY: &str = "preamble\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        write \"tests\"\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"tags\"\r\n\
+        \r\n\
+        a\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"tags\"\r\n\
+        \r\n\
+        b\r\n\
+        --XyZ\r\n\
+        Content-Disposition: form-data; name=\"attachment\"; filename=\"notes; v2.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        line one\r\nline two\r\n\
+        --XyZ--\r\n\
+        epilogue";
+
+    #[derive(serde::Deserialize)]
+    struct Todo {
+        title: String,
+        tags: Vec<String>,
+    }
+
+    fn chunked(body: &str, size: usize) -> Vec<Result<Bytes, crate::body::BodyError>> {
+        body.as_bytes()
+            .chunks(size)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect()
+    }
+
+    #[test]
+    fn parses_multipart_bodies_in_any_chunking() {
+        for size in [1, 3, 7, 64, BODY.len()] {
+            let form = FormData::multipart(chunked(BODY, size), "XyZ", &FormLimits::new()).unwrap();
+
+            let todo: Todo = form.deserialize().unwrap();
+            assert_eq!(todo.title, "write \"tests\"");
+            assert_eq!(todo.tags, vec!["a", "b"]);
+
+            let file = form.file("attachment").unwrap();
+            assert_eq!(file.filename(), "notes; v2.txt");
+            assert_eq!(file.content_type(), Some("text/plain"));
+            assert_eq!(file.bytes().unwrap(), b"line one\r\nline two");
+            assert_eq!(file.path(), None);
+        }
+    }
+
+    #[test]
+    fn spools_large_files_and_enforces_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let limits = FormLimits::new()
+            .with_spool_threshold(4)
+            .with_temp_dir(dir.path());
+        let form = FormData::multipart(chunked(BODY, 5), "XyZ", &limits).unwrap();
+        let file = form.file("attachment").unwrap();
+        assert!(file.path().unwrap().starts_with(dir.path()));
+        assert_eq!(file.len(), 18);
+        assert_eq!(file.bytes().unwrap(), b"line one\r\nline two");
+
+        let target = dir.path().join("notes.txt");
+        form.into_files().pop().unwrap().persist(&target).unwrap();
+        assert_eq!(std::fs::read(&target).unwrap(), b"line one\r\nline two");
+
+        let limits = FormLimits::new().with_max_file_size(10);
+        assert!(matches!(
+            FormData::multipart(chunked(BODY, 8), "XyZ", &limits),
+            Err(FormError::TooLarge(..))
+        ));
+        let limits = FormLimits::new().with_max_fields(2);
+        assert!(matches!(
+            FormData::multipart(chunked(BODY, 8), "XyZ", &limits),
+            Err(FormError::TooManyFields(2))
+        ));
+        let limits = FormLimits::new().with_max_total_size(32);
+        assert!(matches!(
+            FormData::multipart(chunked(BODY, 8), "XyZ", &limits),
+            Err(FormError::TooLarge(..))
+        ));
+        assert!(matches!(
+            FormData::multipart(
+                chunked(&BODY[..BODY.len() - 20], 8),
+                "XyZ",
+                &FormLimits::new()
+            ),
+            Err(FormError::Malformed(_))
+        ));
+
+        let limits = FormLimits::new().with_max_field_size(3);
+        assert!(matches!(
+            FormData::urlencoded(b"title=long&done=1", &limits),
+            Err(FormError::TooLarge(..))
+        ));
+    }
+
+    #[test]
+    fn extracts_forms_from_requests() {
+        let mut head = RequestHead::new(
+            Method::POST,
+            Version::HTTP_11,
+            Uri::from_static("/todos"),
+            String::from("/todos"),
+        );
+        head.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        let request = Request::new(b"title=write&tags=a&tags=b".to_vec(), head);
+        let form = request.extract::<FormData>().unwrap();
+        assert_eq!(form.get("title"), Some("write"));
+        assert_eq!(form.get_all("tags").collect::<Vec<_>>(), vec!["a", "b"]);
+
+        let (mut sender, body) = Body::channel(2);
+        let writer = std::thread::spawn(move || {
+            for chunk in BODY.as_bytes().chunks(16) {
+                sender.send(chunk.to_vec()).unwrap();
+            }
+            sender.finish();
+        });
+        let mut head = RequestHead::new(
+            Method::POST,
+            Version::HTTP_11,
+            Uri::from_static("/todos"),
+            String::from("/todos"),
+        );
+        head.headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("multipart/form-data; boundary=\"XyZ\""),
+        );
+        let form = Request::new(body, head)
+            .into_form_data(&FormLimits::new())
+            .unwrap();
+        assert_eq!(form.files().len(), 1);
+        writer.join().unwrap();
+
+        let mut request = request;
+        request
+            .head
+            .headers
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        assert_eq!(
+            request.extract::<FormData>().err(),
+            Some(Rejection::UnsupportedMediaType("multipart/form-data"))
+        );
+    }
+}
@@ -0,0 +1,101 @@
+// Module implementing the Handler and Layer traits composing request handling.
+
+use http::{header, HeaderValue};
+
+use crate::{
+    router::{Router, RouterError},
+    Request, Response,
+};
+
+/// Handler answers a [`Request`] with a body of type `T` with a
+/// [`Response`] with a body of type `U`.
+///
+/// Functions and closures of `Fn(Request<T>) -> Response<U>` are handlers,
+/// as is a [`Router`] of handlers.
+pub trait Handler<T, U>: Send + Sync + 'static {
+    fn call(&self, req: Request<T>) -> Response<U>;
+}
+
+impl<T, U, F> Handler<T, U> for F
+where
+    F: Fn(Request<T>) -> Response<U> + Send + Sync + 'static,
+{
+    fn call(&self, req: Request<T>) -> Response<U> {
+        self(req)
+    }
+}
+
+pub type BoxHandler<T, U> = Box<dyn Handler<T, U>>;
+
+impl<T: 'static, U: 'static> Handler<T, U> for BoxHandler<T, U> {
+    fn call(&self, req: Request<T>) -> Response<U> {
+        self.as_ref().call(req)
+    }
+}
+
+/// Layer wraps a handler into another adding behaviour around it, such as
+/// the ones in [`crate::middleware`].
+pub trait Layer<H> {
+    type Handler;
+
+    fn layer(&self, inner: H) -> Self::Handler;
+}
+
+pub trait HandlerExt<T, U>: Handler<T, U> + Sized {
+    /// layer wraps the handler with the giving layer.
+    fn layer<L: Layer<Self>>(self, layer: &L) -> L::Handler {
+        layer.layer(self)
+    }
+
+    /// boxed erases the type of the handler, letting handlers of different
+    /// types share a [`Router`].
+    fn boxed(self) -> BoxHandler<T, U> {
+        Box::new(self)
+    }
+}
+
+impl<T, U, H: Handler<T, U>> HandlerExt<T, U> for H {}
+
+impl<H> Router<H> {
+    /// boxed erases the type of the router's handlers, e.g to nest a group
+    /// of layered routes into a router of [`BoxHandler`]s.
+    pub fn boxed<T: 'static, U: 'static>(self) -> Router<BoxHandler<T, U>>
+    where
+        H: Handler<T, U>,
+    {
+        self.map(|handler| handler.boxed())
+    }
+
+    /// dispatch routes the request to its handler, answering with `404 Not
+    /// Found` or `405 Method Not Allowed` if no route matches.
+    pub fn dispatch<T, U>(&self, mut req: Request<T>) -> Response<U>
+    where
+        H: Handler<T, U>,
+        U: From<String>,
+    {
+        match self.route_request(&mut req) {
+            Ok(handler) => handler.call(req),
+            Err(err) => {
+                let mut response = crate::middleware::status_response(err.status());
+                *response.body_mut() = Some(U::from(err.to_string()));
+                if let RouterError::MethodNotAllowed { allowed, .. } = &err {
+                    let allowed: Vec<&str> = allowed.iter().map(|method| method.as_str()).collect();
+                    if let Ok(value) = HeaderValue::from_str(&allowed.join(", ")) {
+                        response.head_mut().headers.insert(header::ALLOW, value);
+                    }
+                }
+                response
+            }
+        }
+    }
+}
+
+impl<T, U, H> Handler<T, U> for Router<H>
+where
+    H: Handler<T, U>,
+    U: From<String>,
+{
+    fn call(&self, req: Request<T>) -> Response<U> {
+        self.dispatch(req)
+    }
+}
@@ -1,15 +1,26 @@
 use core::fmt;
-use std::{collections::HashMap, convert::Infallible, fmt::Debug, ops::Deref, str::FromStr};
-
-use http::{
-    header::{InvalidHeaderName, InvalidHeaderValue, ToStrError},
-    method::InvalidMethod,
-    status::InvalidStatusCode,
-    uri::InvalidUri,
-    HeaderValue, StatusCode,
-};
+use std::{fmt::Debug, ops::Deref, str::FromStr};
+
+use http::{HeaderName, HeaderValue, StatusCode};
 /// Implementation of routing and request/response primitives.
 pub use http::{Extensions, HeaderMap, Method, Uri, Version};
+use serde::{Deserialize, Serialize};
+
+pub mod body;
+#[cfg(feature = "domain")]
+pub mod bridge;
+pub mod cookies;
+mod de;
+pub mod extract;
+pub mod files;
+pub mod forms;
+pub mod handler;
+pub mod middleware;
+pub mod respond;
+pub mod router;
+pub mod server;
+pub mod session;
+pub mod urls;
 
 macro_rules! field_method {
     ($field_name:ident, $type_name:ty) => {
@@ -244,140 +255,112 @@ impl<T> Request<T> {
 /// LightRequest is a definition of request that allows these elements to be passed over
 /// to WASM or any other light weight runtime environment that do not require the larger
 /// content of a Request object that has more larger details.
+///
+/// Headers are kept in order as name and raw value pairs, one pair per
+/// value, so multi-valued and non-ASCII headers survive the round trip
+/// to and from a [`Request`]. Extensions are not carried over.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LightRequest<T> {
     pub method: String,
     pub url: String,
-    pub headers: HashMap<String, String>,
+    pub version: String,
+    pub route_path: RouteURL,
+    pub headers: Vec<(String, Vec<u8>)>,
     pub body: Option<T>,
 }
 
-impl<T: fmt::Debug> fmt::Debug for LightRequest<T> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("LightRequest")
-            .field("method", &self.method)
-            .field("url", &self.url)
-            .field("headers", &self.headers)
-            .field("body", &self.body)
-            .finish()
-    }
-}
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LightError {
+    #[error("invalid url: {0}")]
+    InvalidUrl(String),
 
-pub enum TryFromLightRequestError {
-    FailedURLConversion(InvalidUri),
-    FailedMethodConversion(InvalidMethod),
-    FailedInvalidHeaderName(InvalidHeaderName),
-    FailedInvalidHeaderValue(InvalidHeaderValue),
-}
+    #[error("invalid method: {0}")]
+    InvalidMethod(String),
 
-impl From<InvalidHeaderName> for TryFromLightRequestError {
-    fn from(value: InvalidHeaderName) -> Self {
-        TryFromLightRequestError::FailedInvalidHeaderName(value)
-    }
-}
+    #[error("invalid status code: {0}")]
+    InvalidStatusCode(String),
 
-impl From<InvalidHeaderValue> for TryFromLightRequestError {
-    fn from(value: InvalidHeaderValue) -> Self {
-        TryFromLightRequestError::FailedInvalidHeaderValue(value)
-    }
-}
+    #[error("unknown http version: {0}")]
+    UnknownVersion(String),
 
-impl From<InvalidUri> for TryFromLightRequestError {
-    fn from(value: InvalidUri) -> Self {
-        TryFromLightRequestError::FailedURLConversion(value)
-    }
-}
+    #[error("invalid header name: {0}")]
+    InvalidHeaderName(String),
 
-impl From<InvalidMethod> for TryFromLightRequestError {
-    fn from(value: InvalidMethod) -> Self {
-        TryFromLightRequestError::FailedMethodConversion(value)
-    }
+    #[error("invalid value for header {0}")]
+    InvalidHeaderValue(String),
 }
 
-impl<T> TryFrom<LightRequest<T>> for Request<T> {
-    type Error = TryFromLightRequestError;
+pub type LightResult<T> = std::result::Result<T, LightError>;
 
-    /// This implementation of is unique in that it skips any headers that
-    /// to not mappable to a String for as the `HeaderMap` type actually
-    /// does some underlying logic to deal with non-ASCII character heders.
-    ///
-    /// Secondly the underlying body of the Request is also consumed by this
-    /// returned LightRequest.
-    ///
-    /// WARNING: Be warned this method will panic if the method or url are invalid.
-    fn try_from(value: LightRequest<T>) -> Result<Self, TryFromLightRequestError> {
-        let url = value.url.clone();
-        let uri = Uri::from_str(value.url.as_str())?;
-        let method = Method::from_bytes(value.method.as_bytes())?;
-
-        let mut head = RequestHead::new(method, Version::HTTP_10, uri, url);
-        for (key, value) in value.headers.iter() {
-            head.headers.insert(
-                http::HeaderName::from_str(key.as_ref())?,
-                http::HeaderValue::from_str(value.as_ref())?,
-            );
+fn to_light_headers(headers: HeaderMap) -> Vec<(String, Vec<u8>)> {
+    let mut light = Vec::with_capacity(headers.len());
+    let mut current: Option<HeaderName> = None;
+    for (name, value) in headers {
+        // the map only yields a name on the first of its values.
+        if let Some(name) = name {
+            current = Some(name);
+        }
+        if let Some(name) = &current {
+            light.push((name.to_string(), value.as_bytes().to_vec()));
         }
-        return Ok(Request::from(value.body, head));
     }
+    light
 }
 
-pub enum TryFromRequestError {
-    NonASCIIHeaderValue(ToStrError),
-    InfallibleError(Infallible),
-}
-
-impl From<ToStrError> for TryFromRequestError {
-    fn from(value: ToStrError) -> Self {
-        TryFromRequestError::NonASCIIHeaderValue(value)
+fn from_light_headers(light: Vec<(String, Vec<u8>)>) -> LightResult<HeaderMap> {
+    let mut headers = HeaderMap::with_capacity(light.len());
+    for (name, value) in light {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| LightError::InvalidHeaderName(name))?;
+        let value = HeaderValue::from_bytes(&value)
+            .map_err(|_| LightError::InvalidHeaderValue(name.to_string()))?;
+        headers.append(name, value);
     }
+    Ok(headers)
 }
 
-impl From<Infallible> for TryFromRequestError {
-    fn from(value: Infallible) -> Self {
-        TryFromRequestError::InfallibleError(value)
+impl<T> TryFrom<LightRequest<T>> for Request<T> {
+    type Error = LightError;
+
+    /// Builds the request from its light form, failing on any invalid
+    /// method, url, version or header.
+    fn try_from(value: LightRequest<T>) -> LightResult<Self> {
+        let uri =
+            Uri::from_str(&value.url).map_err(|_| LightError::InvalidUrl(value.url.clone()))?;
+        let method = Method::from_bytes(value.method.as_bytes())
+            .map_err(|_| LightError::InvalidMethod(value.method.clone()))?;
+        let version = get_version(&value.version)?;
+
+        let mut head = RequestHead::new(method, version, uri, value.route_path);
+        head.headers = from_light_headers(value.headers)?;
+        Ok(Request::from(value.body, head))
     }
 }
 
 impl<T> TryFrom<Request<T>> for LightRequest<T> {
-    type Error = TryFromRequestError;
-
-    /// This implementation of is unique in that it skips any headers that
-    /// to not mappable to a String for as the `HeaderMap` type actually
-    /// does some underlying logic to deal with non-ASCII character heders.
-    ///
-    /// Secondly the underlying body of the Request is also consumed by this
-    /// returned LightRequest.
-    fn try_from(value: Request<T>) -> Result<Self, Self::Error> {
-        let mut headers = HashMap::new();
-
-        for (keyc, value) in value.head.headers {
-            match keyc {
-                Some(key) => {
-                    let key_name = key.to_string();
-                    let value_string = String::try_from(value.to_str()?)?;
-                    headers.entry(key_name).and_modify(|e| *e = value_string);
-                }
-                None => continue,
-            }
-        }
+    type Error = LightError;
 
+    /// Consumes the request and its body into its light form, failing only
+    /// on http versions it has no name for.
+    fn try_from(value: Request<T>) -> LightResult<Self> {
         Ok(Self {
             method: value.head.method.to_string(),
             url: value.head.target.to_string(),
+            version: get_version_text(value.head.version)?,
+            route_path: value.head.route_path,
+            headers: to_light_headers(value.head.headers),
             body: value.body,
-            headers,
         })
     }
 }
 
-impl<T> TryFrom<http::Request<T>> for Request<T> {
-    type Error = TryFromRequestError;
-
-    fn try_from(value: http::Request<T>) -> Result<Self, Self::Error> {
+impl<T> From<http::Request<T>> for Request<T> {
+    fn from(value: http::Request<T>) -> Self {
         let (head, body) = value.into_parts();
-        Ok(Self {
+        Self {
             head: head.into(),
             body: Some(body),
-        })
+        }
     }
 }
 
@@ -452,13 +435,13 @@ impl From<http::response::Parts> for ResponseHead {
     }
 }
 
-/// LightRequest is a definition of request that allows these elements to be passed over
-/// to WASM or any other light weight runtime environment that do not require the larger
-/// content of a Request object that has more larger details.
+/// LightResponse is the [`LightRequest`] counterpart of a [`Response`],
+/// keeping its headers the same way.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LightResponse<T> {
     pub version: String,
-    pub status: String,
-    pub headers: HashMap<String, String>,
+    pub status: u16,
+    pub headers: Vec<(String, Vec<u8>)>,
     pub body: Option<T>,
 }
 
@@ -563,149 +546,167 @@ impl<T> Response<T> {
     field_method_as_mut!(body_mut, body, Option<T>);
 }
 
-pub enum TryFromLightResponseError {
-    FailedURLConversion(InvalidUri),
-    FailedMethodConversion(InvalidMethod),
-    FailedInvalidStatusCode(InvalidStatusCode),
-    FailedInvalidHeaderName(InvalidHeaderName),
-    FailedInvalidHeaderValue(InvalidHeaderValue),
-}
-
-impl From<InvalidHeaderName> for TryFromLightResponseError {
-    fn from(value: InvalidHeaderName) -> Self {
-        TryFromLightResponseError::FailedInvalidHeaderName(value)
-    }
-}
-
-impl From<InvalidHeaderValue> for TryFromLightResponseError {
-    fn from(value: InvalidHeaderValue) -> Self {
-        TryFromLightResponseError::FailedInvalidHeaderValue(value)
-    }
-}
-
-impl From<InvalidStatusCode> for TryFromLightResponseError {
-    fn from(value: InvalidStatusCode) -> Self {
-        TryFromLightResponseError::FailedInvalidStatusCode(value)
-    }
-}
-
-impl From<InvalidUri> for TryFromLightResponseError {
-    fn from(value: InvalidUri) -> Self {
-        TryFromLightResponseError::FailedURLConversion(value)
-    }
-}
-
-impl From<InvalidMethod> for TryFromLightResponseError {
-    fn from(value: InvalidMethod) -> Self {
-        TryFromLightResponseError::FailedMethodConversion(value)
-    }
-}
-
-fn get_version<'a>(text: &'a str) -> Version {
+fn get_version(text: &str) -> LightResult<Version> {
     match text {
-        "HTTP/0.9" => Version::HTTP_09,
-        "HTTP/1.0" => Version::HTTP_10,
-        "HTTP/1.1" => Version::HTTP_11,
-        "HTTP/2.0" => Version::HTTP_2,
-        "HTTP/3.0" => Version::HTTP_3,
-        _ => unreachable!(),
+        "HTTP/0.9" => Ok(Version::HTTP_09),
+        "HTTP/1.0" => Ok(Version::HTTP_10),
+        "HTTP/1.1" => Ok(Version::HTTP_11),
+        "HTTP/2.0" => Ok(Version::HTTP_2),
+        "HTTP/3.0" => Ok(Version::HTTP_3),
+        _ => Err(LightError::UnknownVersion(String::from(text))),
     }
 }
 
-fn get_version_text(ver: Version) -> String {
+fn get_version_text(ver: Version) -> LightResult<String> {
     match ver {
-        Version::HTTP_09 => String::from("HTTP/0.9"),
-        Version::HTTP_10 => String::from("HTTP/1.0"),
-        Version::HTTP_11 => String::from("HTTP/1.1"),
-        Version::HTTP_2 => String::from("HTTP/2.0"),
-        Version::HTTP_3 => String::from("HTTP/3.0"),
-        _ => unreachable!(),
+        Version::HTTP_09 => Ok(String::from("HTTP/0.9")),
+        Version::HTTP_10 => Ok(String::from("HTTP/1.0")),
+        Version::HTTP_11 => Ok(String::from("HTTP/1.1")),
+        Version::HTTP_2 => Ok(String::from("HTTP/2.0")),
+        Version::HTTP_3 => Ok(String::from("HTTP/3.0")),
+        _ => Err(LightError::UnknownVersion(format!("{:?}", ver))),
     }
 }
 
 impl<T> TryFrom<LightResponse<T>> for Response<T> {
-    type Error = TryFromLightResponseError;
-
-    /// This implementation of is unique in that it skips any headers that
-    /// to not mappable to a String for as the `HeaderMap` type actually
-    /// does some underlying logic to deal with non-ASCII character heders.
-    ///
-    /// Secondly the underlying body of the Response is also consumed by this
-    /// returned LightResponse.
-    ///
-    /// WARNING: Be warned this method will panic if the method or url are invalid.
-    fn try_from(value: LightResponse<T>) -> Result<Self, TryFromLightResponseError> {
-        let status = StatusCode::from_str(&value.status)?;
-        let version = get_version(&value.version);
-
-        let mut head = ResponseHead::new(status, version, HeaderMap::new(), Extensions::new());
-        for (key, value) in value.headers.iter() {
-            head.headers.insert(
-                http::HeaderName::from_str(key.as_ref())?,
-                http::HeaderValue::from_str(value.as_ref())?,
-            );
-        }
-        return Ok(Response::from(value.body, head));
-    }
-}
-
-pub enum TryFromResponseError {
-    NonASCIIHeaderValue(ToStrError),
-    InfallibleError(Infallible),
-}
+    type Error = LightError;
 
-impl From<ToStrError> for TryFromResponseError {
-    fn from(value: ToStrError) -> Self {
-        TryFromResponseError::NonASCIIHeaderValue(value)
-    }
-}
+    /// Builds the response from its light form, failing on any invalid
+    /// status, version or header.
+    fn try_from(value: LightResponse<T>) -> LightResult<Self> {
+        let status = StatusCode::from_u16(value.status)
+            .map_err(|_| LightError::InvalidStatusCode(value.status.to_string()))?;
+        let version = get_version(&value.version)?;
+        let headers = from_light_headers(value.headers)?;
 
-impl From<Infallible> for TryFromResponseError {
-    fn from(value: Infallible) -> Self {
-        TryFromResponseError::InfallibleError(value)
+        let head = ResponseHead::new(status, version, headers, Extensions::new());
+        Ok(Response::from(value.body, head))
     }
 }
 
 impl<T> TryFrom<Response<T>> for LightResponse<T> {
-    type Error = TryFromResponseError;
-
-    /// This implementation of is unique in that it skips any headers that
-    /// to not mappable to a String for as the `HeaderMap` type actually
-    /// does some underlying logic to deal with non-ASCII character heders.
-    ///
-    /// Secondly the underlying body of the Response is also consumed by this
-    /// returned LightResponse.
-    fn try_from(value: Response<T>) -> Result<Self, Self::Error> {
-        let mut headers = HashMap::new();
-
-        for (keyc, value) in value.head.headers {
-            match keyc {
-                Some(key) => {
-                    let key_name = key.to_string();
-                    let value_string = String::try_from(value.to_str()?)?;
-                    headers.entry(key_name).and_modify(|e| *e = value_string);
-                }
-                None => continue,
-            }
-        }
+    type Error = LightError;
 
+    /// Consumes the response and its body into its light form, failing only
+    /// on http versions it has no name for.
+    fn try_from(value: Response<T>) -> LightResult<Self> {
         Ok(Self {
-            version: get_version_text(value.head.version),
-            status: String::from(value.head.status.as_str()),
+            version: get_version_text(value.head.version)?,
+            status: value.head.status.as_u16(),
+            headers: to_light_headers(value.head.headers),
             body: value.body,
-            headers,
         })
     }
 }
 
-impl<T> TryFrom<http::Response<T>> for Response<T> {
-    type Error = TryFromResponseError;
-
-    fn try_from(value: http::Response<T>) -> Result<Self, Self::Error> {
+impl<T> From<http::Response<T>> for Response<T> {
+    fn from(value: http::Response<T>) -> Self {
         let (head, body) = value.into_parts();
-        Ok(Self {
+        Self {
             head: head.into(),
             body: Some(body),
-        })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header, HeaderValue, Method, StatusCode, Uri, Version};
+
+    use crate::{
+        LightError, LightRequest, LightResponse, Request, RequestHead, Response, ResponseHead,
+    };
+
+    #[test]
+    fn light_request_round_trips_headers() {
+        let mut head = RequestHead::new(
+            Method::POST,
+            Version::HTTP_11,
+            Uri::from_static("/todos/1?done=true"),
+            String::from("/todos/:id"),
+        );
+        head.headers
+            .append(header::ACCEPT, HeaderValue::from_static("text/html"));
+        head.headers
+            .append(header::ACCEPT, HeaderValue::from_static("application/json"));
+        head.headers.append(
+            "x-name",
+            HeaderValue::from_bytes(&[0x6e, 0xe9, 0x65]).unwrap(),
+        );
+
+        let light = LightRequest::try_from(Request::new(vec![1u8, 2], head)).unwrap();
+        assert_eq!(light.version, "HTTP/1.1");
+        assert_eq!(light.route_path, "/todos/:id");
+        assert_eq!(
+            light.headers,
+            vec![
+                (String::from("accept"), b"text/html".to_vec()),
+                (String::from("accept"), b"application/json".to_vec()),
+                (String::from("x-name"), vec![0x6e, 0xe9, 0x65]),
+            ]
+        );
+
+        let encoded = serde_json::to_string(&light).unwrap();
+        let decoded: LightRequest<Vec<u8>> = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, light);
+
+        let request = Request::try_from(decoded).unwrap();
+        assert_eq!(request.head().method(), Method::POST);
+        assert_eq!(request.headers().get_all(header::ACCEPT).iter().count(), 2);
+        assert_eq!(LightRequest::try_from(request).unwrap(), light);
+    }
+
+    #[test]
+    fn light_conversions_fail_instead_of_panicking() {
+        let light = LightRequest::<()> {
+            method: String::from("GET"),
+            url: String::from("/"),
+            version: String::from("HTTP/4.0"),
+            route_path: String::from("/"),
+            headers: vec![],
+            body: None,
+        };
+        assert_eq!(
+            Request::try_from(light.clone()).err(),
+            Some(LightError::UnknownVersion(String::from("HTTP/4.0")))
+        );
+
+        let light = LightRequest {
+            version: String::from("HTTP/1.1"),
+            headers: vec![(String::from("bad header"), vec![])],
+            ..light
+        };
+        assert!(matches!(
+            Request::try_from(light),
+            Err(LightError::InvalidHeaderName(_))
+        ));
+
+        let response = LightResponse::<()> {
+            version: String::from("HTTP/1.1"),
+            status: 1000,
+            headers: vec![],
+            body: None,
+        };
+        assert!(matches!(
+            Response::try_from(response),
+            Err(LightError::InvalidStatusCode(_))
+        ));
+
+        let mut head = ResponseHead::new(
+            StatusCode::CREATED,
+            Version::HTTP_2,
+            Default::default(),
+            Default::default(),
+        );
+        head.headers
+            .append(header::SET_COOKIE, HeaderValue::from_static("a=1"));
+        head.headers
+            .append(header::SET_COOKIE, HeaderValue::from_static("b=2"));
+        let light = LightResponse::try_from(Response::new(String::from("ok"), head)).unwrap();
+        assert_eq!(light.status, 201);
+        assert_eq!(light.headers.len(), 2);
+
+        let response = Response::try_from(light.clone()).unwrap();
+        assert_eq!(LightResponse::try_from(response).unwrap(), light);
     }
 }
@@ -0,0 +1,801 @@
+// Module implementing the layers commonly wrapped around handlers.
+
+use std::{
+    io::Write,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{write::GzEncoder, Compression as GzLevel};
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
+use tracing::{error, info};
+
+use crate::{
+    handler::{Handler, Layer},
+    Extensions, Request, Response, ResponseHead, Version,
+};
+
+/// status_response creates a response of the giving status without a body.
+pub fn status_response<U>(status: StatusCode) -> Response<U> {
+    Response::from_head(ResponseHead::new(
+        status,
+        Version::HTTP_11,
+        HeaderMap::new(),
+        Extensions::new(),
+    ))
+}
+
+/// Logging logs the method, path, status and duration of every request.
+#[derive(Clone, Debug, Default)]
+pub struct Logging;
+
+pub struct LoggingHandler<H> {
+    inner: H,
+}
+
+impl<H> Layer<H> for Logging {
+    type Handler = LoggingHandler<H>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        LoggingHandler { inner }
+    }
+}
+
+impl<T, U, H: Handler<T, U>> Handler<T, U> for LoggingHandler<H> {
+    fn call(&self, req: Request<T>) -> Response<U> {
+        let method = req.head.method.clone();
+        let path = String::from(req.url().path());
+        let id = req
+            .extensions()
+            .get::<RequestId>()
+            .map(|id| id.0.clone())
+            .unwrap_or_default();
+
+        let started = Instant::now();
+        let response = self.inner.call(req);
+        info!(
+            "{} {} -> {} in {:?} {}",
+            method,
+            path,
+            response.head().status(),
+            started.elapsed(),
+            id
+        );
+        response
+    }
+}
+
+pub const REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// RequestId is the id of a request, added to its extensions by the
+/// [`RequestIds`] layer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// RequestIds gives every request an id, taken from its `X-Request-Id`
+/// header or generated, and echoes it on the response.
+#[derive(Clone, Debug, Default)]
+pub struct RequestIds;
+
+pub struct RequestIdHandler<H> {
+    inner: H,
+}
+
+impl<H> Layer<H> for RequestIds {
+    type Handler = RequestIdHandler<H>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        RequestIdHandler { inner }
+    }
+}
+
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn generate_request_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    let count = REQUEST_COUNTER.fetch_add(1, Ordering::SeqCst);
+    format!("{:016x}-{:08x}", nanos, count)
+}
+
+impl<T, U, H: Handler<T, U>> Handler<T, U> for RequestIdHandler<H> {
+    fn call(&self, mut req: Request<T>) -> Response<U> {
+        let id = match req.headers().get(&REQUEST_ID).map(|id| id.to_str()) {
+            Some(Ok(id)) if !id.is_empty() => String::from(id),
+            _ => generate_request_id(),
+        };
+        let value = HeaderValue::from_str(&id).expect("request id is visible ascii");
+
+        req.head.headers.insert(REQUEST_ID, value.clone());
+        req.head.extensions.insert(RequestId(id));
+
+        let mut response = self.inner.call(req);
+        response.head_mut().headers.insert(REQUEST_ID, value);
+        response
+    }
+}
+
+/// Timeout answers with `503 Service Unavailable` if the handler did not
+/// respond within the duration.
+///
+/// The handler runs on its own thread which is left to finish in the
+/// background once timed out.
+#[derive(Clone, Debug)]
+pub struct Timeout(pub Duration);
+
+pub struct TimeoutHandler<H> {
+    inner: Arc<H>,
+    duration: Duration,
+}
+
+impl<H> Layer<H> for Timeout {
+    type Handler = TimeoutHandler<H>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        TimeoutHandler {
+            inner: Arc::new(inner),
+            duration: self.0,
+        }
+    }
+}
+
+impl<T, U, H> Handler<T, U> for TimeoutHandler<H>
+where
+    T: Send + 'static,
+    U: Send + 'static,
+    H: Handler<T, U>,
+{
+    fn call(&self, req: Request<T>) -> Response<U> {
+        let (sender, receiver) = mpsc::channel();
+        let inner = self.inner.clone();
+        let spawned = thread::Builder::new()
+            .name(String::from("ewe-routing-timeout"))
+            .spawn(move || {
+                _ = sender.send(inner.call(req));
+            });
+
+        if let Err(err) = spawned {
+            error!("Timeout failed to spawn handler thread: {}", err);
+            return status_response(StatusCode::SERVICE_UNAVAILABLE);
+        }
+
+        receiver
+            .recv_timeout(self.duration)
+            .unwrap_or_else(|_| status_response(StatusCode::SERVICE_UNAVAILABLE))
+    }
+}
+
+/// CatchPanic answers with `500 Internal Server Error` if the handler
+/// panicked instead of unwinding into the server.
+#[derive(Clone, Debug, Default)]
+pub struct CatchPanic;
+
+pub struct CatchPanicHandler<H> {
+    inner: H,
+}
+
+impl<H> Layer<H> for CatchPanic {
+    type Handler = CatchPanicHandler<H>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        CatchPanicHandler { inner }
+    }
+}
+
+impl<T, U, H: Handler<T, U>> Handler<T, U> for CatchPanicHandler<H> {
+    fn call(&self, req: Request<T>) -> Response<U> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.inner.call(req))) {
+            Ok(response) => response,
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| String::from("unknown panic"));
+                error!("Handler panicked: {}", message);
+                status_response(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
+
+/// RequireAuthorization answers with `401 Unauthorized` requests whose
+/// `Authorization` header is missing or refused by its check.
+#[derive(Clone)]
+pub struct RequireAuthorization {
+    scheme: &'static str,
+    check: Arc<dyn Fn(&str) -> bool + Send + Sync>,
+}
+
+impl RequireAuthorization {
+    /// bearer accepts requests carrying `Authorization: Bearer <token>`.
+    pub fn bearer(token: impl Into<String>) -> Self {
+        let token = token.into();
+        Self::custom("Bearer", move |credentials| {
+            constant_time_eq(credentials.as_bytes(), token.as_bytes())
+        })
+    }
+
+    /// custom accepts requests of the giving scheme whose credentials pass
+    /// the check.
+    pub fn custom(
+        scheme: &'static str,
+        check: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            scheme,
+            check: Arc::new(check),
+        }
+    }
+}
+
+pub struct AuthorizationHandler<H> {
+    inner: H,
+    auth: RequireAuthorization,
+}
+
+impl<H> Layer<H> for RequireAuthorization {
+    type Handler = AuthorizationHandler<H>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        AuthorizationHandler {
+            inner,
+            auth: self.clone(),
+        }
+    }
+}
+
+impl<T, U, H: Handler<T, U>> Handler<T, U> for AuthorizationHandler<H> {
+    fn call(&self, req: Request<T>) -> Response<U> {
+        let authorized = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .map(|(scheme, credentials)| {
+                scheme.eq_ignore_ascii_case(self.auth.scheme)
+                    && (self.auth.check)(credentials.trim())
+            })
+            .unwrap_or(false);
+
+        if authorized {
+            return self.inner.call(req);
+        }
+
+        let mut response = status_response(StatusCode::UNAUTHORIZED);
+        if let Ok(challenge) = HeaderValue::from_str(self.auth.scheme) {
+            response
+                .head_mut()
+                .headers
+                .insert(header::WWW_AUTHENTICATE, challenge);
+        }
+        response
+    }
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right.iter())
+            .fold(0, |diff, (l, r)| diff | (l ^ r))
+            == 0
+}
+
+/// Cors answers CORS preflight requests and adds the CORS headers to the
+/// responses of the origins it allows.
+#[derive(Clone, Debug, Default)]
+pub struct Cors {
+    any_origin: bool,
+    origins: Vec<String>,
+    methods: Vec<Method>,
+    headers: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_any_origin(mut self) -> Self {
+        self.any_origin = true;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origins.push(origin.into());
+        self
+    }
+
+    pub fn with_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods.extend(methods);
+        self
+    }
+
+    pub fn with_headers(mut self, headers: impl IntoIterator<Item = HeaderName>) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// with_credentials allows the listed origins to make credentialed
+    /// requests, it is never granted while any origin is allowed as that
+    /// would let every site read responses made with the user's cookies.
+    pub fn with_credentials(mut self) -> Self {
+        self.credentials = true;
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    fn allows(&self, origin: &str) -> bool {
+        self.any_origin || self.origins.iter().any(|allowed| allowed == origin)
+    }
+
+    /// decorate adds the headers shared by preflight and actual responses.
+    fn decorate(&self, origin: &HeaderValue, headers: &mut HeaderMap) {
+        if self.any_origin {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+        } else {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            headers.append(header::VARY, HeaderValue::from_static("origin"));
+        }
+        if self.credentials && !self.any_origin {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+}
+
+pub struct CorsHandler<H> {
+    inner: H,
+    cors: Cors,
+}
+
+impl<H> Layer<H> for Cors {
+    type Handler = CorsHandler<H>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        CorsHandler {
+            inner,
+            cors: self.clone(),
+        }
+    }
+}
+
+impl<T, U, H: Handler<T, U>> Handler<T, U> for CorsHandler<H> {
+    fn call(&self, req: Request<T>) -> Response<U> {
+        let Some(origin) = req.headers().get(header::ORIGIN).cloned() else {
+            return self.inner.call(req);
+        };
+        let allowed = origin
+            .to_str()
+            .map(|origin| self.cors.allows(origin))
+            .unwrap_or(false);
+
+        let preflight = req.head.method == Method::OPTIONS
+            && req
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+        if !preflight {
+            let mut response = self.inner.call(req);
+            if allowed {
+                self.cors
+                    .decorate(&origin, response.head_mut().headers_mut());
+            }
+            return response;
+        }
+
+        if !allowed {
+            return status_response(StatusCode::FORBIDDEN);
+        }
+
+        let mut response = status_response(StatusCode::NO_CONTENT);
+        let headers = response.head_mut().headers_mut();
+        self.cors.decorate(&origin, headers);
+
+        let methods: Vec<&str> = self
+            .cors
+            .methods
+            .iter()
+            .map(|method| method.as_str())
+            .collect();
+        if let Ok(methods) = HeaderValue::from_str(&methods.join(", ")) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        let allowed_headers: Vec<&str> =
+            self.cors.headers.iter().map(|name| name.as_str()).collect();
+        if let Ok(allowed_headers) = HeaderValue::from_str(&allowed_headers.join(", ")) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+        }
+        if let Some(max_age) = self.cors.max_age {
+            headers.insert(
+                header::ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(max_age.as_secs()),
+            );
+        }
+        response
+    }
+}
+
+/// Compression gzips response bodies of compressible content types for
+/// clients accepting the `gzip` encoding.
+#[derive(Clone, Debug)]
+pub struct Compression {
+    min_size: usize,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self { min_size: 256 }
+    }
+}
+
+impl Compression {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// with_min_size skips compressing bodies smaller than the size.
+    pub fn with_min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+}
+
+pub struct CompressionHandler<H> {
+    inner: H,
+    min_size: usize,
+}
+
+impl<H> Layer<H> for Compression {
+    type Handler = CompressionHandler<H>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        CompressionHandler {
+            inner,
+            min_size: self.min_size,
+        }
+    }
+}
+
+impl<T, U, H> Handler<T, U> for CompressionHandler<H>
+where
+    U: AsRef<[u8]> + From<Vec<u8>>,
+    H: Handler<T, U>,
+{
+    fn call(&self, req: Request<T>) -> Response<U> {
+        let accepts_gzip = accepts_gzip(req.headers());
+        let mut response = self.inner.call(req);
+
+        let headers = response.head().headers();
+        let compressible = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(is_compressible)
+            .unwrap_or(false);
+        let size = response.body().as_ref().map(|body| body.as_ref().len());
+        if !accepts_gzip
+            || !compressible
+            || headers.contains_key(header::CONTENT_ENCODING)
+            || headers.contains_key(header::CONTENT_RANGE)
+            || size.unwrap_or(0) < self.min_size
+        {
+            return response;
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), GzLevel::default());
+        let body = response
+            .body()
+            .as_ref()
+            .map(|body| body.as_ref())
+            .unwrap_or_default();
+        let compressed = match encoder.write_all(body).and_then(|_| encoder.finish()) {
+            Ok(compressed) => compressed,
+            Err(err) => {
+                error!("Compression failed, sending uncompressed: {}", err);
+                return response;
+            }
+        };
+
+        let headers = response.head_mut().headers_mut();
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        headers.append(header::VARY, HeaderValue::from_static("accept-encoding"));
+        headers.remove(header::CONTENT_LENGTH);
+        *response.body_mut() = Some(U::from(compressed));
+        response
+    }
+}
+
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or_default().trim();
+            let refused = parts.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    == Some(0.0)
+            });
+            (name.eq_ignore_ascii_case("gzip") || name == "*") && !refused
+        })
+}
+
+fn is_compressible(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime,
+            "application/json" | "application/javascript" | "application/xml" | "image/svg+xml"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, thread, time::Duration};
+
+    use flate2::read::GzDecoder;
+    use http::{header, HeaderValue, Method, StatusCode, Uri, Version};
+
+    use crate::{
+        handler::{BoxHandler, Handler, HandlerExt},
+        middleware::{
+            status_response, CatchPanic, Compression, Cors, RequestId, RequestIds,
+            RequireAuthorization, Timeout, REQUEST_ID,
+        },
+        router::Router,
+        Request, RequestHead, Response,
+    };
+
+    fn request(method: Method, path: &'static str) -> Request<Vec<u8>> {
+        Request::from_head(RequestHead::new(
+            method,
+            Version::HTTP_11,
+            Uri::from_static(path),
+            String::from(path),
+        ))
+    }
+
+    fn text(body: &str) -> Response<Vec<u8>> {
+        let mut response = status_response(StatusCode::OK);
+        response
+            .head_mut()
+            .headers
+            .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        *response.body_mut() = Some(body.as_bytes().to_vec());
+        response
+    }
+
+    fn hello(_: Request<Vec<u8>>) -> Response<Vec<u8>> {
+        text("hello")
+    }
+
+    #[test]
+    fn layers_apply_per_route_and_per_group() {
+        let auth = RequireAuthorization::bearer("secret");
+
+        let mut admin: Router<BoxHandler<Vec<u8>, Vec<u8>>> = Router::new();
+        admin.get("/stats", hello.boxed()).unwrap();
+        admin.post("/reset", hello.boxed()).unwrap();
+
+        let mut router: Router<BoxHandler<Vec<u8>, Vec<u8>>> = Router::new();
+        router.get("/", hello.boxed()).unwrap();
+        router
+            .get(
+                "/slow",
+                hello.layer(&Timeout(Duration::from_secs(1))).boxed(),
+            )
+            .unwrap();
+        router.nest("/admin", admin.layer(&auth).boxed()).unwrap();
+        let app = router.layer(&RequestIds);
+
+        let response = app.call(request(Method::GET, "/"));
+        assert_eq!(*response.head().status(), StatusCode::OK);
+        assert!(response.head().headers().contains_key(REQUEST_ID));
+
+        let response = app.call(request(Method::GET, "/admin/stats"));
+        assert_eq!(*response.head().status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.head().headers().get(header::WWW_AUTHENTICATE),
+            Some(&HeaderValue::from_static("Bearer"))
+        );
+
+        let mut req = request(Method::POST, "/admin/reset");
+        req.head.headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer secret"),
+        );
+        req.head
+            .headers
+            .insert(REQUEST_ID, HeaderValue::from_static("abc"));
+        let response = app.call(req);
+        assert_eq!(*response.head().status(), StatusCode::OK);
+        assert_eq!(
+            response.head().headers().get(REQUEST_ID),
+            Some(&HeaderValue::from_static("abc"))
+        );
+
+        let response = app.call(request(Method::GET, "/admin/missing"));
+        assert_eq!(*response.head().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn request_ids_are_added_to_the_request() {
+        let handler = (|req: Request<Vec<u8>>| {
+            let id = req.extensions().get::<RequestId>().cloned().unwrap();
+            text(&id.0)
+        })
+        .layer(&RequestIds);
+
+        let first = handler.call(request(Method::GET, "/"));
+        let second = handler.call(request(Method::GET, "/"));
+        assert_ne!(first.body(), second.body());
+        assert_eq!(
+            first
+                .head()
+                .headers()
+                .get(REQUEST_ID)
+                .map(|id| id.as_bytes()),
+            first.body().as_deref()
+        );
+    }
+
+    #[test]
+    fn timeouts_and_panics_become_error_responses() {
+        let slow = (|_: Request<Vec<u8>>| {
+            thread::sleep(Duration::from_millis(200));
+            text("late")
+        })
+        .layer(&Timeout(Duration::from_millis(20)));
+        assert_eq!(
+            *slow.call(request(Method::GET, "/")).head().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let panicking =
+            (|_: Request<Vec<u8>>| -> Response<Vec<u8>> { panic!("boom") }).layer(&CatchPanic);
+        assert_eq!(
+            *panicking.call(request(Method::GET, "/")).head().status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[test]
+    fn cors_answers_preflights_and_decorates_responses() {
+        let handler = hello.layer(
+            &Cors::new()
+                .with_origin("https://ewe.dev")
+                .with_methods([Method::GET, Method::POST])
+                .with_headers([header::CONTENT_TYPE])
+                .with_max_age(Duration::from_secs(60)),
+        );
+
+        let mut preflight = request(Method::OPTIONS, "/");
+        preflight
+            .head
+            .headers
+            .insert(header::ORIGIN, HeaderValue::from_static("https://ewe.dev"));
+        preflight.head.headers.insert(
+            header::ACCESS_CONTROL_REQUEST_METHOD,
+            HeaderValue::from_static("POST"),
+        );
+        let response = handler.call(preflight);
+        let headers = response.head().headers();
+        assert_eq!(*response.head().status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some(&HeaderValue::from_static("https://ewe.dev"))
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_METHODS),
+            Some(&HeaderValue::from_static("GET, POST"))
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_MAX_AGE),
+            Some(&HeaderValue::from_static("60"))
+        );
+
+        let mut other = request(Method::GET, "/");
+        other
+            .head
+            .headers
+            .insert(header::ORIGIN, HeaderValue::from_static("https://evil.dev"));
+        let response = handler.call(other);
+        assert_eq!(*response.head().status(), StatusCode::OK);
+        assert!(!response
+            .head()
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn cors_never_allows_credentials_for_any_origin() {
+        let handler = hello.layer(&Cors::new().with_any_origin().with_credentials());
+
+        let mut req = request(Method::GET, "/");
+        req.head
+            .headers
+            .insert(header::ORIGIN, HeaderValue::from_static("https://evil.dev"));
+        let response = handler.call(req);
+        let headers = response.head().headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some(&HeaderValue::from_static("*"))
+        );
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+        let handler = hello.layer(
+            &Cors::new()
+                .with_origin("https://ewe.dev")
+                .with_credentials(),
+        );
+        let mut req = request(Method::GET, "/");
+        req.head
+            .headers
+            .insert(header::ORIGIN, HeaderValue::from_static("https://ewe.dev"));
+        let response = handler.call(req);
+        let headers = response.head().headers();
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some(&HeaderValue::from_static("https://ewe.dev"))
+        );
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            Some(&HeaderValue::from_static("true"))
+        );
+    }
+
+    #[test]
+    fn compresses_bodies_for_clients_accepting_gzip() {
+        let body = "hello ".repeat(100);
+        let expected = body.clone();
+        let handler = (move |_: Request<Vec<u8>>| text(&body)).layer(&Compression::new());
+
+        let response = handler.call(request(Method::GET, "/"));
+        assert!(!response
+            .head()
+            .headers()
+            .contains_key(header::CONTENT_ENCODING));
+
+        let mut req = request(Method::GET, "/");
+        req.head.headers.insert(
+            header::ACCEPT_ENCODING,
+            HeaderValue::from_static("br;q=1, gzip;q=0.5"),
+        );
+        let response = handler.call(req);
+        assert_eq!(
+            response.head().headers().get(header::CONTENT_ENCODING),
+            Some(&HeaderValue::from_static("gzip"))
+        );
+
+        let mut decoded = String::new();
+        GzDecoder::new(response.body().as_deref().unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, expected);
+    }
+}
@@ -0,0 +1,357 @@
+// Module implementing response builders and the negotiation of a response's
+// format from the request's Accept header.
+
+use std::{fs, io, path::Path};
+
+use http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri};
+use serde::Serialize;
+
+use crate::{middleware::status_response, Response};
+
+impl<T> Response<T> {
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.head.status = status;
+        self
+    }
+
+    /// with_header sets the header, replacing any values it had.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.head.headers.insert(name, value);
+        self
+    }
+}
+
+impl<T: From<Vec<u8>>> Response<T> {
+    /// bytes returns a response with the body of the giving content type,
+    /// setting its `Content-Type` and `Content-Length`.
+    pub fn bytes(status: StatusCode, content_type: &str, body: Vec<u8>) -> Self {
+        let mut response = status_response(status);
+        let headers = &mut response.head.headers;
+        if let Ok(value) = HeaderValue::from_str(content_type) {
+            headers.insert(header::CONTENT_TYPE, value);
+        }
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+        response.body = Some(T::from(body));
+        response
+    }
+
+    /// json returns a `200 OK` response with the value serialized as JSON,
+    /// or a `500 Internal Server Error` if it fails to serialize.
+    pub fn json<V: Serialize + ?Sized>(value: &V) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self::bytes(StatusCode::OK, Format::Json.content_type(), body),
+            Err(err) => {
+                tracing::error!("failed to serialize the response body: {}", err);
+                Self::text("Internal Server Error").with_status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+
+    pub fn html(body: impl Into<String>) -> Self {
+        let body: String = body.into();
+        Self::bytes(
+            StatusCode::OK,
+            Format::Html.content_type(),
+            body.into_bytes(),
+        )
+    }
+
+    pub fn text(body: impl Into<String>) -> Self {
+        let body: String = body.into();
+        Self::bytes(
+            StatusCode::OK,
+            Format::Text.content_type(),
+            body.into_bytes(),
+        )
+    }
+
+    /// redirect returns a `303 See Other` response to the location, use
+    /// [`Response::with_status`] for the other redirections.
+    pub fn redirect(location: &Uri) -> Self {
+        let mut response = status_response(StatusCode::SEE_OTHER);
+        let headers = &mut response.head.headers;
+        if let Ok(value) = HeaderValue::from_str(&location.to_string()) {
+            headers.insert(header::LOCATION, value);
+        }
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(0));
+        response
+    }
+
+    pub fn not_found() -> Self {
+        Self::text("Not Found").with_status(StatusCode::NOT_FOUND)
+    }
+
+    /// file returns a response with the content of the file, its
+    /// `Content-Type` guessed from its extension.
+    ///
+    /// See [`crate::files::StaticFiles`] for serving directories.
+    pub fn file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let body = fs::read(path)?;
+        Ok(Self::bytes(StatusCode::OK, &guess_content_type(path), body))
+    }
+}
+
+/// guess_content_type returns the content type of the file from its
+/// extension, falling back to `application/octet-stream`.
+pub(crate) fn guess_content_type(path: &Path) -> String {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    match mime.type_() == mime_guess::mime::TEXT
+        || mime.subtype() == mime_guess::mime::JAVASCRIPT
+        || mime.subtype() == mime_guess::mime::JSON
+    {
+        true => format!("{}; charset=utf-8", mime.essence_str()),
+        false => mime.essence_str().to_string(),
+    }
+}
+
+/// Format is a representation a response can be negotiated into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Html,
+    Text,
+}
+
+impl Format {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Html => "text/html; charset=utf-8",
+            Format::Text => "text/plain; charset=utf-8",
+        }
+    }
+
+    fn media_type(&self) -> (&'static str, &'static str) {
+        match self {
+            Format::Json => ("application", "json"),
+            Format::Html => ("text", "html"),
+            Format::Text => ("text", "plain"),
+        }
+    }
+
+    /// quality returns the quality the accepted ranges give the format,
+    /// from the most specific range matching it.
+    fn quality(&self, accepted: &[(String, String, f32)]) -> Option<f32> {
+        let (kind, subtype) = self.media_type();
+        accepted
+            .iter()
+            .filter_map(|(range_kind, range_subtype, quality)| {
+                let specificity = match (range_kind.as_str(), range_subtype.as_str()) {
+                    (k, s) if k == kind && s == subtype => 2,
+                    (k, "*") if k == kind => 1,
+                    ("*", "*") => 0,
+                    _ => return None,
+                };
+                Some((specificity, *quality))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality)
+    }
+}
+
+/// parse_accept returns the media ranges of the `Accept` headers with
+/// their quality, skipping malformed ones.
+fn parse_accept(headers: &HeaderMap) -> Vec<(String, String, f32)> {
+    let mut accepted = Vec::new();
+    for value in headers.get_all(header::ACCEPT) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for range in value.split(',') {
+            let mut parts = range.split(';');
+            let Some((kind, subtype)) = parts.next().and_then(|media| media.trim().split_once('/'))
+            else {
+                continue;
+            };
+
+            let mut quality = 1.0;
+            for param in parts {
+                if let Some(("q", value)) = param.trim().split_once('=') {
+                    quality = value.trim().parse().unwrap_or(0.0);
+                }
+            }
+            accepted.push((
+                kind.to_ascii_lowercase(),
+                subtype.to_ascii_lowercase(),
+                quality,
+            ));
+        }
+    }
+    accepted
+}
+
+/// negotiate picks the offered format the request accepts best, preferring
+/// the earlier offers on ties. Requests without an `Accept` header accept
+/// the first offer.
+pub fn negotiate(headers: &HeaderMap, offered: &[Format]) -> Option<Format> {
+    let accepted = parse_accept(headers);
+    if accepted.is_empty() {
+        return offered.first().copied();
+    }
+
+    let mut best: Option<(Format, f32)> = None;
+    for format in offered {
+        match format.quality(&accepted) {
+            Some(quality) if quality > 0.0 && best.map_or(true, |(_, q)| quality > q) => {
+                best = Some((*format, quality));
+            }
+            _ => continue,
+        }
+    }
+    best.map(|(format, _)| format)
+}
+
+type Render<'a, T> = Box<dyn FnOnce() -> Response<T> + 'a>;
+
+/// Negotiate builds the response in the format the request accepts best
+/// among the offered ones, rendering only the picked one and answering
+/// `406 Not Acceptable` if none is. Its responses vary by `Accept`.
+///
+/// # Example:
+///
+/// ```ignore
+/// let response: Response<Vec<u8>> = Negotiate::new(req.headers())
+///     .json(&todo)
+///     .html(|| templates.render("todo.html", &todo))
+///     .text(|| todo.title.clone())
+///     .respond();
+/// ```
+pub struct Negotiate<'a, T> {
+    headers: &'a HeaderMap,
+    offers: Vec<(Format, Render<'a, T>)>,
+}
+
+impl<'a, T: From<Vec<u8>> + 'a> Negotiate<'a, T> {
+    pub fn new(headers: &'a HeaderMap) -> Self {
+        Self {
+            headers,
+            offers: Vec::new(),
+        }
+    }
+
+    pub fn json<V: Serialize + ?Sized>(mut self, value: &'a V) -> Self {
+        self.offers
+            .push((Format::Json, Box::new(move || Response::json(value))));
+        self
+    }
+
+    pub fn html<S: Into<String>>(mut self, render: impl FnOnce() -> S + 'a) -> Self {
+        self.offers
+            .push((Format::Html, Box::new(move || Response::html(render()))));
+        self
+    }
+
+    pub fn text<S: Into<String>>(mut self, render: impl FnOnce() -> S + 'a) -> Self {
+        self.offers
+            .push((Format::Text, Box::new(move || Response::text(render()))));
+        self
+    }
+
+    pub fn respond(self) -> Response<T> {
+        let formats: Vec<Format> = self.offers.iter().map(|(format, _)| *format).collect();
+        let picked = negotiate(self.headers, &formats);
+
+        let mut response = match self
+            .offers
+            .into_iter()
+            .find(|(format, _)| Some(*format) == picked)
+        {
+            Some((_, render)) => render(),
+            None => Response::text("Not Acceptable").with_status(StatusCode::NOT_ACCEPTABLE),
+        };
+        response
+            .head
+            .headers
+            .append(header::VARY, HeaderValue::from_static("accept"));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header, HeaderMap, HeaderValue, StatusCode, Uri};
+
+    use crate::{
+        respond::{negotiate, Format, Negotiate},
+        Response,
+    };
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn builds_responses_with_content_headers() {
+        let response: Response<Vec<u8>> = Response::json(&serde_json::json!({ "id": 1 }));
+        assert_eq!(*response.head().status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "8");
+        assert_eq!(response.body().as_deref(), Some(&b"{\"id\":1}"[..]));
+
+        let response: Response<Vec<u8>> = Response::html("<p>hi</p>");
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/html; charset=utf-8"
+        );
+
+        let response: Response<Vec<u8>> =
+            Response::redirect(&Uri::from_static("/todos/1")).with_status(StatusCode::FOUND);
+        assert_eq!(*response.head().status(), StatusCode::FOUND);
+        assert_eq!(response.headers()[header::LOCATION], "/todos/1");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "0");
+
+        let response: Response<Vec<u8>> = Response::not_found();
+        assert_eq!(*response.head().status(), StatusCode::NOT_FOUND);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("site.css");
+        std::fs::write(&path, "p {}").unwrap();
+        let response: Response<Vec<u8>> = Response::file(&path).unwrap();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/css; charset=utf-8"
+        );
+        assert!(Response::<Vec<u8>>::file(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn negotiates_formats_from_accept() {
+        let offered = [Format::Json, Format::Html, Format::Text];
+
+        assert_eq!(negotiate(&HeaderMap::new(), &offered), Some(Format::Json));
+        assert_eq!(
+            negotiate(
+                &accept("text/html,application/xhtml+xml,*/*;q=0.8"),
+                &offered
+            ),
+            Some(Format::Html)
+        );
+        assert_eq!(
+            negotiate(&accept("text/*;q=0.5, application/json;q=0.4"), &offered),
+            Some(Format::Html)
+        );
+        assert_eq!(
+            negotiate(&accept("text/*, text/html;q=0"), &offered),
+            Some(Format::Text)
+        );
+        assert_eq!(negotiate(&accept("image/png"), &offered), None);
+
+        let todo = serde_json::json!({ "title": "write" });
+        let response: Response<Vec<u8>> = Negotiate::new(&accept("text/plain"))
+            .json(&todo)
+            .text(|| "write")
+            .html(|| -> String { unreachable!("only the picked format renders") })
+            .respond();
+        assert_eq!(response.body().as_deref(), Some(&b"write"[..]));
+        assert_eq!(response.headers()[header::VARY], "accept");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "5");
+
+        let response: Response<Vec<u8>> =
+            Negotiate::new(&accept("image/png")).json(&todo).respond();
+        assert_eq!(*response.head().status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(response.headers()[header::VARY], "accept");
+    }
+}
@@ -0,0 +1,822 @@
+// Module implementing the routing table matching request paths to handlers.
+
+use std::{borrow::Cow, fmt::Display};
+
+use http::{Method, StatusCode, Uri};
+use percent_encoding::percent_decode_str;
+
+use crate::{handler::Layer, urls::Urls, Request, RequestHead};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RouterError {
+    #[error("route pattern {0:?} is invalid: {1}")]
+    InvalidPattern(String, String),
+
+    #[error("route {method} {pattern:?} conflicts with the registered {existing:?}")]
+    Conflict {
+        method: Method,
+        pattern: String,
+        existing: String,
+    },
+
+    #[error("no route matches {0:?}")]
+    NotFound(String),
+
+    #[error("{method} is not allowed for {path:?}")]
+    MethodNotAllowed {
+        method: Method,
+        path: String,
+        allowed: Vec<Method>,
+    },
+
+    #[error("route name {name:?} for {pattern:?} is already given to {existing:?}")]
+    DuplicateName {
+        name: String,
+        pattern: String,
+        existing: String,
+    },
+
+    #[error("no route is named {0:?}")]
+    UnknownRoute(String),
+
+    #[error("route {route:?} requires the parameter {param:?}")]
+    MissingParam { route: String, param: String },
+
+    #[error("route {route:?} has no parameter {param:?}")]
+    UnexpectedParam { route: String, param: String },
+}
+
+impl RouterError {
+    /// status returns the response status matching the error.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            RouterError::NotFound(_) => StatusCode::NOT_FOUND,
+            RouterError::MethodNotAllowed { .. } => StatusCode::METHOD_NOT_ALLOWED,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+pub type RouterResult<T> = std::result::Result<T, RouterError>;
+
+/// Params are the named parameters extracted from a request path in the
+/// order they appear in the route pattern, decoded from their
+/// percent-encoding. The [`Router`] adds them to the
+/// [`RequestHead::extensions`] of the requests it routes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl FromIterator<(String, String)> for Params {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+enum Segment<'a> {
+    Static(&'a str),
+    Param(&'a str),
+    Wildcard(&'a str),
+}
+
+/// parse_pattern parses the route pattern into the pieces of each path it
+/// matches, one for every number of its optional segments present.
+///
+/// Segments are static text, `:name` matching a single segment or
+/// `*name` matching the rest of the path, which must come last. Segments
+/// suffixed with `?` are optional and must follow all required ones.
+pub(crate) fn parse_pattern(pattern: &str) -> RouterResult<Vec<Vec<Piece>>> {
+    let invalid = |reason: &str| RouterError::InvalidPattern(pattern.into(), reason.into());
+
+    let Some(path) = pattern.strip_prefix('/') else {
+        return Err(invalid("must start with '/'"));
+    };
+
+    let raw_segments: Vec<&str> = path.split('/').collect();
+    let mut segments = Vec::with_capacity(raw_segments.len());
+    let mut names: Vec<&str> = Vec::new();
+    for (index, raw) in raw_segments.iter().enumerate() {
+        let (raw, optional) = match raw.strip_suffix('?') {
+            Some(raw) => (raw, true),
+            None => (*raw, false),
+        };
+
+        let segment = if let Some(name) = raw.strip_prefix(':') {
+            Segment::Param(name)
+        } else if let Some(name) = raw.strip_prefix('*') {
+            if index != raw_segments.len() - 1 {
+                return Err(invalid("wildcards must be the last segment"));
+            }
+            Segment::Wildcard(name)
+        } else if raw.contains([':', '*', '?']) {
+            return Err(invalid("parameters must span a whole segment"));
+        } else {
+            if raw.is_empty() && index != raw_segments.len() - 1 {
+                return Err(invalid("empty segments are not allowed"));
+            }
+            Segment::Static(raw)
+        };
+
+        if let Segment::Param(name) | Segment::Wildcard(name) = segment {
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(invalid("parameter names must be alphanumeric"));
+            }
+            if names.contains(&name) {
+                return Err(invalid("parameter names must be unique"));
+            }
+            names.push(name);
+        }
+
+        segments.push((segment, optional));
+    }
+
+    let required = segments
+        .iter()
+        .take_while(|(_, optional)| !*optional)
+        .count();
+    if segments[required..].iter().any(|(_, optional)| !*optional) {
+        return Err(invalid("optional segments must come last"));
+    }
+
+    let mut variants = Vec::with_capacity(segments.len() - required + 1);
+    for included in required..=segments.len() {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        for (segment, _) in segments[..included].iter() {
+            text.push('/');
+            match segment {
+                Segment::Static(value) => text.push_str(value),
+                Segment::Param(name) => {
+                    pieces.push(Piece::Static(std::mem::take(&mut text)));
+                    pieces.push(Piece::Param(name.to_string()));
+                }
+                Segment::Wildcard(name) => {
+                    pieces.push(Piece::Static(std::mem::take(&mut text)));
+                    pieces.push(Piece::Wildcard(name.to_string()));
+                }
+            }
+        }
+
+        if pieces.is_empty() && text.is_empty() {
+            text.push('/');
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Static(text));
+        }
+        variants.push(pieces);
+    }
+
+    Ok(variants)
+}
+
+enum Token<'a> {
+    Char(char),
+    Param(&'a str),
+    Wildcard(&'a str),
+}
+
+fn tokens(pieces: &[Piece]) -> Vec<Token<'_>> {
+    pieces
+        .iter()
+        .flat_map(|piece| -> Vec<Token<'_>> {
+            match piece {
+                Piece::Static(text) => text.chars().map(Token::Char).collect(),
+                Piece::Param(name) => vec![Token::Param(name)],
+                Piece::Wildcard(name) => vec![Token::Wildcard(name)],
+            }
+        })
+        .collect()
+}
+
+/// conflicts returns true if the paths cannot share the routing tree, either
+/// because they match the same requests or because they name the parameter
+/// at the same position differently.
+fn conflicts(left: &[Piece], right: &[Piece]) -> bool {
+    let (left, right) = (tokens(left), tokens(right));
+    for (l, r) in left.iter().zip(right.iter()) {
+        match (l, r) {
+            (Token::Char(a), Token::Char(b)) if a == b => continue,
+            (Token::Param(a), Token::Param(b)) | (Token::Wildcard(a), Token::Wildcard(b)) => {
+                if a == b {
+                    continue;
+                }
+                return true;
+            }
+            _ => return false,
+        }
+    }
+    left.len() == right.len()
+}
+
+struct Route {
+    method: Method,
+    pattern: String,
+    pieces: Vec<Piece>,
+    handler: usize,
+}
+
+/// Node is a node of the radix tree, its static children never share their
+/// first character.
+#[derive(Default)]
+struct Node {
+    prefix: String,
+    statics: Vec<Node>,
+    param: Option<(String, Box<Node>)>,
+    wildcard: Option<(String, Box<Node>)>,
+    endpoints: Vec<(Method, usize)>,
+}
+
+impl Node {
+    fn insert(&mut self, pieces: &[Piece], method: Method, route: usize) {
+        match pieces.split_first() {
+            None => self.endpoints.push((method, route)),
+            Some((Piece::Static(text), rest)) => self.insert_static(text, rest, method, route),
+            Some((Piece::Param(name), rest)) => self
+                .param
+                .get_or_insert_with(|| (name.clone(), Box::default()))
+                .1
+                .insert(rest, method, route),
+            Some((Piece::Wildcard(name), rest)) => self
+                .wildcard
+                .get_or_insert_with(|| (name.clone(), Box::default()))
+                .1
+                .insert(rest, method, route),
+        }
+    }
+
+    fn insert_static(&mut self, text: &str, rest: &[Piece], method: Method, route: usize) {
+        let first = text.chars().next();
+        let Some(child) = self
+            .statics
+            .iter_mut()
+            .find(|child| child.prefix.chars().next() == first)
+        else {
+            let mut child = Node {
+                prefix: text.to_string(),
+                ..Node::default()
+            };
+            child.insert(rest, method, route);
+            self.statics.push(child);
+            return;
+        };
+
+        let common: usize = child
+            .prefix
+            .chars()
+            .zip(text.chars())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+
+        if common < child.prefix.len() {
+            child.split(common);
+        }
+        if common < text.len() {
+            child.insert_static(&text[common..], rest, method, route);
+        } else {
+            child.insert(rest, method, route);
+        }
+    }
+
+    fn split(&mut self, at: usize) {
+        let tail = Node {
+            prefix: self.prefix.split_off(at),
+            statics: std::mem::take(&mut self.statics),
+            param: self.param.take(),
+            wildcard: self.wildcard.take(),
+            endpoints: std::mem::take(&mut self.endpoints),
+        };
+        self.statics.push(tail);
+    }
+
+    /// find walks the tree for the path, preferring static matches over
+    /// parameters and parameters over wildcards.
+    fn find(&self, path: &str, params: &mut Vec<(String, String)>) -> Option<&Node> {
+        if path.is_empty() {
+            return (!self.endpoints.is_empty()).then_some(self);
+        }
+
+        for child in self.statics.iter() {
+            if let Some(rest) = path.strip_prefix(child.prefix.as_str()) {
+                if let Some(found) = child.find(rest, params) {
+                    return Some(found);
+                }
+            }
+        }
+
+        if let Some((name, node)) = &self.param {
+            let end = path.find('/').unwrap_or(path.len());
+            if end > 0 {
+                params.push((name.clone(), decode(&path[..end])));
+                if let Some(found) = node.find(&path[end..], params) {
+                    return Some(found);
+                }
+                params.pop();
+            }
+        }
+
+        if let Some((name, node)) = &self.wildcard {
+            if !node.endpoints.is_empty() {
+                params.push((name.clone(), decode(path)));
+                return Some(node);
+            }
+        }
+
+        None
+    }
+}
+
+fn decode(value: &str) -> String {
+    match percent_decode_str(value).decode_utf8_lossy() {
+        Cow::Borrowed(value) => value.to_string(),
+        Cow::Owned(value) => value,
+    }
+}
+
+/// Match is the result of a successful route lookup.
+#[derive(Debug)]
+pub struct Match<'a, H> {
+    pub handler: &'a H,
+    pub params: Params,
+    pub pattern: &'a str,
+}
+
+/// Router is a routing table matching request methods and paths to
+/// handlers of type `H` using a radix tree.
+///
+/// Routes are registered with patterns such as `/todos/:id`,
+/// `/assets/*path` or `/archive/:year?`, conflicting routes are refused at
+/// registration instead of shadowing each other at runtime.
+///
+/// # Example:
+///
+/// ```ignore
+/// let mut router = Router::new();
+/// router.get("/todos/:id", "show")?;
+///
+/// let found = router.at(&Method::GET, "/todos/1")?;
+/// assert_eq!(found.params.get("id"), Some("1"));
+/// ```
+pub struct Router<H> {
+    root: Node,
+    routes: Vec<Route>,
+    handlers: Vec<H>,
+    urls: Urls,
+}
+
+impl<H> Default for Router<H> {
+    fn default() -> Self {
+        Self {
+            root: Node::default(),
+            routes: Vec::new(),
+            handlers: Vec::new(),
+            urls: Urls::new(),
+        }
+    }
+}
+
+impl<H> Router<H> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// route registers the handler for requests of the giving method whose
+    /// path matches the pattern.
+    pub fn route(&mut self, method: Method, pattern: &str, handler: H) -> RouterResult<()> {
+        let variants = parse_pattern(pattern)?;
+        for pieces in variants.iter() {
+            if let Some(existing) = self.routes.iter().find(|route| {
+                conflicts(&route.pieces, pieces)
+                    && (route.method == method || route.pieces != *pieces)
+            }) {
+                return Err(RouterError::Conflict {
+                    method,
+                    pattern: pattern.into(),
+                    existing: existing.pattern.clone(),
+                });
+            }
+        }
+
+        let handler_index = self.handlers.len();
+        self.handlers.push(handler);
+        for pieces in variants {
+            let route_index = self.routes.len();
+            self.root.insert(&pieces, method.clone(), route_index);
+            self.routes.push(Route {
+                method: method.clone(),
+                pattern: pattern.into(),
+                pieces,
+                handler: handler_index,
+            });
+        }
+        Ok(())
+    }
+
+    /// route_named registers the route like [`Router::route`] under the
+    /// name, letting [`Router::url_for`] build its urls.
+    pub fn route_named(
+        &mut self,
+        name: &str,
+        method: Method,
+        pattern: &str,
+        handler: H,
+    ) -> RouterResult<()> {
+        if let Some(existing) = self
+            .urls
+            .pattern(name)
+            .filter(|existing| *existing != pattern)
+        {
+            return Err(RouterError::DuplicateName {
+                name: name.into(),
+                pattern: pattern.into(),
+                existing: existing.into(),
+            });
+        }
+        self.route(method, pattern, handler)?;
+        self.urls.insert(name, pattern)
+    }
+
+    pub fn get(&mut self, pattern: &str, handler: H) -> RouterResult<()> {
+        self.route(Method::GET, pattern, handler)
+    }
+
+    pub fn post(&mut self, pattern: &str, handler: H) -> RouterResult<()> {
+        self.route(Method::POST, pattern, handler)
+    }
+
+    pub fn put(&mut self, pattern: &str, handler: H) -> RouterResult<()> {
+        self.route(Method::PUT, pattern, handler)
+    }
+
+    pub fn patch(&mut self, pattern: &str, handler: H) -> RouterResult<()> {
+        self.route(Method::PATCH, pattern, handler)
+    }
+
+    pub fn delete(&mut self, pattern: &str, handler: H) -> RouterResult<()> {
+        self.route(Method::DELETE, pattern, handler)
+    }
+
+    /// at looks up the handler for the method and path, `HEAD` requests
+    /// fall back to the `GET` handler of the route.
+    pub fn at(&self, method: &Method, path: &str) -> RouterResult<Match<'_, H>> {
+        let mut params = Vec::new();
+        let Some(node) = self.root.find(path, &mut params) else {
+            return Err(RouterError::NotFound(path.into()));
+        };
+
+        let endpoint = node
+            .endpoints
+            .iter()
+            .find(|(allowed, _)| allowed == method)
+            .or_else(|| {
+                (method == Method::HEAD)
+                    .then(|| {
+                        node.endpoints
+                            .iter()
+                            .find(|(allowed, _)| allowed == Method::GET)
+                    })
+                    .flatten()
+            });
+
+        let Some((_, route_index)) = endpoint else {
+            return Err(RouterError::MethodNotAllowed {
+                method: method.clone(),
+                path: path.into(),
+                allowed: node
+                    .endpoints
+                    .iter()
+                    .map(|(allowed, _)| allowed.clone())
+                    .collect(),
+            });
+        };
+
+        let route = &self.routes[*route_index];
+        Ok(Match {
+            handler: &self.handlers[route.handler],
+            params: Params(params),
+            pattern: &route.pattern,
+        })
+    }
+
+    /// route_head looks up the handler for the request, adding the
+    /// extracted [`Params`] to its extensions and setting its
+    /// [`RequestHead::route_path`] to the matched pattern.
+    pub fn route_head(&self, head: &mut RequestHead) -> RouterResult<&H> {
+        let found = self.at(&head.method, head.target.path())?;
+        head.route_path = found.pattern.to_string();
+        head.extensions.insert(found.params);
+        Ok(found.handler)
+    }
+
+    /// route_request looks up the handler for the request, see
+    /// [`Router::route_head`].
+    pub fn route_request<T>(&self, req: &mut Request<T>) -> RouterResult<&H> {
+        self.route_head(&mut req.head)
+    }
+
+    /// patterns lists the registered route patterns with their method.
+    pub fn patterns(&self) -> Vec<(&Method, &str)> {
+        let mut patterns: Vec<(&Method, &str)> = Vec::new();
+        for route in self.routes.iter() {
+            if !patterns
+                .iter()
+                .any(|(method, pattern)| **method == route.method && *pattern == route.pattern)
+            {
+                patterns.push((&route.method, &route.pattern));
+            }
+        }
+        patterns
+    }
+
+    /// urls returns the named routes of the router.
+    pub fn urls(&self) -> &Urls {
+        &self.urls
+    }
+
+    /// url_for builds the url of the named route, see [`Urls::url_for`].
+    pub fn url_for<I, K, V>(&self, name: &str, params: I) -> RouterResult<Uri>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Display,
+    {
+        self.urls.url_for(name, params)
+    }
+
+    /// layer wraps every handler of the router with the giving
+    /// [`crate::handler::Layer`], together with [`Router::nest`] this
+    /// applies layers to a group of routes.
+    pub fn layer<L: Layer<H>>(self, layer: &L) -> Router<L::Handler> {
+        self.map(|handler| layer.layer(handler))
+    }
+
+    /// map replaces every handler of the router with the one returned for it.
+    pub fn map<H2>(self, f: impl FnMut(H) -> H2) -> Router<H2> {
+        Router {
+            root: self.root,
+            routes: self.routes,
+            handlers: self.handlers.into_iter().map(f).collect(),
+            urls: self.urls,
+        }
+    }
+
+    /// nest registers the routes of the giving router under the prefix,
+    /// e.g `/todos/:id` nested under `/api` is routed as `/api/todos/:id`.
+    pub fn nest(&mut self, prefix: &str, router: Router<H>) -> RouterResult<()> {
+        if !prefix.starts_with('/') || prefix.ends_with('/') {
+            return Err(RouterError::InvalidPattern(
+                prefix.into(),
+                String::from("prefixes must start but not end with '/'"),
+            ));
+        }
+
+        let mut handlers: Vec<Option<H>> = router.handlers.into_iter().map(Some).collect();
+        for route in router.routes {
+            // optional segments register a route per variant, all sharing
+            // one handler.
+            let Some(handler) = handlers[route.handler].take() else {
+                continue;
+            };
+
+            let pattern = match route.pattern.as_str() {
+                "/" => String::from(prefix),
+                pattern => format!("{}{}", prefix, pattern),
+            };
+            self.route(route.method, &pattern, handler)?;
+        }
+
+        for (name, pattern) in router.urls.iter() {
+            match pattern {
+                "/" => self.urls.insert(name, prefix)?,
+                pattern => self.urls.insert(name, &format!("{}{}", prefix, pattern))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{Method, Uri, Version};
+
+    use crate::{
+        router::{Params, Router, RouterError},
+        RequestHead,
+    };
+
+    fn router() -> Router<&'static str> {
+        let mut router = Router::new();
+        router.get("/", "index").unwrap();
+        router.get("/todos", "list").unwrap();
+        router.post("/todos", "create").unwrap();
+        router.get("/todos/new", "new").unwrap();
+        router.get("/todos/:id", "show").unwrap();
+        router.delete("/todos/:id", "remove").unwrap();
+        router.get("/todos/:id/notes/:note", "note").unwrap();
+        router.get("/assets/*path", "assets").unwrap();
+        router.get("/archive/:year?/:month?", "archive").unwrap();
+        router
+    }
+
+    #[test]
+    fn matches_static_param_and_wildcard_routes() {
+        let router = router();
+
+        let found = router.at(&Method::GET, "/").unwrap();
+        assert_eq!(*found.handler, "index");
+
+        let found = router.at(&Method::POST, "/todos").unwrap();
+        assert_eq!(*found.handler, "create");
+
+        // static segments win over parameters.
+        let found = router.at(&Method::GET, "/todos/new").unwrap();
+        assert_eq!(*found.handler, "new");
+        assert!(found.params.is_empty());
+
+        let found = router.at(&Method::GET, "/todos/new%20one").unwrap();
+        assert_eq!(*found.handler, "show");
+        assert_eq!(found.params.get("id"), Some("new one"));
+        assert_eq!(found.pattern, "/todos/:id");
+
+        let found = router.at(&Method::GET, "/todos/1/notes/2").unwrap();
+        assert_eq!(
+            found.params.iter().collect::<Vec<_>>(),
+            vec![("id", "1"), ("note", "2")]
+        );
+
+        let found = router.at(&Method::GET, "/assets/css/site.css").unwrap();
+        assert_eq!(found.params.get("path"), Some("css/site.css"));
+
+        assert_eq!(
+            router.at(&Method::GET, "/todos/1/notes").err(),
+            Some(RouterError::NotFound("/todos/1/notes".into()))
+        );
+        assert!(router.at(&Method::GET, "/assets/").is_err());
+    }
+
+    #[test]
+    fn matches_optional_segments() {
+        let router = router();
+
+        for (path, params) in [
+            ("/archive", vec![]),
+            ("/archive/2024", vec![("year", "2024")]),
+            ("/archive/2024/05", vec![("year", "2024"), ("month", "05")]),
+        ] {
+            let found = router.at(&Method::GET, path).unwrap();
+            assert_eq!(*found.handler, "archive");
+            assert_eq!(found.pattern, "/archive/:year?/:month?");
+            assert_eq!(found.params.iter().collect::<Vec<_>>(), params);
+        }
+    }
+
+    #[test]
+    fn reports_methods_allowed_for_a_path() {
+        let router = router();
+
+        assert_eq!(
+            *router.at(&Method::HEAD, "/todos/1").unwrap().handler,
+            "show"
+        );
+
+        match router.at(&Method::PUT, "/todos/1") {
+            Err(err @ RouterError::MethodNotAllowed { .. }) => {
+                assert_eq!(err.status(), http::StatusCode::METHOD_NOT_ALLOWED);
+                let RouterError::MethodNotAllowed { allowed, .. } = err else {
+                    unreachable!()
+                };
+                assert_eq!(allowed, vec![Method::GET, Method::DELETE]);
+            }
+            other => panic!("unexpected {:?}", other.map(|found| *found.handler)),
+        }
+    }
+
+    #[test]
+    fn refuses_conflicting_and_invalid_routes() {
+        let mut router = router();
+
+        for (method, pattern) in [
+            (Method::GET, "/todos/:id"),
+            (Method::POST, "/todos/:todo_id/done"),
+            (Method::GET, "/archive"),
+            (Method::GET, "/assets/*file"),
+        ] {
+            assert!(
+                matches!(
+                    router.route(method.clone(), pattern, "conflict"),
+                    Err(RouterError::Conflict { .. })
+                ),
+                "{} {} should conflict",
+                method,
+                pattern
+            );
+        }
+
+        for pattern in [
+            "todos",
+            "/todos/:",
+            "/*rest/end",
+            "/a/:id/:id",
+            "/a//b",
+            "/file.:ext",
+            "/a/:b?/c",
+        ] {
+            assert!(
+                matches!(
+                    router.get(pattern, "invalid"),
+                    Err(RouterError::InvalidPattern(..))
+                ),
+                "{} should be invalid",
+                pattern
+            );
+        }
+
+        // a failed registration leaves the table untouched.
+        assert!(router.at(&Method::GET, "/todos/1/done").is_err());
+        router.post("/todos/:id/done", "done").unwrap();
+        assert_eq!(
+            *router.at(&Method::POST, "/todos/1/done").unwrap().handler,
+            "done"
+        );
+    }
+
+    #[test]
+    fn routing_a_request_head_adds_params_to_its_extensions() {
+        let router = router();
+        let mut head = RequestHead::new(
+            Method::GET,
+            Version::HTTP_11,
+            Uri::from_static("/todos/7?expand=notes"),
+            String::from("/todos/7"),
+        );
+
+        assert_eq!(*router.route_head(&mut head).unwrap(), "show");
+        assert_eq!(head.route_path(), "/todos/:id");
+        assert_eq!(
+            head.extensions()
+                .get::<Params>()
+                .and_then(|params| params.get("id")),
+            Some("7")
+        );
+    }
+
+    #[test]
+    fn builds_urls_of_named_and_nested_routes() {
+        let mut todos = Router::new();
+        todos
+            .route_named("todo.show", Method::GET, "/todos/:id", "show")
+            .unwrap();
+        todos
+            .route_named("todo.index", Method::GET, "/", "index")
+            .unwrap();
+
+        let mut router = Router::new();
+        router.nest("/api", todos).unwrap();
+
+        assert_eq!(
+            router.url_for("todo.show", [("id", 3)]).unwrap(),
+            "/api/todos/3"
+        );
+        assert_eq!(
+            router.url_for("todo.index", [("id", 3)]).err(),
+            Some(RouterError::UnexpectedParam {
+                route: "todo.index".into(),
+                param: "id".into(),
+            })
+        );
+        assert!(matches!(
+            router.route_named("todo.show", Method::GET, "/other/:id", "other"),
+            Err(RouterError::DuplicateName { .. })
+        ));
+        assert!(router.at(&Method::GET, "/other/1").is_err());
+    }
+}
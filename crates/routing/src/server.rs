@@ -0,0 +1,1154 @@
+// Module implementing a minimal HTTP/1.1 server over std networking.
+
+use std::{
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri, Version};
+use tracing::{debug, error};
+
+use crate::{
+    body::Body, handler::Handler, Extensions, Request, RequestHead, Response, ResponseHead,
+};
+
+const POLL: Duration = Duration::from_millis(20);
+const READ_CHUNK: usize = 8 * 1024;
+
+// longest pause between accepts once accepting keeps failing, e.g while
+// the process is out of file descriptors.
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// ServerConfig declares the limits and timeouts of a [`Server`].
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    max_head_size: usize,
+    max_body_size: usize,
+    max_connections: usize,
+    keep_alive: Duration,
+    request_timeout: Duration,
+    shutdown_timeout: Duration,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            max_head_size: 16 * 1024,
+            max_body_size: 2 * 1024 * 1024,
+            max_connections: 1024,
+            keep_alive: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(10),
+            shutdown_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// with_max_head_size caps the size of the request line and headers,
+    /// larger requests are answered with `431 Request Header Fields Too Large`.
+    pub fn with_max_head_size(mut self, max: usize) -> Self {
+        self.max_head_size = max;
+        self
+    }
+
+    /// with_max_body_size caps the size of request bodies, larger requests
+    /// are answered with `413 Payload Too Large`.
+    pub fn with_max_body_size(mut self, max: usize) -> Self {
+        self.max_body_size = max;
+        self
+    }
+
+    /// with_max_connections caps the number of connections served at once,
+    /// further clients wait to be accepted until a connection closes.
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.max_connections = max;
+        self
+    }
+
+    /// with_keep_alive sets how long an idle connection may stay silent
+    /// before the connection is closed.
+    pub fn with_keep_alive(mut self, timeout: Duration) -> Self {
+        self.keep_alive = timeout;
+        self
+    }
+
+    /// with_request_timeout sets how long a client may take to send a whole
+    /// request, from its first byte to the end of its body, before the
+    /// connection is closed.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// with_shutdown_timeout sets how long [`Server::shutdown`] waits for
+    /// requests in flight to finish.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    pub fn max_head_size(&self) -> usize {
+        self.max_head_size
+    }
+
+    pub fn max_body_size(&self) -> usize {
+        self.max_body_size
+    }
+
+    pub fn max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    pub fn keep_alive(&self) -> Duration {
+        self.keep_alive
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        self.request_timeout
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        self.shutdown_timeout
+    }
+}
+
+/// App is the handler a [`Server`] hands every request to.
+pub type App = Arc<dyn Handler<Vec<u8>, Body>>;
+
+/// Server serves HTTP/1.1 over a std [`TcpListener`], handling every
+/// connection on its own thread.
+///
+/// Connections are kept alive between requests unless the client asks
+/// otherwise, request bodies may be sent with a `Content-Length` or
+/// chunked. Responses are written as their [`Body`] is read, with a
+/// `Content-Length` when its size hint is exact and chunked, ending with
+/// the body's trailers, when it is not or the handler sets the
+/// `Transfer-Encoding: chunked` header. HTTP/1.0 clients get those bodies
+/// delimited by closing the connection instead.
+///
+/// # Example:
+///
+/// ```ignore
+/// let mut router = Router::new();
+/// router.get("/todos/:id", show_todo)?;
+///
+/// let server = Server::bind("127.0.0.1:8080", ServerConfig::new(), router)?;
+/// ...
+/// server.shutdown();
+/// ```
+pub struct Server {
+    address: SocketAddr,
+    state: Arc<ServerState>,
+    config: ServerConfig,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+struct ServerState {
+    stopped: AtomicBool,
+    connections: AtomicUsize,
+}
+
+/// OpenConnection counts a connection as open for as long as it lives,
+/// even if serving it unwinds.
+struct OpenConnection(Arc<ServerState>);
+
+impl OpenConnection {
+    fn new(state: Arc<ServerState>) -> Self {
+        state.connections.fetch_add(1, Ordering::SeqCst);
+        Self(state)
+    }
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Server {
+    /// bind listens on the address, serving the requests with the app
+    /// whose response bodies may be anything convertible into a [`Body`].
+    pub fn bind<U: Into<Body> + 'static>(
+        address: impl ToSocketAddrs,
+        config: ServerConfig,
+        app: impl Handler<Vec<u8>, U>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let app: App = Arc::new(move |req: Request<Vec<u8>>| -> Response<Body> {
+            app.call(req).map(|body| body.map(Into::into))
+        });
+        let state = Arc::new(ServerState {
+            stopped: AtomicBool::new(false),
+            connections: AtomicUsize::new(0),
+        });
+
+        let server_state = state.clone();
+        let server_config = config.clone();
+        let thread = thread::Builder::new()
+            .name(String::from("ewe-routing-server"))
+            .spawn(move || accept(listener, app, server_config, server_state))?;
+
+        Ok(Self {
+            address,
+            state,
+            config,
+            thread: Some(thread),
+        })
+    }
+
+    /// local_addr returns the address the server listens on, useful when
+    /// bound to port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// connections returns the number of open connections.
+    pub fn connections(&self) -> usize {
+        self.state.connections.load(Ordering::SeqCst)
+    }
+
+    /// shutdown stops accepting connections and waits, up to the
+    /// configured shutdown timeout, for the requests in flight to be
+    /// answered. Idle connections are closed right away.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        self.state.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            _ = thread.join();
+        }
+
+        let deadline = Instant::now() + self.config.shutdown_timeout;
+        while self.connections() > 0 && Instant::now() < deadline {
+            thread::sleep(POLL);
+        }
+        debug!(
+            "Server stopped with {} open connections",
+            self.connections()
+        );
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn accept(listener: TcpListener, app: App, config: ServerConfig, state: Arc<ServerState>) {
+    let mut backoff = POLL;
+    while !state.stopped.load(Ordering::SeqCst) {
+        // clients beyond the limit wait in the listener's backlog.
+        if state.connections.load(Ordering::SeqCst) >= config.max_connections {
+            thread::sleep(POLL);
+            continue;
+        }
+
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                backoff = POLL;
+                let (app, config) = (app.clone(), config.clone());
+                let open = OpenConnection::new(state.clone());
+                let spawned = thread::Builder::new()
+                    .name(String::from("ewe-routing-connection"))
+                    .spawn(move || {
+                        if let Err(err) = serve_connection(stream, &app, &config, &open.0) {
+                            debug!("Connection with {} failed: {}", peer, err);
+                        }
+                    });
+                if let Err(err) = spawned {
+                    error!("Server failed to spawn connection thread: {}", err);
+                }
+            }
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL),
+            Err(err) => {
+                error!("Server failed to accept: {}", err);
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+            }
+        }
+    }
+}
+
+/// ParseError is a request the server refuses, answered with its status
+/// before the connection is closed.
+#[derive(Debug)]
+struct ParseError(StatusCode, &'static str);
+
+impl From<ParseError> for io::Error {
+    fn from(err: ParseError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err.1)
+    }
+}
+
+/// Connection buffers the bytes read from a stream, idle reads wait at most
+/// the keep-alive timeout for the next request and give up early once the
+/// server stops, a request must be read whole within the request timeout.
+struct Connection<'a> {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    deadline: Option<Instant>,
+    config: &'a ServerConfig,
+    state: &'a ServerState,
+}
+
+enum Fill {
+    Read,
+    Closed,
+}
+
+impl<'a> Connection<'a> {
+    fn fill(&mut self, idle: bool) -> io::Result<Fill> {
+        let deadline = match self.deadline {
+            Some(deadline) if !idle => deadline,
+            _ => Instant::now() + self.config.keep_alive,
+        };
+        let mut chunk = [0; READ_CHUNK];
+        loop {
+            if idle && self.state.stopped.load(Ordering::SeqCst) {
+                return Ok(Fill::Closed);
+            }
+            if Instant::now() >= deadline {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Ok(Fill::Closed),
+                Ok(read) => {
+                    self.buffer.extend_from_slice(&chunk[..read]);
+                    return Ok(Fill::Read);
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) => {}
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// read_head returns the bytes of the next request's head, none if the
+    /// client closed the connection or went idle.
+    fn read_head(&mut self) -> io::Result<Result<Option<Vec<u8>>, ParseError>> {
+        loop {
+            // the request timeout runs from the first byte of the request.
+            if self.deadline.is_none() && !self.buffer.is_empty() {
+                self.deadline = Some(Instant::now() + self.config.request_timeout);
+            }
+
+            if let Some(end) = find(&self.buffer, b"\r\n\r\n") {
+                if end + 4 > self.config.max_head_size {
+                    return Ok(Err(HEAD_TOO_LARGE));
+                }
+                let head: Vec<u8> = self.buffer.drain(..end + 4).collect();
+                return Ok(Ok(Some(head)));
+            }
+            if self.buffer.len() > self.config.max_head_size {
+                return Ok(Err(HEAD_TOO_LARGE));
+            }
+
+            match self.fill(self.buffer.is_empty()) {
+                Ok(Fill::Read) => continue,
+                Ok(Fill::Closed) => return Ok(Ok(None)),
+                Err(err) if err.kind() == io::ErrorKind::TimedOut && self.buffer.is_empty() => {
+                    return Ok(Ok(None))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn read_exact(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        while self.buffer.len() < len {
+            if let Fill::Closed = self.fill(false)? {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+        Ok(self.buffer.drain(..len).collect())
+    }
+
+    fn read_line(&mut self, max: usize) -> io::Result<Result<Vec<u8>, ParseError>> {
+        loop {
+            if let Some(end) = find(&self.buffer, b"\r\n") {
+                let line: Vec<u8> = self.buffer.drain(..end + 2).take(end).collect();
+                return Ok(Ok(line));
+            }
+            if self.buffer.len() > max {
+                return Ok(Err(ParseError(StatusCode::BAD_REQUEST, "line too long")));
+            }
+            if let Fill::Closed = self.fill(false)? {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+        }
+    }
+
+    /// read_chunked decodes a chunked body, trailers are read and dropped.
+    fn read_chunked(&mut self) -> io::Result<Result<Vec<u8>, ParseError>> {
+        let mut body = Vec::new();
+        loop {
+            let line = match self.read_line(self.config.max_head_size)? {
+                Ok(line) => line,
+                Err(err) => return Ok(Err(err)),
+            };
+            let size = std::str::from_utf8(&line)
+                .ok()
+                .and_then(|line| line.split(';').next())
+                .and_then(|size| usize::from_str_radix(size.trim(), 16).ok());
+            let Some(size) = size else {
+                return Ok(Err(ParseError(
+                    StatusCode::BAD_REQUEST,
+                    "invalid chunk size",
+                )));
+            };
+
+            if size == 0 {
+                return Ok(self.read_trailers()?.map(|_| body));
+            }
+
+            match body.len().checked_add(size) {
+                Some(len) if len <= self.config.max_body_size => {}
+                _ => return Ok(Err(BODY_TOO_LARGE)),
+            }
+            body.extend(self.read_exact(size)?);
+            if self.read_exact(2)? != b"\r\n" {
+                return Ok(Err(ParseError(StatusCode::BAD_REQUEST, "invalid chunk")));
+            }
+        }
+    }
+
+    /// read_trailers reads and drops the trailers of a chunked body, which
+    /// may not take more than the head's limit altogether.
+    fn read_trailers(&mut self) -> io::Result<Result<(), ParseError>> {
+        let mut remaining = self.config.max_head_size;
+        loop {
+            match self.read_line(remaining)? {
+                Ok(trailer) if trailer.is_empty() => return Ok(Ok(())),
+                Ok(trailer) if trailer.len() + 2 <= remaining => remaining -= trailer.len() + 2,
+                Ok(_) => return Ok(Err(HEAD_TOO_LARGE)),
+                Err(err) => return Ok(Err(err)),
+            }
+        }
+    }
+}
+
+const HEAD_TOO_LARGE: ParseError = ParseError(
+    StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+    "request head too large",
+);
+const BODY_TOO_LARGE: ParseError =
+    ParseError(StatusCode::PAYLOAD_TOO_LARGE, "request body too large");
+
+fn serve_connection(
+    stream: TcpStream,
+    app: &App,
+    config: &ServerConfig,
+    state: &ServerState,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(POLL))?;
+    let mut connection = Connection {
+        stream,
+        buffer: Vec::new(),
+        deadline: None,
+        config,
+        state,
+    };
+
+    loop {
+        let head = match connection.read_head()? {
+            Ok(Some(head)) => head,
+            Ok(None) => return Ok(()),
+            Err(err) => return refuse(&mut connection.stream, err),
+        };
+
+        let head = match parse_head(&head) {
+            Ok(head) => head,
+            Err(err) => return refuse(&mut connection.stream, err),
+        };
+
+        if expects_continue(&head.headers) {
+            connection
+                .stream
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        }
+
+        let body = match read_body(&mut connection, &head.headers)? {
+            Ok(body) => body,
+            Err(err) => return refuse(&mut connection.stream, err),
+        };
+        connection.deadline = None;
+
+        let wants_keep_alive = wants_keep_alive(&head);
+        let is_head = head.method == Method::HEAD;
+        let version = head.version;
+        // a panicking handler fails its request and closes the connection,
+        // whether or not it is wrapped in a catching middleware.
+        let response =
+            match panic::catch_unwind(AssertUnwindSafe(|| app.call(Request::from(body, head)))) {
+                Ok(response) => response,
+                Err(_) => {
+                    let response =
+                        text_response(StatusCode::INTERNAL_SERVER_ERROR, "internal server error")
+                            .map(|body| body.map(Body::from));
+                    write_response(&mut connection.stream, response, version, false, is_head)?;
+                    return Err(io::Error::other("handler panicked"));
+                }
+            };
+
+        // requests answered once the server stops close their connection.
+        let keep_alive = wants_keep_alive && !state.stopped.load(Ordering::SeqCst);
+        let kept_alive = write_response(
+            &mut connection.stream,
+            response,
+            version,
+            keep_alive,
+            is_head,
+        )?;
+
+        if !kept_alive {
+            return Ok(());
+        }
+    }
+}
+
+fn parse_head(bytes: &[u8]) -> Result<RequestHead, ParseError> {
+    let bad_request = |reason| ParseError(StatusCode::BAD_REQUEST, reason);
+
+    let mut lines = bytes[..bytes.len() - 4].split(|byte| *byte == b'\n');
+    let request_line = lines.next().ok_or(bad_request("missing request line"))?;
+    let request_line = std::str::from_utf8(trim_cr(request_line))
+        .map_err(|_| bad_request("invalid request line"))?;
+
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(bad_request("invalid request line"));
+    };
+
+    let method =
+        Method::from_bytes(method.as_bytes()).map_err(|_| bad_request("invalid method"))?;
+    let target = Uri::try_from(target).map_err(|_| bad_request("invalid request target"))?;
+    let version = match version {
+        "HTTP/1.1" => Version::HTTP_11,
+        "HTTP/1.0" => Version::HTTP_10,
+        _ => {
+            return Err(ParseError(
+                StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+                "unsupported http version",
+            ))
+        }
+    };
+
+    let route_path = String::from(target.path());
+    let mut head = RequestHead::new(method, version, target, route_path);
+    for line in lines {
+        let line = trim_cr(line);
+        if line.starts_with(b" ") || line.starts_with(b"\t") {
+            return Err(bad_request("folded headers are not supported"));
+        }
+
+        let colon = line
+            .iter()
+            .position(|byte| *byte == b':')
+            .ok_or(bad_request("invalid header"))?;
+        let name =
+            HeaderName::from_bytes(&line[..colon]).map_err(|_| bad_request("invalid header"))?;
+        let value = HeaderValue::from_bytes(trim_whitespace(&line[colon + 1..]))
+            .map_err(|_| bad_request("invalid header"))?;
+        head.headers.append(name, value);
+    }
+
+    Ok(head)
+}
+
+fn read_body(
+    connection: &mut Connection<'_>,
+    headers: &HeaderMap,
+) -> io::Result<Result<Option<Vec<u8>>, ParseError>> {
+    let bad_request = |reason| Ok(Err(ParseError(StatusCode::BAD_REQUEST, reason)));
+
+    if let Some(encoding) = headers.get(header::TRANSFER_ENCODING) {
+        if headers.contains_key(header::CONTENT_LENGTH) {
+            return bad_request("both content-length and transfer-encoding given");
+        }
+        if !encoding.as_bytes().eq_ignore_ascii_case(b"chunked") {
+            return Ok(Err(ParseError(
+                StatusCode::NOT_IMPLEMENTED,
+                "unsupported transfer-encoding",
+            )));
+        }
+        return Ok(connection.read_chunked()?.map(Some));
+    }
+
+    let mut lengths = headers.get_all(header::CONTENT_LENGTH).iter();
+    let Some(length) = lengths.next() else {
+        return Ok(Ok(None));
+    };
+    if lengths.any(|other| other != length) {
+        return bad_request("conflicting content-length");
+    }
+
+    let Some(length) = length
+        .to_str()
+        .ok()
+        .and_then(|length| length.parse::<usize>().ok())
+    else {
+        return bad_request("invalid content-length");
+    };
+    if length > connection.config.max_body_size {
+        return Ok(Err(BODY_TOO_LARGE));
+    }
+
+    Ok(Ok(Some(connection.read_exact(length)?)))
+}
+
+fn wants_keep_alive(head: &RequestHead) -> bool {
+    let connection = head
+        .headers
+        .get(header::CONNECTION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_ascii_lowercase());
+
+    match connection.as_deref() {
+        Some(value) if value.split(',').any(|token| token.trim() == "close") => false,
+        Some(value) if value.split(',').any(|token| token.trim() == "keep-alive") => true,
+        _ => head.version == Version::HTTP_11,
+    }
+}
+
+fn expects_continue(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::EXPECT)
+        .map(|value| value.as_bytes().eq_ignore_ascii_case(b"100-continue"))
+        .unwrap_or(false)
+}
+
+/// write_response writes the response in the HTTP version of the request,
+/// returning whether the connection is kept alive.
+fn write_response(
+    stream: &mut TcpStream,
+    response: Response<Body>,
+    version: Version,
+    keep_alive: bool,
+    is_head: bool,
+) -> io::Result<bool> {
+    let status = response.head.status;
+    let mut headers = response.head.headers;
+    let mut body = response.body.unwrap_or_default();
+    let sends_body = !is_head && has_body(status);
+
+    let chunked = match headers.get(header::TRANSFER_ENCODING) {
+        Some(value) => value.as_bytes().eq_ignore_ascii_case(b"chunked"),
+        None => {
+            sends_body
+                && !headers.contains_key(header::CONTENT_LENGTH)
+                && body.size_hint().exact_len().is_none()
+        }
+    };
+
+    // HTTP/1.0 clients know no chunked encoding, the body ends with the
+    // connection instead.
+    let is_http_10 = version == Version::HTTP_10;
+    let close_delimited = chunked && is_http_10;
+    let chunked = chunked && !is_http_10;
+    let keep_alive = keep_alive && !close_delimited;
+
+    if close_delimited {
+        headers.remove(header::TRANSFER_ENCODING);
+        headers.remove(header::CONTENT_LENGTH);
+    } else if chunked {
+        headers.remove(header::CONTENT_LENGTH);
+        headers.insert(
+            header::TRANSFER_ENCODING,
+            HeaderValue::from_static("chunked"),
+        );
+    } else if sends_body && !headers.contains_key(header::CONTENT_LENGTH) {
+        if let Some(len) = body.size_hint().exact_len() {
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+        }
+    }
+    headers.insert(
+        header::CONNECTION,
+        HeaderValue::from_static(if keep_alive { "keep-alive" } else { "close" }),
+    );
+
+    let mut out = io::BufWriter::new(stream);
+    write!(
+        out,
+        "{} {} {}\r\n",
+        if is_http_10 { "HTTP/1.0" } else { "HTTP/1.1" },
+        status.as_str(),
+        status.canonical_reason().unwrap_or_default()
+    )?;
+    write_fields(&mut out, &headers)?;
+    out.write_all(b"\r\n")?;
+
+    if !is_head {
+        // streamed chunks are flushed as they come, a slow stream must not
+        // hold back what it already produced.
+        let streaming = body.is_stream();
+        while let Some(chunk) = body.next_chunk().map_err(io::Error::other)? {
+            if chunked {
+                write!(out, "{:x}\r\n", chunk.len())?;
+                out.write_all(&chunk)?;
+                out.write_all(b"\r\n")?;
+            } else {
+                out.write_all(&chunk)?;
+            }
+            if streaming {
+                out.flush()?;
+            }
+        }
+        if chunked {
+            out.write_all(b"0\r\n")?;
+            write_fields(&mut out, &body.trailers().unwrap_or_default())?;
+            out.write_all(b"\r\n")?;
+        }
+    }
+    out.flush()?;
+    Ok(keep_alive)
+}
+
+fn write_fields(out: &mut impl Write, fields: &HeaderMap) -> io::Result<()> {
+    for (name, value) in fields.iter() {
+        out.write_all(name.as_str().as_bytes())?;
+        out.write_all(b": ")?;
+        out.write_all(value.as_bytes())?;
+        out.write_all(b"\r\n")?;
+    }
+    Ok(())
+}
+
+/// has_body reports whether responses of the status may carry a body,
+/// the others never get a `Content-Length` of their dropped body.
+fn has_body(status: StatusCode) -> bool {
+    !(status.is_informational()
+        || status == StatusCode::NO_CONTENT
+        || status == StatusCode::NOT_MODIFIED)
+}
+
+fn refuse(stream: &mut TcpStream, err: ParseError) -> io::Result<()> {
+    debug!("Refusing request: {}", err.1);
+    let response = text_response(err.0, err.1).map(|body| body.map(Body::from));
+    write_response(stream, response, Version::HTTP_11, false, false)?;
+    Err(err.into())
+}
+
+pub(crate) fn text_response(status: StatusCode, message: &str) -> Response<Vec<u8>> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    let head = ResponseHead::new(status, Version::HTTP_11, headers, Extensions::new());
+    Response::new(message.as_bytes().to_vec(), head)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn trim_whitespace(value: &[u8]) -> &[u8] {
+    let start = value
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(value.len());
+    let end = value
+        .iter()
+        .rposition(|byte| !byte.is_ascii_whitespace())
+        .map(|end| end + 1)
+        .unwrap_or(start);
+    &value[start..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Read, Write},
+        net::{SocketAddr, TcpStream},
+        sync::{Arc, Barrier},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use http::{header, HeaderMap, HeaderValue, StatusCode};
+
+    use crate::{
+        body::Body,
+        router::{Params, Router},
+        server::{text_response, Server, ServerConfig},
+        Request, Response,
+    };
+
+    type Handler = fn(Request<Vec<u8>>) -> Response<Vec<u8>>;
+
+    fn show(req: Request<Vec<u8>>) -> Response<Vec<u8>> {
+        let id = req
+            .extensions()
+            .get::<Params>()
+            .and_then(|params| params.get("id"))
+            .unwrap_or_default()
+            .to_string();
+        text_response(StatusCode::OK, &format!("todo {}", id))
+    }
+
+    fn echo(req: Request<Vec<u8>>) -> Response<Vec<u8>> {
+        let mut response = text_response(StatusCode::OK, "");
+        *response.body_mut() = req.into_body();
+        response
+    }
+
+    fn stream(req: Request<Vec<u8>>) -> Response<Vec<u8>> {
+        let mut response = echo(req);
+        response.head_mut().headers.insert(
+            header::TRANSFER_ENCODING,
+            HeaderValue::from_static("chunked"),
+        );
+        response
+    }
+
+    fn server(config: ServerConfig) -> Server {
+        let mut router: Router<Handler> = Router::new();
+        router.get("/todos/:id", show).unwrap();
+        router.post("/echo", echo).unwrap();
+        router.post("/stream", stream).unwrap();
+        Server::bind("127.0.0.1:0", config, router).unwrap()
+    }
+
+    fn connect(address: SocketAddr) -> TcpStream {
+        let stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        stream
+    }
+
+    /// read_response reads a single response with a content-length body.
+    fn read_response(stream: &mut TcpStream) -> String {
+        let mut response = Vec::new();
+        let mut byte = [0; 1];
+        while !response.ends_with(b"\r\n\r\n") {
+            if stream.read(&mut byte).unwrap() == 0 {
+                break;
+            }
+            response.push(byte[0]);
+        }
+
+        let head = String::from_utf8(response).unwrap();
+        let length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length: "))
+            .map(|length| length.parse::<usize>().unwrap())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        stream.read_exact(&mut body).unwrap();
+        head + &String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn serves_keep_alive_connections() {
+        let server = server(ServerConfig::new());
+        let mut stream = connect(server.local_addr());
+
+        stream
+            .write_all(b"GET /todos/1 HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("connection: keep-alive\r\n"));
+        assert!(response.ends_with("todo 1"));
+
+        stream.write_all(b"HEAD /todos/1 HTTP/1.1\r\n\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(!response.contains("content-length"));
+        assert!(response.ends_with("\r\n\r\n"));
+
+        // a pipelined pair of requests on the same connection.
+        stream
+            .write_all(
+                b"DELETE /todos/2 HTTP/1.1\r\n\r\nGET /missing HTTP/1.1\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(response.contains("allow: GET\r\n"));
+
+        let response = read_response(&mut stream);
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+        assert_eq!(stream.read(&mut [0; 1]).unwrap(), 0);
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn reads_and_writes_chunked_bodies() {
+        let server = server(ServerConfig::new());
+        let mut stream = connect(server.local_addr());
+
+        stream
+            .write_all(
+                b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6;ext=1\r\n world\r\n0\r\nX-Trailer: 1\r\n\r\n",
+            )
+            .unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.contains("content-length: 11\r\n"));
+        assert!(response.ends_with("hello world"));
+
+        stream
+            .write_all(
+                b"POST /stream HTTP/1.1\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+            )
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.contains("transfer-encoding: chunked\r\n"));
+        assert!(!response.contains("content-length"));
+        assert!(response.ends_with("\r\n\r\n5\r\nhello\r\n0\r\n\r\n"));
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn writes_streaming_bodies_by_their_size_hint() {
+        let respond = |body: Body| text_response(StatusCode::OK, "").map(|_| Some(body));
+        let server = Server::bind(
+            "127.0.0.1:0",
+            ServerConfig::new(),
+            move |req: Request<Vec<u8>>| {
+                if req.url().path() == "/file" {
+                    return respond(Body::reader(io::Cursor::new(b"hello world".to_vec()), 5));
+                }
+
+                let (mut sender, body) = Body::channel(1);
+                thread::spawn(move || {
+                    sender.send("hello").unwrap();
+                    sender.send(" world").unwrap();
+                    let mut trailers = HeaderMap::new();
+                    trailers.insert("x-checksum", HeaderValue::from_static("abc"));
+                    sender.finish_with_trailers(trailers);
+                });
+                respond(body)
+            },
+        )
+        .unwrap();
+        let mut stream = connect(server.local_addr());
+
+        stream.write_all(b"GET /file HTTP/1.1\r\n\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.contains("content-length: 5\r\n"));
+        assert!(!response.contains("transfer-encoding"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+
+        stream
+            .write_all(b"GET /events HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.contains("transfer-encoding: chunked\r\n"));
+        assert!(!response.contains("content-length"));
+        assert!(
+            response.ends_with("\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\nx-checksum: abc\r\n\r\n")
+        );
+
+        // HTTP/1.0 clients get the stream delimited by the connection.
+        let mut stream = connect(server.local_addr());
+        stream
+            .write_all(b"GET /events HTTP/1.0\r\nConnection: keep-alive\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+        assert!(!response.contains("transfer-encoding"));
+        assert!(!response.contains("content-length"));
+        assert!(response.ends_with("\r\n\r\nhello world"));
+
+        server.shutdown();
+    }
+
+    #[test]
+    fn refuses_requests_beyond_the_size_limits() {
+        let server = server(
+            ServerConfig::new()
+                .with_max_head_size(128)
+                .with_max_body_size(8),
+        );
+
+        let mut stream = connect(server.local_addr());
+        stream
+            .write_all(b"POST /echo HTTP/1.1\r\nContent-Length: 9\r\n\r\n123456789")
+            .unwrap();
+        assert!(read_response(&mut stream).starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        let mut stream = connect(server.local_addr());
+        stream
+            .write_all(b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n9\r\n123456789\r\n0\r\n\r\n")
+            .unwrap();
+        assert!(read_response(&mut stream).starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        let mut stream = connect(server.local_addr());
+        stream
+            .write_all(b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n1234\r\nffffffffffffffff\r\n")
+            .unwrap();
+        assert!(read_response(&mut stream).starts_with("HTTP/1.1 413 Payload Too Large\r\n"));
+
+        let mut stream = connect(server.local_addr());
+        stream
+            .write_all(b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n")
+            .unwrap();
+        for _ in 0..16 {
+            stream.write_all(b"X-Trailer: 1234567890\r\n").unwrap();
+        }
+        assert!(read_response(&mut stream)
+            .starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+        let mut stream = connect(server.local_addr());
+        let cookie = "a".repeat(256);
+        write!(
+            stream,
+            "GET /todos/1 HTTP/1.1\r\nCookie: {}\r\n\r\n",
+            cookie
+        )
+        .unwrap();
+        assert!(read_response(&mut stream)
+            .starts_with("HTTP/1.1 431 Request Header Fields Too Large\r\n"));
+
+        let mut stream = connect(server.local_addr());
+        stream
+            .write_all(
+                b"POST /echo HTTP/1.1\r\nContent-Length: 1\r\nTransfer-Encoding: chunked\r\n\r\n",
+            )
+            .unwrap();
+        assert!(read_response(&mut stream).starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[test]
+    fn closes_connections_sending_requests_slower_than_the_request_timeout() {
+        let server = server(
+            ServerConfig::new()
+                .with_keep_alive(Duration::from_secs(5))
+                .with_request_timeout(Duration::from_millis(200)),
+        );
+        let mut stream = connect(server.local_addr());
+
+        // trickles headers, every read arrives well within the keep-alive.
+        let mut writer = stream.try_clone().unwrap();
+        let trickle = thread::spawn(move || {
+            _ = writer.write_all(b"GET /todos/1 HTTP/1.1\r\n");
+            for _ in 0..100 {
+                if writer.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+        });
+
+        let started = Instant::now();
+        let mut response = Vec::new();
+        _ = stream.read_to_end(&mut response);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(response.is_empty());
+        trickle.join().unwrap();
+    }
+
+    #[test]
+    fn limits_the_connections_served_at_once() {
+        let server = server(ServerConfig::new().with_max_connections(1));
+        let address = server.local_addr();
+
+        let mut first = connect(address);
+        first.write_all(b"GET /todos/1 HTTP/1.1\r\n\r\n").unwrap();
+        assert!(read_response(&mut first).starts_with("HTTP/1.1 200 OK\r\n"));
+
+        let mut second = connect(address);
+        second.write_all(b"GET /todos/2 HTTP/1.1\r\n\r\n").unwrap();
+        second
+            .set_read_timeout(Some(Duration::from_millis(200)))
+            .unwrap();
+        assert!(second.read(&mut [0; 1]).is_err());
+
+        drop(first);
+        second
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(read_response(&mut second).ends_with("todo 2"));
+    }
+
+    #[test]
+    fn answers_panicking_handlers_and_closes_their_connection() {
+        let server = Server::bind(
+            "127.0.0.1:0",
+            ServerConfig::new(),
+            |_| -> Response<Vec<u8>> { panic!("boom") },
+        )
+        .unwrap();
+
+        let mut stream = connect(server.local_addr());
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+        assert_eq!(stream.read(&mut [0; 1]).unwrap(), 0);
+
+        for _ in 0..100 {
+            if server.connections() == 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(server.connections(), 0);
+    }
+
+    #[test]
+    fn shutdown_waits_for_requests_in_flight() {
+        let started = Arc::new(Barrier::new(2));
+        let handler_started = started.clone();
+        let server = Server::bind("127.0.0.1:0", ServerConfig::new(), move |_| {
+            handler_started.wait();
+            thread::sleep(Duration::from_millis(100));
+            text_response(StatusCode::OK, "done")
+        })
+        .unwrap();
+        let address = server.local_addr();
+
+        let idle = connect(address);
+        let mut busy = connect(address);
+        busy.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+        started.wait();
+
+        server.shutdown();
+
+        let response = read_response(&mut busy);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("connection: close\r\n"));
+        assert_eq!((&idle).read(&mut [0; 1]).unwrap(), 0);
+        assert!(TcpStream::connect(address).is_err());
+    }
+}
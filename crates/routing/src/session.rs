@@ -0,0 +1,575 @@
+// Module implementing sessions, keeping per client data in a store across
+// requests identified by a cookie.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use http::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    cookies::{random_bytes, Cookie, CookieError, CookieJar, Key, SameSite},
+    extract::{ExtractResult, FromRequest, Rejection},
+    handler::{Handler, Layer},
+    middleware::status_response,
+    Request, Response,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    #[error("session store failed: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("session value failed to serialize: {0}")]
+    Serialization(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Cookie(#[from] CookieError),
+}
+
+pub type SessionResult<T> = std::result::Result<T, SessionError>;
+
+/// SessionData are the values of a session by key.
+pub type SessionData = BTreeMap<String, serde_json::Value>;
+
+/// SessionStore keeps the data of sessions by their id, dropping them once
+/// their time to live passed.
+pub trait SessionStore: Send + Sync + 'static {
+    fn load(&self, id: &str) -> SessionResult<Option<SessionData>>;
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> SessionResult<()>;
+    fn remove(&self, id: &str) -> SessionResult<()>;
+}
+
+/// MemoryStore keeps sessions in memory, they are lost when the process
+/// exits.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    sessions: Arc<Mutex<HashMap<String, (SessionData, SystemTime)>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> SessionResult<Option<SessionData>> {
+        let mut sessions = self.sessions.lock().unwrap();
+        match sessions.get(id) {
+            Some((_, expires)) if *expires <= SystemTime::now() => {
+                sessions.remove(id);
+                Ok(None)
+            }
+            Some((data, _)) => Ok(Some(data.clone())),
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> SessionResult<()> {
+        let expires = SystemTime::now() + ttl;
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(id.into(), (data.clone(), expires));
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> SessionResult<()> {
+        self.sessions.lock().unwrap().remove(id);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSession {
+    expires: u64,
+    data: SessionData,
+}
+
+/// FileStore keeps every session as a JSON file in its directory.
+#[derive(Clone, Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    /// new returns a store keeping its sessions in the directory, creating
+    /// it if missing.
+    pub fn new(dir: impl Into<PathBuf>) -> SessionResult<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// path returns the file of the session, `None` for ids which are not
+    /// ones this module generates, keeping them from naming other files.
+    fn path(&self, id: &str) -> Option<PathBuf> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| self.dir.join(format!("{}.json", id)))
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> SessionResult<Option<SessionData>> {
+        let Some(path) = self.path(id) else {
+            return Ok(None);
+        };
+        let content = match fs::read(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        let stored: StoredSession = serde_json::from_slice(&content)?;
+        if stored.expires <= unix_seconds(SystemTime::now()) {
+            self.remove(id)?;
+            return Ok(None);
+        }
+        Ok(Some(stored.data))
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> SessionResult<()> {
+        let Some(path) = self.path(id) else {
+            return Ok(());
+        };
+        let stored = StoredSession {
+            expires: unix_seconds(SystemTime::now() + ttl),
+            data: data.clone(),
+        };
+
+        // written to a file of its own and renamed so readers never see a
+        // partial file, nor do concurrent saves write into each other.
+        let mut temporary = tempfile::NamedTempFile::new_in(&self.dir)?;
+        temporary.write_all(&serde_json::to_vec(&stored)?)?;
+        temporary.persist(&path).map_err(|err| err.error)?;
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> SessionResult<()> {
+        let Some(path) = self.path(id) else {
+            return Ok(());
+        };
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[derive(Debug, Default)]
+struct State {
+    id: Option<String>,
+    data: SessionData,
+    changed: bool,
+    renewed: bool,
+    destroyed: bool,
+}
+
+/// Session is the session of the request, added to its extensions by the
+/// [`Sessions`] layer. Clones share the same session, changes are saved
+/// once the handler returns.
+///
+/// # Example:
+///
+/// ```ignore
+/// fn login(req: Request<Vec<u8>>) -> Response<Vec<u8>> {
+///     let session = req.extract::<Session>()?;
+///     session.renew();
+///     session.insert("user_id", 42)?;
+///     Response::redirect(&Uri::from_static("/"))
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    state: Arc<Mutex<State>>,
+}
+
+impl Session {
+    fn load(id: Option<String>, data: SessionData) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                id,
+                data,
+                ..State::default()
+            })),
+        }
+    }
+
+    /// id returns the id of the session, `None` until it is first saved.
+    pub fn id(&self) -> Option<String> {
+        self.state.lock().unwrap().id.clone()
+    }
+
+    /// get returns the value of the key, `None` if it is missing or is not
+    /// a `T`.
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let state = self.state.lock().unwrap();
+        let value = state.data.get(key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    pub fn insert<T: Serialize>(&self, key: &str, value: T) -> SessionResult<()> {
+        let value = serde_json::to_value(value)?;
+        let mut state = self.state.lock().unwrap();
+        state.data.insert(key.into(), value);
+        state.changed = true;
+        Ok(())
+    }
+
+    pub fn remove(&self, key: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.data.remove(key).is_some() {
+            state.changed = true;
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.data.clear();
+        state.changed = true;
+    }
+
+    /// renew moves the session to a new id, e.g. when a user logs in to
+    /// keep a session id set before from being reused.
+    pub fn renew(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.renewed = true;
+        state.changed = true;
+    }
+
+    /// destroy drops the session from the store and the client.
+    pub fn destroy(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+impl<B> FromRequest<B> for Session {
+    fn from_request(req: &Request<B>) -> ExtractResult<Self> {
+        req.extensions()
+            .get::<Session>()
+            .cloned()
+            .ok_or(Rejection::MissingExtension(std::any::type_name::<Session>()))
+    }
+}
+
+/// Sessions is a [`Layer`] loading the [`Session`] of every request from
+/// its store, identified by a cookie holding a random id, and saving it
+/// after the handler if it changed.
+///
+/// Session cookies are `HttpOnly` and `SameSite=Lax`, with
+/// [`Sessions::with_key`] their ids are also signed.
+///
+/// # Example:
+///
+/// ```ignore
+/// let sessions = Sessions::new(FileStore::new("/var/lib/todo/sessions")?)
+///     .with_key(Key::new(&secret)?)
+///     .with_secure(true);
+///
+/// let router = router.layer(&sessions);
+/// ```
+pub struct Sessions<S> {
+    store: Arc<S>,
+    cookie_name: String,
+    ttl: Duration,
+    key: Option<Key>,
+    secure: bool,
+}
+
+impl<S: SessionStore> Sessions<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store: Arc::new(store),
+            cookie_name: String::from("session"),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            key: None,
+            secure: false,
+        }
+    }
+
+    pub fn with_cookie_name(mut self, name: &str) -> Self {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// with_ttl sets how long sessions live after their last change,
+    /// defaults to a day.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// with_key signs the session cookies with the key.
+    pub fn with_key(mut self, key: Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// with_secure sets whether the session cookie is only sent over
+    /// HTTPS.
+    pub fn with_secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+}
+
+impl<H, S: SessionStore> Layer<H> for Sessions<S> {
+    type Handler = SessionHandler<H, S>;
+
+    fn layer(&self, inner: H) -> Self::Handler {
+        SessionHandler {
+            inner,
+            store: self.store.clone(),
+            cookie_name: self.cookie_name.clone(),
+            ttl: self.ttl,
+            key: self.key.clone(),
+            secure: self.secure,
+        }
+    }
+}
+
+pub struct SessionHandler<H, S> {
+    inner: H,
+    store: Arc<S>,
+    cookie_name: String,
+    ttl: Duration,
+    key: Option<Key>,
+    secure: bool,
+}
+
+impl<H, S: SessionStore> SessionHandler<H, S> {
+    fn load(&self, jar: &CookieJar) -> Session {
+        let id = match &self.key {
+            Some(key) => jar.get_signed(&self.cookie_name, key),
+            None => jar.get(&self.cookie_name).map(String::from),
+        };
+        let Some(id) = id else {
+            return Session::default();
+        };
+
+        match self.store.load(&id) {
+            Ok(Some(data)) => Session::load(Some(id), data),
+            Ok(None) => Session::default(),
+            Err(err) => {
+                tracing::error!("Failed to load session, starting a new one: {}", err);
+                Session::default()
+            }
+        }
+    }
+
+    fn cookie(&self, value: String) -> Cookie {
+        Cookie::new(self.cookie_name.clone(), value)
+            .with_path("/")
+            .with_http_only(true)
+            .with_secure(self.secure)
+            .with_same_site(SameSite::Lax)
+    }
+
+    /// save stores the changes of the session, returning the cookie to
+    /// send the client whenever the session was stored, its Max-Age
+    /// following the extended time to live.
+    fn save(&self, session: &Session) -> SessionResult<Option<Cookie>> {
+        let mut state = session.state.lock().unwrap();
+        if state.destroyed {
+            if let Some(id) = state.id.take() {
+                self.store.remove(&id)?;
+            }
+            let removal = Cookie::removal(self.cookie_name.clone()).with_path("/");
+            return Ok(Some(removal));
+        }
+        if !state.changed {
+            return Ok(None);
+        }
+
+        let previous = state.id.clone();
+        if state.renewed || previous.is_none() {
+            state.id = Some(URL_SAFE_NO_PAD.encode(random_bytes::<32>()?));
+        }
+        let id = state.id.clone().unwrap_or_default();
+        self.store.save(&id, &state.data, self.ttl)?;
+
+        if let Some(previous) = previous.filter(|previous| *previous != id) {
+            self.store.remove(&previous)?;
+        }
+        let cookie = self.cookie(id).with_max_age(self.ttl);
+        Ok(Some(match &self.key {
+            Some(key) => key.sign(cookie),
+            None => cookie,
+        }))
+    }
+}
+
+impl<T, U, H, S> Handler<T, U> for SessionHandler<H, S>
+where
+    H: Handler<T, U>,
+    S: SessionStore,
+{
+    fn call(&self, mut req: Request<T>) -> Response<U> {
+        let session = self.load(&CookieJar::from_headers(req.headers()));
+        req.head.extensions.insert(session.clone());
+
+        let mut response = self.inner.call(req);
+        match self.save(&session) {
+            Ok(Some(cookie)) => response.add_cookie(&cookie),
+            Ok(None) => {}
+            // the handler's response relies on changes which were lost.
+            Err(err) => {
+                tracing::error!("Failed to save session: {}", err);
+                return status_response(StatusCode::INTERNAL_SERVER_ERROR);
+            }
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, time::Duration};
+
+    use http::{header, HeaderValue, Method, StatusCode, Uri, Version};
+
+    use crate::{
+        cookies::Key,
+        handler::{Handler, HandlerExt},
+        session::{
+            FileStore, MemoryStore, Session, SessionData, SessionResult, SessionStore, Sessions,
+        },
+        Request, RequestHead, Response,
+    };
+
+    fn request(cookie: Option<&str>) -> Request<Vec<u8>> {
+        let mut head = RequestHead::new(
+            Method::GET,
+            Version::HTTP_11,
+            Uri::from_static("/"),
+            String::from("/"),
+        );
+        if let Some(cookie) = cookie {
+            head.headers
+                .insert(header::COOKIE, HeaderValue::from_str(cookie).unwrap());
+        }
+        Request::from_head(head)
+    }
+
+    fn visits(req: Request<Vec<u8>>) -> Response<Vec<u8>> {
+        let session = req.extract::<Session>().unwrap();
+        if req.url().query() == Some("logout") {
+            session.destroy();
+            return Response::text("bye");
+        }
+        let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+        session.insert("visits", visits).unwrap();
+        Response::text(visits.to_string())
+    }
+
+    fn session_cookie(response: &Response<Vec<u8>>) -> Option<String> {
+        let value = response.headers().get(header::SET_COOKIE)?.to_str().ok()?;
+        Some(value.split(';').next()?.to_string())
+    }
+
+    fn run_visits(store: impl SessionStore) {
+        let sessions = Sessions::new(store).with_key(Key::new(&[1u8; 32]).unwrap());
+        let handler = visits.layer(&sessions);
+
+        let response = handler.call(request(None));
+        assert_eq!(response.body().as_deref(), Some(&b"1"[..]));
+        let cookie = session_cookie(&response).unwrap();
+        assert!(response.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .contains("HttpOnly"));
+
+        // the cookie is refreshed along the session's time to live.
+        let response = handler.call(request(Some(&cookie)));
+        assert_eq!(response.body().as_deref(), Some(&b"2"[..]));
+        assert_eq!(session_cookie(&response).as_ref(), Some(&cookie));
+        assert!(response.headers()[header::SET_COOKIE]
+            .to_str()
+            .unwrap()
+            .contains("Max-Age=86400"));
+
+        // a forged id starts a new session.
+        let response = handler.call(request(Some("session=forged")));
+        assert_eq!(response.body().as_deref(), Some(&b"1"[..]));
+
+        let mut logout = request(Some(&cookie));
+        logout.head.target = Uri::from_static("/?logout");
+        let response = handler.call(logout);
+        assert_eq!(session_cookie(&response).as_deref(), Some("session="));
+
+        let response = handler.call(request(Some(&cookie)));
+        assert_eq!(response.body().as_deref(), Some(&b"1"[..]));
+    }
+
+    #[test]
+    fn keeps_sessions_across_requests() {
+        run_visits(MemoryStore::new());
+
+        let dir = tempfile::tempdir().unwrap();
+        run_visits(FileStore::new(dir.path().join("sessions")).unwrap());
+    }
+
+    #[test]
+    fn expires_sessions_and_refuses_foreign_ids() {
+        let store = MemoryStore::new();
+        let data = [(String::from("a"), serde_json::json!(1))].into();
+        store.save("id", &data, Duration::ZERO).unwrap();
+        assert_eq!(store.load("id").unwrap(), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::new(dir.path()).unwrap();
+        store.save("id", &data, Duration::from_secs(60)).unwrap();
+        assert_eq!(store.load("id").unwrap(), Some(data.clone()));
+        store
+            .save("../escape", &data, Duration::from_secs(60))
+            .unwrap();
+        assert!(!dir.path().join("../escape.json").exists());
+        assert_eq!(store.load("../id").unwrap(), None);
+
+        // without the layer there is no session to extract.
+        assert!(request(None).extract::<Session>().is_err());
+    }
+
+    struct FailingStore;
+
+    impl SessionStore for FailingStore {
+        fn load(&self, _: &str) -> SessionResult<Option<SessionData>> {
+            Ok(None)
+        }
+
+        fn save(&self, _: &str, _: &SessionData, _: Duration) -> SessionResult<()> {
+            Err(io::Error::other("disk full").into())
+        }
+
+        fn remove(&self, _: &str) -> SessionResult<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn answers_with_server_errors_when_sessions_fail_to_save() {
+        let handler = visits.layer(&Sessions::new(FailingStore));
+
+        let response = handler.call(request(None));
+        assert_eq!(*response.head().status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(session_cookie(&response), None);
+    }
+}
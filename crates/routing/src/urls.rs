@@ -0,0 +1,224 @@
+// Module implementing reverse routing, building the urls of named routes.
+
+use std::{fmt::Display, str::FromStr};
+
+use http::Uri;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+
+use crate::router::{RouterError, RouterResult};
+
+/// SEGMENT are the characters encoded in a path segment, see
+/// <https://url.spec.whatwg.org/#path-percent-encode-set>.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Urls maps the names of routes to their patterns, building their urls
+/// from parameters. A [`crate::router::Router`] keeps the names of its
+/// routes in one, which can be cloned out of it to be shared with
+/// templates.
+///
+/// # Example:
+///
+/// ```ignore
+/// router.route_named("todo.show", Method::GET, "/todos/:id", show)?;
+///
+/// let url = router.urls().url_for("todo.show", [("id", 7)])?;
+/// assert_eq!(url, "/todos/7");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Urls {
+    routes: Vec<(String, String)>,
+}
+
+impl Urls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// insert names the pattern, a name can only be given to one pattern.
+    pub fn insert(&mut self, name: &str, pattern: &str) -> RouterResult<()> {
+        match self.pattern(name) {
+            Some(existing) if existing == pattern => Ok(()),
+            Some(existing) => Err(RouterError::DuplicateName {
+                name: name.into(),
+                pattern: pattern.into(),
+                existing: existing.into(),
+            }),
+            None => {
+                self.routes.push((name.into(), pattern.into()));
+                Ok(())
+            }
+        }
+    }
+
+    pub fn pattern(&self, name: &str) -> Option<&str> {
+        self.routes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, pattern)| pattern.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.routes
+            .iter()
+            .map(|(name, pattern)| (name.as_str(), pattern.as_str()))
+    }
+
+    /// url_for builds the percent-encoded url of the named route from the
+    /// parameters of its pattern. Every required parameter must be given
+    /// and none the pattern does not have, optional ones can be left out
+    /// from the last.
+    pub fn url_for<I, K, V>(&self, name: &str, params: I) -> RouterResult<Uri>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Display,
+    {
+        let Some(pattern) = self.pattern(name) else {
+            return Err(RouterError::UnknownRoute(name.into()));
+        };
+
+        let mut params: Vec<(String, Option<String>)> = params
+            .into_iter()
+            .map(|(key, value)| (key.as_ref().to_string(), Some(value.to_string())))
+            .filter(|(_, value)| value.as_deref() != Some(""))
+            .collect();
+        let mut take = |param: &str| {
+            params
+                .iter_mut()
+                .find(|(key, _)| key == param)
+                .and_then(|(_, value)| value.take())
+        };
+
+        let mut url = String::new();
+        let mut skipped: Option<&str> = None;
+        for raw in pattern.split('/').skip(1) {
+            let (raw, optional) = match raw.strip_suffix('?') {
+                Some(raw) => (raw, true),
+                None => (raw, false),
+            };
+
+            let segment = if let Some(param) = raw.strip_prefix(':') {
+                take(param).map(|value| utf8_percent_encode(&value, SEGMENT).to_string())
+            } else if let Some(param) = raw.strip_prefix('*') {
+                take(param).map(|value| {
+                    let parts: Vec<String> = value
+                        .split('/')
+                        .map(|part| utf8_percent_encode(part, SEGMENT).to_string())
+                        .collect();
+                    parts.join("/")
+                })
+            } else {
+                Some(raw.to_string())
+            };
+
+            let param = raw.trim_start_matches([':', '*']);
+            match (segment, optional) {
+                (Some(_), true) if skipped.is_some() => {
+                    return Err(RouterError::MissingParam {
+                        route: name.into(),
+                        param: skipped.unwrap_or_default().into(),
+                    });
+                }
+                (Some(segment), _) => {
+                    url.push('/');
+                    url.push_str(&segment);
+                }
+                (None, true) => skipped = skipped.or(Some(param)),
+                (None, false) => {
+                    return Err(RouterError::MissingParam {
+                        route: name.into(),
+                        param: param.into(),
+                    });
+                }
+            }
+        }
+
+        if let Some((param, _)) = params.iter().find(|(_, value)| value.is_some()) {
+            return Err(RouterError::UnexpectedParam {
+                route: name.into(),
+                param: param.clone(),
+            });
+        }
+
+        if url.is_empty() {
+            url.push('/');
+        }
+        Uri::from_str(&url)
+            .map_err(|err| RouterError::InvalidPattern(pattern.into(), err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{router::RouterError, urls::Urls};
+
+    #[test]
+    fn builds_encoded_urls_from_params() {
+        let mut urls = Urls::new();
+        urls.insert("home", "/").unwrap();
+        urls.insert("todo.show", "/todos/:id").unwrap();
+        urls.insert("assets", "/assets/*path").unwrap();
+        urls.insert("archive", "/archive/:year?/:month?").unwrap();
+
+        let none: [(&str, &str); 0] = [];
+        assert_eq!(urls.url_for("home", none).unwrap(), "/");
+        assert_eq!(urls.url_for("todo.show", [("id", 7)]).unwrap(), "/todos/7");
+        assert_eq!(
+            urls.url_for("todo.show", [("id", "a b/c?")]).unwrap(),
+            "/todos/a%20b%2Fc%3F"
+        );
+        assert_eq!(
+            urls.url_for("assets", [("path", "css/site one.css")])
+                .unwrap(),
+            "/assets/css/site%20one.css"
+        );
+        assert_eq!(urls.url_for("archive", none).unwrap(), "/archive");
+        assert_eq!(
+            urls.url_for("archive", [("year", "2024")]).unwrap(),
+            "/archive/2024"
+        );
+
+        assert_eq!(
+            urls.url_for("todo.show", none),
+            Err(RouterError::MissingParam {
+                route: "todo.show".into(),
+                param: "id".into()
+            })
+        );
+        assert_eq!(
+            urls.url_for("todo.show", [("id", "1"), ("page", "2")]),
+            Err(RouterError::UnexpectedParam {
+                route: "todo.show".into(),
+                param: "page".into()
+            })
+        );
+        assert!(matches!(
+            urls.url_for("archive", [("month", "05")]),
+            Err(RouterError::MissingParam { .. })
+        ));
+        assert_eq!(
+            urls.url_for("todo.edit", none),
+            Err(RouterError::UnknownRoute("todo.edit".into()))
+        );
+        assert!(matches!(
+            urls.insert("home", "/index"),
+            Err(RouterError::DuplicateName { .. })
+        ));
+    }
+}
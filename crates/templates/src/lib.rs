@@ -1,2 +1,5 @@
 pub use minijinja;
 pub use tinytemplate;
+
+#[cfg(feature = "routing")]
+pub mod urls;
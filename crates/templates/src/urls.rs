@@ -0,0 +1,116 @@
+// Module implementing the url_for helpers of the template engines, building
+// urls from the routes named in a routing Router.
+
+use minijinja::{value::Kwargs, Environment, ErrorKind, Value};
+use routing::urls::Urls;
+use tinytemplate::{error::Error as TinyError, TinyTemplate};
+
+/// add_url_for registers the `url_for` function with the minijinja
+/// environment, taking the route name and its parameters as keyword
+/// arguments.
+///
+/// # Example:
+///
+/// ```ignore
+/// add_url_for(&mut env, router.urls().clone());
+///
+/// // <a href="{{ url_for('todo.show', id=todo.id) }}">
+/// ```
+pub fn add_url_for(env: &mut Environment<'_>, urls: Urls) {
+    env.add_function(
+        "url_for",
+        move |name: &str, kwargs: Kwargs| -> Result<String, minijinja::Error> {
+            let mut params = Vec::new();
+            for key in kwargs.args() {
+                let value: Value = kwargs.get(key)?;
+                params.push((key.to_string(), value.to_string()));
+            }
+
+            urls.url_for(name, params)
+                .map(|url| url.to_string())
+                .map_err(|err| minijinja::Error::new(ErrorKind::InvalidOperation, err.to_string()))
+        },
+    );
+}
+
+/// add_url_for_formatter registers the `url_for` formatter with the
+/// tinytemplate instance, formatting either a route name or an object with
+/// the route name under `route` and its parameters.
+///
+/// # Example:
+///
+/// ```ignore
+/// add_url_for_formatter(&mut tt, router.urls().clone());
+///
+/// // context: { "link": { "route": "todo.show", "id": 7 } }
+/// // <a href="{ link | url_for }">
+/// ```
+pub fn add_url_for_formatter(tt: &mut TinyTemplate<'_>, urls: Urls) {
+    tt.add_formatter("url_for", move |value, out| {
+        let failed = |msg: String| TinyError::GenericError { msg };
+
+        let (name, params) = match value {
+            serde_json::Value::String(name) => (name.as_str(), Vec::new()),
+            serde_json::Value::Object(fields) => {
+                let Some(name) = fields.get("route").and_then(|name| name.as_str()) else {
+                    return Err(failed(String::from("url_for requires a route name")));
+                };
+                let params: Vec<(String, String)> = fields
+                    .iter()
+                    .filter(|(key, _)| *key != "route")
+                    .map(|(key, value)| match value {
+                        serde_json::Value::String(value) => (key.clone(), value.clone()),
+                        value => (key.clone(), value.to_string()),
+                    })
+                    .collect();
+                (name, params)
+            }
+            _ => return Err(failed(String::from("url_for requires a route name"))),
+        };
+
+        let url = urls
+            .url_for(name, params)
+            .map_err(|err| failed(err.to_string()))?;
+        out.push_str(&url.to_string());
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use minijinja::Environment;
+    use routing::urls::Urls;
+    use tinytemplate::TinyTemplate;
+
+    use crate::urls::{add_url_for, add_url_for_formatter};
+
+    fn urls() -> Urls {
+        let mut urls = Urls::new();
+        urls.insert("todo.show", "/todos/:id").unwrap();
+        urls
+    }
+
+    #[test]
+    fn builds_urls_in_templates() {
+        let mut env = Environment::new();
+        add_url_for(&mut env, urls());
+        env.add_template("link", "{{ url_for('todo.show', id=id) }}")
+            .unwrap();
+        env.add_template("broken", "{{ url_for('todo.show') }}")
+            .unwrap();
+
+        let template = env.get_template("link").unwrap();
+        let rendered = template
+            .render(minijinja::context! { id => "a b" })
+            .unwrap();
+        assert_eq!(rendered, "/todos/a%20b");
+        assert!(env.get_template("broken").unwrap().render(()).is_err());
+
+        let mut tt = TinyTemplate::new();
+        add_url_for_formatter(&mut tt, urls());
+        tt.add_template("link", "{ link | url_for }").unwrap();
+
+        let context = serde_json::json!({ "link": { "route": "todo.show", "id": 7 } });
+        assert_eq!(tt.render("link", &context).unwrap(), "/todos/7");
+    }
+}